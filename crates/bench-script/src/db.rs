@@ -1,14 +1,14 @@
 use std::{
     collections::HashMap,
     fs::File,
-    io::{BufReader, BufWriter},
+    io::{BufReader, BufWriter, Write},
     path::Path,
     time::UNIX_EPOCH,
 };
 
 use serde::{Deserialize, Serialize};
 
-use crate::benchmark::{Benchmark, BenchmarkResult};
+use crate::benchmark::{Benchmark, BenchmarkResult, FailReason};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Entry {
@@ -35,3 +35,229 @@ pub fn read_from_file(
         .map(|entry| (entry.benchmark, entry.result))
         .collect())
 }
+
+/// Reads the results of all files via [`read_from_file`] and merges them in the given order, see
+/// [`merge_results`].
+pub fn read_from_files(
+    paths: impl IntoIterator<Item = impl AsRef<Path>>,
+) -> anyhow::Result<HashMap<Benchmark, BenchmarkResult>> {
+    let mut results = HashMap::new();
+    for path in paths {
+        merge_results(&mut results, read_from_file(path)?);
+    }
+    Ok(results)
+}
+
+/// Adds the results of `other` to `results`. On collision, the result of `other` is kept unless it
+/// is a failure while the previous result is a success, i.e. successes are preferred over failures
+/// and later results over earlier ones otherwise.
+pub fn merge_results(
+    results: &mut HashMap<Benchmark, BenchmarkResult>,
+    other: HashMap<Benchmark, BenchmarkResult>,
+) {
+    for (benchmark, result) in other {
+        match results.get(&benchmark) {
+            Some(previous) if previous.result.is_ok() && result.result.is_err() => {}
+            _ => {
+                results.insert(benchmark, result);
+            }
+        }
+    }
+}
+
+/// Row of the CSV output, i.e. a flattened [`Entry`]. The result fields are empty for failed
+/// benchmarks, `error` is empty for successful ones.
+#[derive(Debug, Serialize)]
+struct CsvRow<'a> {
+    benchmark: &'a str,
+    arch: &'a str,
+    mode: &'a str,
+    candidate_selection: &'a str,
+    rewriting_mode: &'a str,
+    rewriting_size_factor: usize,
+    t_total: u64,
+    error: String,
+    t_preopt: Option<u64>,
+    n_nodes: Option<u64>,
+    n_inputs: Option<u64>,
+    n_outputs: Option<u64>,
+    t_runner: Option<u64>,
+    n_nodes_pre_trim: Option<u64>,
+    t_trim: Option<u64>,
+    n_nodes_post_trim: Option<u64>,
+    t_extractor: Option<u64>,
+    rebuilt_ntk_cost: Option<f64>,
+    ntk_size: Option<u64>,
+    t_compile: Option<u64>,
+    t_cost: Option<f64>,
+    num_cells: Option<u64>,
+    num_instr: Option<u64>,
+    validation_success: Option<u8>,
+}
+
+impl<'a> CsvRow<'a> {
+    fn new(entry: &'a Entry) -> Self {
+        let Benchmark {
+            benchmark,
+            arch,
+            mode,
+            candidate_selection,
+            rewriting_mode,
+            rewriting_size_factor,
+        } = &entry.benchmark;
+        let result = entry.result.result.as_ref();
+        let field = |f: fn(&_) -> _| result.ok().map(f);
+        Self {
+            benchmark,
+            arch,
+            mode,
+            candidate_selection,
+            rewriting_mode,
+            rewriting_size_factor: *rewriting_size_factor,
+            t_total: entry.result.t_total,
+            error: match result {
+                Ok(_) => String::new(),
+                Err(FailReason::Infeasible) => "infeasible".to_string(),
+                Err(FailReason::Timeout) => "timeout".to_string(),
+                Err(FailReason::Error(err)) => format!("error: {err}"),
+                Err(FailReason::Other) => "other".to_string(),
+            },
+            t_preopt: field(|r| r.t_preopt),
+            n_nodes: field(|r| r.n_nodes),
+            n_inputs: field(|r| r.n_inputs),
+            n_outputs: field(|r| r.n_outputs),
+            t_runner: field(|r| r.t_runner),
+            n_nodes_pre_trim: field(|r| r.n_nodes_pre_trim),
+            t_trim: field(|r| r.t_trim),
+            n_nodes_post_trim: field(|r| r.n_nodes_post_trim),
+            t_extractor: field(|r| r.t_extractor),
+            rebuilt_ntk_cost: result.ok().map(|r| r.rebuilt_ntk_cost),
+            ntk_size: field(|r| r.ntk_size),
+            t_compile: field(|r| r.t_compile),
+            t_cost: result.ok().map(|r| r.t_cost),
+            num_cells: field(|r| r.num_cells),
+            num_instr: field(|r| r.num_instr),
+            validation_success: result.ok().map(|r| r.validation_success),
+        }
+    }
+}
+
+/// Writes the entries as CSV with one row per benchmark, see [`CsvRow`].
+pub fn write_csv(entries: &[Entry], writer: impl Write) -> anyhow::Result<()> {
+    let mut writer = csv::Writer::from_writer(writer);
+    for entry in entries {
+        writer.serialize(CsvRow::new(entry))?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+pub fn write_csv_to_file(path: impl AsRef<Path>, entries: &[Entry]) -> anyhow::Result<()> {
+    write_csv(entries, BufWriter::new(File::create(path)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::benchmark::BenchmarkCmdLineResult;
+
+    #[test]
+    fn merge() {
+        let benchmark = |benchmark: &str| Benchmark {
+            benchmark: benchmark.to_string(),
+            arch: "ambit".to_string(),
+            mode: "greedy".to_string(),
+            candidate_selection: "all".to_string(),
+            rewriting_mode: "none".to_string(),
+            rewriting_size_factor: 0,
+        };
+        let success = |num_instr| BenchmarkResult {
+            result: Ok(BenchmarkCmdLineResult {
+                num_instr,
+                ..Default::default()
+            }),
+            t_total: 0,
+            stdout: String::new(),
+            stderr: String::new(),
+        };
+        let timeout = || BenchmarkResult {
+            result: Err(FailReason::Timeout),
+            t_total: 0,
+            stdout: String::new(),
+            stderr: String::new(),
+        };
+        let num_instr = |result: &BenchmarkResult| result.result.as_ref().ok().map(|r| r.num_instr);
+
+        let mut results = HashMap::from([
+            (benchmark("fa"), success(1)),
+            (benchmark("fs"), success(1)),
+            (benchmark("mux"), timeout()),
+        ]);
+        merge_results(
+            &mut results,
+            HashMap::from([
+                (benchmark("fa"), timeout()),
+                (benchmark("fs"), success(2)),
+                (benchmark("mux"), success(2)),
+                (benchmark("ntk/dec.aig"), timeout()),
+            ]),
+        );
+        assert_eq!(results.len(), 4);
+        // a success is not replaced by a later failure
+        assert_eq!(num_instr(&results[&benchmark("fa")]), Some(1));
+        // later successes replace earlier ones and failures
+        assert_eq!(num_instr(&results[&benchmark("fs")]), Some(2));
+        assert_eq!(num_instr(&results[&benchmark("mux")]), Some(2));
+        assert!(results[&benchmark("ntk/dec.aig")].result.is_err());
+    }
+
+    #[test]
+    fn csv() {
+        let benchmark = |benchmark: &str| Benchmark {
+            benchmark: benchmark.to_string(),
+            arch: "ambit".to_string(),
+            mode: "greedy".to_string(),
+            candidate_selection: "all".to_string(),
+            rewriting_mode: "none".to_string(),
+            rewriting_size_factor: 0,
+        };
+        let result = |result| BenchmarkResult {
+            result,
+            t_total: 42,
+            stdout: String::new(),
+            stderr: String::new(),
+        };
+        let entries = [
+            Entry {
+                benchmark: benchmark("fa"),
+                result: result(Ok(BenchmarkCmdLineResult {
+                    n_nodes: 7,
+                    num_cells: 5,
+                    num_instr: 12,
+                    t_cost: 12.5,
+                    validation_success: 1,
+                    ..Default::default()
+                })),
+            },
+            Entry {
+                benchmark: benchmark("ntk/dec.aig"),
+                result: result(Err(FailReason::Timeout)),
+            },
+        ];
+        let mut out = Vec::new();
+        write_csv(&entries, &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let lines = out.lines().collect::<Vec<_>>();
+        assert_eq!(
+            lines,
+            [
+                "benchmark,arch,mode,candidate_selection,rewriting_mode,rewriting_size_factor,\
+                 t_total,error,t_preopt,n_nodes,n_inputs,n_outputs,t_runner,n_nodes_pre_trim,\
+                 t_trim,n_nodes_post_trim,t_extractor,rebuilt_ntk_cost,ntk_size,t_compile,t_cost,\
+                 num_cells,num_instr,validation_success",
+                "fa,ambit,greedy,all,none,0,42,,0,7,0,0,0,0,0,0,0,0.0,0,0,12.5,5,12,1",
+                "ntk/dec.aig,ambit,greedy,all,none,0,42,timeout,,,,,,,,,,,,,,,,",
+            ]
+        );
+    }
+}
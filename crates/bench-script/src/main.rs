@@ -1,15 +1,16 @@
 #![allow(dead_code)]
 
 use std::{
-    collections::HashMap,
     env::args,
     sync::{Arc, Mutex},
     time::Duration,
 };
 
+use lime_generic::compilation::{CandidateSelection, CompilationMode};
+
 use crate::{
     benchmark::{ARCHITECTURES, BENCHMARKS, Benchmark, FailReason, run_benchmark},
-    db::{Entry, read_from_file, write_to_file},
+    db::{Entry, read_from_files, write_csv_to_file, write_to_file},
 };
 
 mod benchmark;
@@ -17,12 +18,21 @@ mod db;
 
 #[tokio::main]
 async fn main() {
-    // keep all benchmarks in the argument
-    let previous = if let Some(path) = args().nth(1) {
-        read_from_file(path).unwrap()
-    } else {
-        HashMap::default()
-    };
+    let mut previous_paths = Vec::new();
+    let mut csv_path = None;
+    let mut args = args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--csv" {
+            csv_path = Some(args.next().expect("--csv requires a path"));
+        } else if arg == "--previous" {
+            previous_paths.push(args.next().expect("--previous requires a path"));
+        } else {
+            previous_paths.push(arg);
+        }
+    }
+
+    // keep all benchmarks of the previous results, merged in the order they were given
+    let previous = read_from_files(previous_paths).unwrap();
 
     let timeout = Duration::new(60 * 60, 0);
     let mut benchmarks = compiler_benchmarks();
@@ -51,17 +61,19 @@ async fn main() {
     for handle in handles {
         handle.await.unwrap();
     }
-    let entries = entries.lock().unwrap();
-    write_to_file(
-        &entries
-            .iter()
-            .map(|(benchmark, result)| Entry {
-                benchmark: benchmark.clone(),
-                result: result.clone(),
-            })
-            .collect::<Vec<_>>(),
-    )
-    .unwrap();
+    let entries = entries
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(benchmark, result)| Entry {
+            benchmark: benchmark.clone(),
+            result: result.clone(),
+        })
+        .collect::<Vec<_>>();
+    write_to_file(&entries).unwrap();
+    if let Some(path) = csv_path {
+        write_csv_to_file(path, &entries).unwrap();
+    }
 }
 
 fn simdram_benchmarks() -> Vec<Benchmark> {
@@ -112,8 +124,11 @@ fn compiler_benchmarks() -> Vec<Benchmark> {
     let mut benchmarks = Vec::new();
     for benchmark in BENCHMARKS {
         for arch in ARCHITECTURES {
-            for mode in ["greedy", "exhaustive"] {
-                for cand in ["all", "plim_compiler"] {
+            for mode in [CompilationMode::Greedy, CompilationMode::Exhaustive] {
+                for cand in [
+                    CandidateSelection::All,
+                    CandidateSelection::MIGBasedCompiler,
+                ] {
                     benchmarks.push(Benchmark {
                         benchmark: benchmark.to_string(),
                         arch: arch.to_string(),
@@ -7,6 +7,8 @@ use std::{
     sync::Arc,
 };
 
+use lime_generic::compilation::{CandidateSelection, CompilationMode};
+
 use crate::{
     benchmark::{
         ARCHITECTURES, BENCHMARKS, Benchmark, BenchmarkCmdLineResult, BenchmarkResult, FailReason,
@@ -21,15 +23,17 @@ mod utils;
 
 fn main() -> anyhow::Result<()> {
     let all_results = read_from_file(env::args().nth(1).unwrap())?;
+    // append a geomean row summarizing the improvement over the baseline per architecture
+    let summary = env::args().skip(2).any(|arg| arg == "--baseline");
     generate_table_codegen(&all_results);
-    generate_table_rewriting(&all_results);
+    generate_table_rewriting(&all_results, summary);
     Ok(())
 }
 
-fn generate_table_rewriting(all_results: &HashMap<Benchmark, BenchmarkResult>) {
+fn generate_table_rewriting(all_results: &HashMap<Benchmark, BenchmarkResult>, summary: bool) {
     let comparison = BenchmarkGroup {
-        candidate_selection: "all",
-        mode: "greedy",
+        candidate_selection: CandidateSelection::All.to_string(),
+        mode: CompilationMode::Greedy.to_string(),
         rewrite_size_factor: 0,
         rewrite_strategy: "none",
         title: "".to_string(),
@@ -37,6 +41,7 @@ fn generate_table_rewriting(all_results: &HashMap<Benchmark, BenchmarkResult>) {
     let metrics = [
         Metric::cost(),
         Metric::cost().improvement(&comparison),
+        Metric::normalized_cost(),
         // Metric::transformed_cost(),
         Metric::time(),
     ];
@@ -48,13 +53,13 @@ fn generate_table_rewriting(all_results: &HashMap<Benchmark, BenchmarkResult>) {
     ] {
         groups.push(BenchmarkGroup {
             title: format!("[{title}]"),
-            candidate_selection: "all",
-            mode: "greedy",
+            candidate_selection: CandidateSelection::All.to_string(),
+            mode: CompilationMode::Greedy.to_string(),
             rewrite_size_factor: 100,
             rewrite_strategy: rw_strat,
         });
     }
-    generate_table(all_results, &groups, &metrics);
+    generate_table(all_results, &groups, &metrics, summary);
 }
 
 fn generate_table_codegen(all_results: &HashMap<Benchmark, BenchmarkResult>) {
@@ -65,24 +70,31 @@ fn generate_table_codegen(all_results: &HashMap<Benchmark, BenchmarkResult>) {
         Metric::time(),
     ];
     let mut groups = Vec::new();
-    for (mode, mode_disp) in [("greedy", "Greedy"), ("exhaustive", "Exhaustive")] {
-        for (candsel, candsel_disp) in [("all", "All"), ("plim_compiler", "@PLiMCompiler")] {
+    for (mode, mode_disp) in [
+        (CompilationMode::Greedy, "Greedy"),
+        (CompilationMode::Exhaustive, "Exhaustive"),
+    ] {
+        for (candsel, candsel_disp) in [
+            (CandidateSelection::All, "All"),
+            (CandidateSelection::MIGBasedCompiler, "@PLiMCompiler"),
+        ] {
             groups.push(BenchmarkGroup {
                 title: format!("[{mode_disp} / {candsel_disp}]"),
-                candidate_selection: candsel,
-                mode,
+                candidate_selection: candsel.to_string(),
+                mode: mode.to_string(),
                 rewrite_size_factor: 0,
                 rewrite_strategy: "none",
             });
         }
     }
-    generate_table(all_results, &groups, &metrics);
+    generate_table(all_results, &groups, &metrics, false);
 }
 
 fn generate_table(
     all_results: &HashMap<Benchmark, BenchmarkResult>,
     groups: &[BenchmarkGroup],
     metrics: &[Metric],
+    summary: bool,
 ) {
     let benchmarks = BENCHMARKS;
     let num_metrics = metrics.len();
@@ -130,13 +142,15 @@ fn generate_table(
         }
         println!("table-header({first_line}{second_line}),");
 
+        // improvements per group and metric for the geomean row
+        let mut improvements = vec![vec![Vec::new(); num_metrics]; num_groups];
         for benchmark in benchmarks {
             let get_results = |group: &BenchmarkGroup| {
                 let benchmark = Benchmark {
                     benchmark: benchmark.to_string(),
                     arch: architecture.to_string(),
-                    mode: group.mode.to_string(),
-                    candidate_selection: group.candidate_selection.to_string(),
+                    mode: group.mode.clone(),
+                    candidate_selection: group.candidate_selection.clone(),
                     rewriting_mode: group.rewrite_strategy.to_string(),
                     rewriting_size_factor: group.rewrite_size_factor,
                 };
@@ -171,11 +185,13 @@ fn generate_table(
                         .filter_map(|(benchmark, result)| {
                             Some((benchmark, result, result.result.as_ref().ok()?))
                         })
-                        .map(|result| (metric.get)(&result.0, result.1, result.2, all_results))
+                        .filter_map(|result| {
+                            (metric.get)(&result.0, result.1, result.2, all_results)
+                        })
                         .min_by(|a, b| a.partial_cmp(b).unwrap())
                 })
                 .collect();
-            for group in groups {
+            for (group_idx, group) in groups.iter().enumerate() {
                 let (benchmark, result) = get_results(group);
                 match &result.result {
                     Ok(data) => {
@@ -183,9 +199,17 @@ fn generate_table(
                             panic!("validation failed for {benchmark:?}")
                         }
                         for (i, metric) in metrics.iter().enumerate() {
-                            let min = min_metrics[i].as_ref().unwrap();
-                            let value = (metric.get)(&benchmark, result, data, all_results);
-                            if metric.highlight && value <= *min {
+                            let Some(value) = (metric.get)(&benchmark, result, data, all_results)
+                            else {
+                                print!("[], ");
+                                continue;
+                            };
+                            if let MetricValue::Percentage(improvement) = value {
+                                improvements[group_idx][i].push(improvement);
+                            }
+                            if metric.highlight
+                                && min_metrics[i].as_ref().is_some_and(|min| value <= *min)
+                            {
                                 print!("strong({value}), ")
                             } else {
                                 print!("{value}, ")
@@ -222,6 +246,16 @@ fn generate_table(
             }
             println!()
         }
+        if summary {
+            print!("table.cell(colspan: 2, strong[geomean]), ");
+            for metric_improvements in improvements.iter().flatten() {
+                match geomean_improvement(metric_improvements) {
+                    Some(improvement) => print!("{}, ", MetricValue::Percentage(improvement)),
+                    None => print!("[], "),
+                }
+            }
+            println!()
+        }
         println!(")");
     }
 }
@@ -229,12 +263,14 @@ fn generate_table(
 #[derive(Clone, Debug)]
 struct BenchmarkGroup {
     title: String,
-    mode: &'static str,
-    candidate_selection: &'static str,
+    mode: String,
+    candidate_selection: String,
     rewrite_strategy: &'static str,
     rewrite_size_factor: usize,
 }
 
+/// A column of the table. `get` returns `None` if the value can not be computed for a result, e.g.
+/// because another result it is compared to is missing or failed.
 #[derive(Clone)]
 struct Metric {
     get: Arc<
@@ -243,7 +279,7 @@ struct Metric {
             &BenchmarkResult,
             &BenchmarkCmdLineResult,
             &HashMap<Benchmark, BenchmarkResult>,
-        ) -> MetricValue,
+        ) -> Option<MetricValue>,
     >,
     title: &'static str,
     highlight: bool,
@@ -252,39 +288,57 @@ struct Metric {
 impl Metric {
     fn cost() -> Metric {
         Metric {
-            get: Arc::new(|_, _, cmd, _| MetricValue::Float(cmd.t_cost)),
+            get: Arc::new(|_, _, cmd, _| Some(MetricValue::Float(cmd.t_cost))),
             title: "[cost]",
             highlight: true,
         }
     }
     fn utilization() -> Metric {
         Metric {
-            get: Arc::new(|_, _, cmd, _| MetricValue::Int(cmd.num_cells)),
+            get: Arc::new(|_, _, cmd, _| Some(MetricValue::Int(cmd.num_cells))),
             title: "$\"#\"C$",
             highlight: true,
         }
     }
     fn instructions() -> Metric {
         Metric {
-            get: Arc::new(|_, _, cmd, _| MetricValue::Int(cmd.num_instr)),
+            get: Arc::new(|_, _, cmd, _| Some(MetricValue::Int(cmd.num_instr))),
             title: "$\"#\"I$",
             highlight: true,
         }
     }
     fn time() -> Metric {
         Metric {
-            get: Arc::new(|_, result, _, _| MetricValue::TimeMs(result.t_total)),
+            get: Arc::new(|_, result, _, _| Some(MetricValue::TimeMs(result.t_total))),
             title: "$t$",
             highlight: false,
         }
     }
     fn transformed_cost() -> Metric {
         Metric {
-            get: Arc::new(|_, _, cmd, _| MetricValue::Float(cmd.rebuilt_ntk_cost)),
+            get: Arc::new(|_, _, cmd, _| Some(MetricValue::Float(cmd.rebuilt_ntk_cost))),
             highlight: true,
             title: "[ntkcost]",
         }
     }
+    /// Cost relative to the cost obtained without rewriting for the same benchmark and
+    /// architecture. Unlike the raw cost, this is comparable across architectures. Not available if
+    /// the baseline is missing or failed.
+    fn normalized_cost() -> Metric {
+        Metric {
+            get: Arc::new(|benchmark, _, cmd, results| {
+                let baseline = Benchmark {
+                    rewriting_mode: "none".to_string(),
+                    rewriting_size_factor: 0,
+                    ..benchmark.clone()
+                };
+                let baseline = results.get(&baseline)?.result.as_ref().ok()?;
+                Some(MetricValue::Ratio(cmd.t_cost / baseline.t_cost))
+            }),
+            title: "[norm. cost]",
+            highlight: true,
+        }
+    }
     fn improvement(&self, comparison: &BenchmarkGroup) -> Metric {
         let comparison = comparison.clone();
         let s = self.clone();
@@ -293,20 +347,16 @@ impl Metric {
                 let other = Benchmark {
                     arch: benchmark.arch.clone(),
                     benchmark: benchmark.benchmark.clone(),
-                    candidate_selection: comparison.candidate_selection.to_string(),
-                    mode: comparison.mode.to_string(),
+                    candidate_selection: comparison.candidate_selection.clone(),
+                    mode: comparison.mode.clone(),
                     rewriting_mode: comparison.rewrite_strategy.to_string(),
                     rewriting_size_factor: comparison.rewrite_size_factor,
                 };
-                let other_res = results.get(&other).unwrap();
-                let other_metric = (s.get)(
-                    &other,
-                    &other_res,
-                    other_res.result.as_ref().unwrap(),
-                    results,
-                );
-                let self_metric = (s.get)(benchmark, res, res_cmd, results);
-                self_metric.improvement(&other_metric)
+                let other_res = results.get(&other)?;
+                let other_metric =
+                    (s.get)(&other, other_res, other_res.result.as_ref().ok()?, results)?;
+                let self_metric = (s.get)(benchmark, res, res_cmd, results)?;
+                Some(self_metric.improvement(&other_metric))
             }),
             title: "[impr.]",
             highlight: false,
@@ -320,6 +370,7 @@ enum MetricValue {
     Float(f64),
     Int(u64),
     Percentage(f64),
+    Ratio(f64),
 }
 
 impl MetricValue {
@@ -332,11 +383,30 @@ impl MetricValue {
             Self::Float(f) => *f,
             Self::Int(i) => *i as f64,
             Self::Percentage(p) => *p,
+            Self::Ratio(r) => *r,
             Self::TimeMs(t) => *t as f64,
         }
     }
 }
 
+/// Computes the geometric mean of the given improvements (in percent) by averaging the underlying
+/// cost ratios. Returns `None` if there are no improvements or a ratio is not positive.
+fn geomean_improvement(improvements: &[f64]) -> Option<f64> {
+    if improvements.is_empty() {
+        return None;
+    }
+    let mut log_sum = 0.0;
+    for improvement in improvements {
+        let ratio = 1.0 - improvement / 100.0;
+        if ratio <= 0.0 {
+            return None;
+        }
+        log_sum += ratio.ln();
+    }
+    let ratio = (log_sum / improvements.len() as f64).exp();
+    Some((1.0 - ratio) * 100.0)
+}
+
 impl Display for MetricValue {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result {
         match self {
@@ -344,6 +414,60 @@ impl Display for MetricValue {
             Self::TimeMs(t) => write!(f, "${:.1}s$", *t as f64 / 1000.0),
             Self::Float(v) => write!(f, "${:}$", (v * 10.0).round() / 10.0),
             Self::Percentage(v) => write!(f, "${:.1}%$", v),
+            Self::Ratio(v) => write!(f, "${:.2}$", v),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn geomean() {
+        let assert_close = |a: Option<f64>, b: f64| assert!((a.unwrap() - b).abs() < 1e-9);
+        assert_close(geomean_improvement(&[50.0, -100.0]), 0.0);
+        assert_close(geomean_improvement(&[75.0, 0.0]), 50.0);
+        assert_close(geomean_improvement(&[20.0, 20.0, 20.0]), 20.0);
+        assert_eq!(geomean_improvement(&[]), None);
+        assert_eq!(geomean_improvement(&[100.0]), None);
+    }
+
+    #[test]
+    fn normalized_cost() {
+        let benchmark = |arch: &str, rewriting_mode: &str, rewriting_size_factor| Benchmark {
+            benchmark: "adder".to_string(),
+            arch: arch.to_string(),
+            mode: "greedy".to_string(),
+            candidate_selection: "all".to_string(),
+            rewriting_mode: rewriting_mode.to_string(),
+            rewriting_size_factor,
+        };
+        let result = |t_cost| BenchmarkResult {
+            result: Ok(BenchmarkCmdLineResult {
+                t_cost,
+                ..Default::default()
+            }),
+            t_total: 0,
+            stdout: String::new(),
+            stderr: String::new(),
+        };
+        let results = HashMap::from([
+            (benchmark("ambit", "none", 0), result(200.0)),
+            (benchmark("ambit", "greedy", 100), result(150.0)),
+            (benchmark("felix", "none", 0), result(4.0)),
+            (benchmark("felix", "greedy", 100), result(3.0)),
+        ]);
+        let metric = Metric::normalized_cost();
+        let get = |benchmark: Benchmark| {
+            let res = &results[&benchmark];
+            (metric.get)(&benchmark, res, res.result.as_ref().unwrap(), &results)
+        };
+        assert!(get(benchmark("ambit", "none", 0)) == Some(MetricValue::Ratio(1.0)));
+        assert!(get(benchmark("ambit", "greedy", 100)) == Some(MetricValue::Ratio(0.75)));
+        assert!(get(benchmark("felix", "greedy", 100)) == Some(MetricValue::Ratio(0.75)));
+        // without a baseline, the normalized cost is not available
+        let missing = benchmark("plim", "greedy", 100);
+        assert!((metric.get)(&missing, &result(3.0), &Default::default(), &results).is_none());
+    }
+}
@@ -0,0 +1,63 @@
+//! Compile check for the `no_std` core of `lime-generic-def`.
+//!
+//! This crate depends on `lime-generic-def` without its default `std` feature and exercises the
+//! fundamental definition types. Building it (`cargo build -p lime-generic-def-no-std`) fails if
+//! any of them starts to require `std`.
+#![no_std]
+
+extern crate alloc;
+
+use alloc::borrow::Cow;
+
+use lime_generic_def::{
+    BoolSet, Cell, CellIndex, CellPat, CellType, Function, Gate, InputIndices, PatBase, set::Set,
+};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum EmbeddedCellType {
+    Constant,
+    Row,
+}
+
+impl CellType for EmbeddedCellType {
+    const CONSTANT: Self = Self::Constant;
+
+    fn count(self) -> Option<CellIndex> {
+        match self {
+            Self::Constant => Some(2),
+            Self::Row => Some(16),
+        }
+    }
+
+    fn name(self) -> Cow<'static, str> {
+        match self {
+            Self::Constant => "bool",
+            Self::Row => "R",
+        }
+        .into()
+    }
+}
+
+/// Evaluates the given function on `inputs` if `pattern` matches `cell`, returning the set of
+/// possible outputs.
+pub fn evaluate(
+    function: Function,
+    pattern: CellPat<EmbeddedCellType>,
+    cell: Cell<EmbeddedCellType>,
+    inverted: InputIndices,
+    inputs: &[bool],
+) -> BoolSet {
+    if !pattern.matches(&cell) {
+        return BoolSet::None;
+    }
+    let mut evaluation = function.evaluate(inputs.len());
+    for (i, &input) in inputs.iter().enumerate() {
+        evaluation.add(i, input ^ inverted.contains(&i));
+    }
+    BoolSet::None.insert_optional(evaluation.evaluate())
+}
+
+/// Parses a gate name as used in architecture definitions.
+pub fn parse_gate(name: &str) -> Option<Gate> {
+    name.parse().ok()
+}
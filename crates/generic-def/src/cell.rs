@@ -1,6 +1,8 @@
-use std::borrow::Cow;
-use std::fmt::{self, Debug, Display, Formatter};
-use std::hash::Hash;
+use alloc::borrow::Cow;
+use core::fmt::{self, Debug, Display, Formatter};
+use core::hash::Hash;
+use core::iter;
+use core::ops::Range;
 
 use derive_more::From;
 use itertools::Either;
@@ -28,6 +30,12 @@ pub trait CellType: 'static + Copy + Debug + PartialEq + Eq + Hash + PartialOrd
     fn constant(value: bool) -> Cell<Self> {
         Cell::new(Self::CONSTANT, value as CellIndex)
     }
+    /// Cells reserved for spilling values held by cells of this type, given as a cell type and a
+    /// range of indices of that type. Spills prefer these cells over other free cells. `None` if
+    /// no such region is designated.
+    fn spill_region(self) -> Option<(Self, Range<CellIndex>)> {
+        None
+    }
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
@@ -63,6 +71,36 @@ impl<CT> Cell<CT> {
     }
 }
 
+impl<CT: CellType> Cell<CT> {
+    /// Name used for the constant cell type by [`Cell::display_unambiguous`].
+    pub const UNAMBIGUOUS_CONSTANT_NAME: &'static str = "const";
+
+    /// Returns a [`Display`]able version of this cell that renders constants as `const[0]` and
+    /// `const[1]` instead of `false` and `true`, so they can not be confused with cell types of the
+    /// same name when parsing them back via [`Cell::parse_unambiguous`].
+    pub fn display_unambiguous(self) -> UnambiguousCell<CT> {
+        UnambiguousCell(self)
+    }
+
+    /// Parses a cell in the format produced by [`Cell::display_unambiguous`]. Non-constant cell
+    /// types are looked up by their name in `types`.
+    pub fn parse_unambiguous(s: &str, types: impl IntoIterator<Item = CT>) -> Option<Self> {
+        let (name, idx) = s.trim().strip_suffix(']')?.split_once('[')?;
+        let idx = idx.parse::<CellIndex>().ok()?;
+        let typ = if name == Self::UNAMBIGUOUS_CONSTANT_NAME {
+            CT::CONSTANT
+        } else {
+            types
+                .into_iter()
+                .find(|typ| *typ != CT::CONSTANT && typ.name() == name)?
+        };
+        if typ.count().is_some_and(|count| idx >= count) {
+            return None;
+        }
+        Some(Cell::new(typ, idx))
+    }
+}
+
 impl<CT: CellType> Display for Cell<CT> {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         if self.0 == CT::CONSTANT {
@@ -74,6 +112,22 @@ impl<CT: CellType> Display for Cell<CT> {
     }
 }
 
+/// See [`Cell::display_unambiguous`].
+#[derive(Debug, Copy, Clone)]
+pub struct UnambiguousCell<CT>(Cell<CT>);
+
+impl<CT: CellType> Display for UnambiguousCell<CT> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let Cell(typ, idx) = self.0;
+        if typ == CT::CONSTANT {
+            write!(f, "{}", Cell::<CT>::UNAMBIGUOUS_CONSTANT_NAME)?;
+            display_index(f, idx)
+        } else {
+            Display::fmt(&self.0, f)
+        }
+    }
+}
+
 #[doc(hidden)]
 pub fn __display_cell_type<T: CellType>(typ: T, f: &mut Formatter<'_>) -> Result<(), fmt::Error> {
     write!(f, "{}", typ.name())
@@ -138,6 +192,19 @@ impl<CT: CellType> PatBase for CellPat<CT> {
             CellPat::Cell(self_cell) => self_cell == *cell,
         }
     }
+
+    fn intersect(&self, other: &Self) -> Option<Self> {
+        match (*self, *other) {
+            (CellPat::Type(typ), CellPat::Type(other_typ)) => (typ == other_typ).then_some(*self),
+            (CellPat::Type(typ), CellPat::Cell(cell))
+            | (CellPat::Cell(cell), CellPat::Type(typ)) => {
+                (cell.typ() == typ).then_some(CellPat::Cell(cell))
+            }
+            (CellPat::Cell(cell), CellPat::Cell(other_cell)) => {
+                (cell == other_cell).then_some(*self)
+            }
+        }
+    }
 }
 
 impl<CT: CellType> CellPat<CT> {
@@ -150,7 +217,16 @@ impl<CT: CellType> CellPat<CT> {
         }
     }
     pub fn is_subset_of(self, pat: CellPat<CT>) -> bool {
-        !self.is_superset_of(pat)
+        pat.is_superset_of(self)
+    }
+    /// Returns the cells matched by this pattern, i.e. the cell itself or all cells of the type
+    /// ordered by index. Types with an infinite amount of cells match infinitely many cells, hence
+    /// the iteration has to be bounded by the caller in that case, e.g. using [`Iterator::take`].
+    pub fn matching_cells(self) -> impl Iterator<Item = Cell<CT>> {
+        match self {
+            CellPat::Cell(cell) => Either::Left(iter::once(cell)),
+            CellPat::Type(typ) => Either::Right(typ.cell_iter()),
+        }
     }
     pub fn get_constant(self, value: bool) -> Option<Cell<CT>> {
         match self {
@@ -160,3 +236,63 @@ impl<CT: CellType> CellPat<CT> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use alloc::{format, string::ToString, vec::Vec};
+
+    use crate::tests::DummyCellType;
+
+    use super::*;
+
+    #[test]
+    fn unambiguous_roundtrip() {
+        let types = [DummyCellType::A, DummyCellType::B];
+        for value in [false, true] {
+            let cell = DummyCellType::constant(value);
+            let displayed = cell.display_unambiguous().to_string();
+            assert_eq!(displayed, format!("const[{}]", value as u8));
+            assert_eq!(Cell::parse_unambiguous(&displayed, types), Some(cell));
+        }
+        let cell = Cell::new(DummyCellType::A, 3);
+        assert_eq!(cell.display_unambiguous().to_string(), "A[3]");
+        assert_eq!(Cell::parse_unambiguous("A[3]", types), Some(cell));
+        assert_eq!(Cell::parse_unambiguous("A[4]", types), None);
+        assert_eq!(Cell::parse_unambiguous("const[2]", types), None);
+    }
+
+    #[test]
+    fn subset_superset() {
+        let a = CellPat::Type(DummyCellType::A);
+        let b = CellPat::Type(DummyCellType::B);
+        let a1 = CellPat::Cell(Cell::new(DummyCellType::A, 1));
+        let a2 = CellPat::Cell(Cell::new(DummyCellType::A, 2));
+
+        // type/type
+        assert!(a.is_subset_of(a) && a.is_superset_of(a));
+        assert!(!a.is_subset_of(b) && !a.is_superset_of(b));
+        // type/cell
+        assert!(a1.is_subset_of(a) && a.is_superset_of(a1));
+        assert!(!a.is_subset_of(a1) && !a1.is_superset_of(a));
+        // cell/cell
+        assert!(a1.is_subset_of(a1));
+        assert!(!a1.is_subset_of(a2) && !a1.is_superset_of(a2));
+    }
+
+    #[test]
+    fn matching_cells() {
+        let a = CellPat::Type(DummyCellType::A);
+        let a1 = Cell::new(DummyCellType::A, 1);
+        assert_eq!(
+            a.matching_cells().collect::<Vec<_>>(),
+            (0..4)
+                .map(|idx| Cell::new(DummyCellType::A, idx))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(CellPat::Cell(a1).matching_cells().collect::<Vec<_>>(), [a1]);
+        // infinite types yield as many cells as requested
+        let b = CellPat::Type(DummyCellType::B);
+        assert_eq!(b.matching_cells().take(100).count(), 100);
+        assert!(b.matching_cells().take(100).all(|cell| b.matches(&cell)));
+    }
+}
@@ -63,14 +63,14 @@ impl EvaluationMethods for AndEval {
         }
     }
 
-    fn add(&mut self, value: bool) {
+    fn add(&mut self, _input: usize, value: bool) {
         self.count += 1;
         if !value {
             self.value = Some(false);
         }
     }
 
-    fn add_unknown(&mut self) {
+    fn add_unknown(&mut self, _input: usize) {
         self.count += 1;
         if self.value == Some(true) {
             self.value = None;
@@ -33,11 +33,11 @@ impl EvaluationMethods for ConstEval {
         None
     }
 
-    fn add(&mut self, _value: bool) {
+    fn add(&mut self, _input: usize, _value: bool) {
         self.count += 1
     }
 
-    fn add_unknown(&mut self) {
+    fn add_unknown(&mut self, _input: usize) {
         self.count += 1
     }
 
@@ -2,24 +2,84 @@ use crate::{BoolHint, func::EvaluationMethods};
 
 #[derive(Debug, Clone, Copy)]
 pub struct MajEval {
+    /// votes for false, true and unknown values
     nums: [u8; 3],
+    /// number of values added so far
+    count: u8,
+    /// index of the input whose value resolves ties, see [`MajEval::with_tie_breaker`]
+    tie_breaker: Option<u8>,
+    /// result of [`MajEval::compute_evaluation`], updated whenever a value is added
+    value: Option<bool>,
 }
 
 impl MajEval {
     pub fn new() -> Self {
-        Self { nums: [0, 0, 0] }
+        Self {
+            nums: [0, 0, 0],
+            count: 0,
+            tie_breaker: None,
+            value: None,
+        }
+    }
+
+    /// Majority with an even arity in which the input with the given index resolves ties. The
+    /// tie-breaker is counted twice, making the total number of votes odd again.
+    pub fn with_tie_breaker(input: usize) -> Self {
+        let input = u8::try_from(input).expect("tie-breaker index should fit into a u8");
+        Self {
+            tie_breaker: Some(input),
+            ..Self::new()
+        }
+    }
+
+    /// Total number of votes of all added values.
+    fn votes(&self) -> usize {
+        self.nums.iter().sum::<u8>().into()
+    }
+
+    /// Total number of votes of all values for the given arity.
+    fn total_votes(&self, arity: usize) -> usize {
+        arity + usize::from(self.tie_breaker.is_some())
+    }
+
+    fn add_vote(&mut self, input: usize, idx: usize) {
+        let weight = if self
+            .tie_breaker
+            .is_some_and(|tie_breaker| input == tie_breaker.into())
+        {
+            2
+        } else {
+            1
+        };
+        self.nums[idx] += weight;
+        self.count += 1;
+        self.value = self.compute_evaluation();
+    }
+
+    fn compute_evaluation(&self) -> Option<bool> {
+        if self.votes() % 2 != 1 {
+            None
+        } else {
+            let value = self.nums[1] > self.nums[0];
+            let diff = self.nums[value as usize] - self.nums[!value as usize];
+            if diff <= self.nums[2] {
+                None
+            } else {
+                Some(value)
+            }
+        }
     }
 }
 
 impl EvaluationMethods for MajEval {
     fn hint(&self, arity: usize, target: bool) -> Option<BoolHint> {
         let num_target = usize::from(self.nums[target as usize]);
-        let required_target = arity.div_ceil(2);
+        let required_target = self.total_votes(arity).div_ceil(2);
         if num_target >= required_target {
             return Some(BoolHint::Any);
         }
         let missing_values = required_target - num_target;
-        let leftover = arity - self.count();
+        let leftover = self.total_votes(arity) - self.votes();
         if leftover < missing_values {
             None
         } else if missing_values == leftover {
@@ -30,7 +90,7 @@ impl EvaluationMethods for MajEval {
     }
 
     fn hint_id(&self, arity: usize, inverted: bool) -> Option<BoolHint> {
-        if inverted || self.nums[2] != 0 {
+        if inverted || self.nums[2] != 0 || self.tie_breaker.is_some() {
             return None;
         }
         if self.nums[0] == self.nums[1] {
@@ -66,30 +126,20 @@ impl EvaluationMethods for MajEval {
         }
     }
 
-    fn add(&mut self, value: bool) {
-        self.nums[value as usize] += 1
+    fn add(&mut self, input: usize, value: bool) {
+        self.add_vote(input, value as usize);
     }
 
-    fn add_unknown(&mut self) {
-        self.nums[2] += 1;
+    fn add_unknown(&mut self, input: usize) {
+        self.add_vote(input, 2);
     }
 
     fn evaluate(&self) -> Option<bool> {
-        if self.count() % 2 != 1 {
-            None
-        } else {
-            let value = self.nums[1] > self.nums[0];
-            let diff = self.nums[value as usize] - self.nums[!value as usize];
-            if diff <= self.nums[2] {
-                None
-            } else {
-                Some(value)
-            }
-        }
+        self.value
     }
 
     fn count(&self) -> usize {
-        self.nums.iter().sum::<u8>().into()
+        self.count.into()
     }
 }
 
@@ -98,3 +148,66 @@ impl Default for MajEval {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cached_evaluation() {
+        for values in [
+            &[Some(true), Some(false), Some(true)] as &[Option<bool>],
+            &[Some(false), None, Some(false), Some(true), Some(false)],
+            &[None, Some(true), Some(true), Some(true), None],
+            &[Some(true), Some(false), None, None, Some(true)],
+        ] {
+            let mut eval = MajEval::new();
+            assert_eq!(eval.evaluate(), eval.compute_evaluation());
+            for (input, value) in values.iter().enumerate() {
+                match value {
+                    Some(value) => eval.add(input, *value),
+                    None => eval.add_unknown(input),
+                }
+                assert_eq!(eval.evaluate(), eval.compute_evaluation());
+            }
+        }
+    }
+
+    #[test]
+    fn tie_breaker() {
+        for (values, result) in [
+            // 2-2 ties are resolved by the last input
+            ([true, true, false, false], false),
+            ([true, false, false, true], true),
+            ([false, true, false, true], true),
+            ([true, true, true, false], true),
+            ([false, false, false, true], false),
+        ] {
+            let mut eval = MajEval::with_tie_breaker(3);
+            assert_eq!(eval.hint(4, result), Some(BoolHint::Prefer(result)));
+            for (input, value) in values.into_iter().enumerate() {
+                eval.add(input, value);
+            }
+            assert_eq!(eval.count(), 4);
+            assert_eq!(eval.evaluate(), Some(result));
+        }
+
+        // after a 2-1 split, only the tie-breaker can still decide the result
+        let mut eval = MajEval::with_tie_breaker(3);
+        for (input, value) in [true, true, false].into_iter().enumerate() {
+            eval.add(input, value);
+        }
+        assert_eq!(eval.hint(4, false), Some(BoolHint::Require(false)));
+        assert_eq!(eval.hint(4, true), Some(BoolHint::Prefer(true)));
+        eval.add(3, false);
+        assert_eq!(eval.evaluate(), Some(false));
+
+        // the tie-breaker is determined by the index of the input, not by the order of adding
+        let mut eval = MajEval::with_tie_breaker(3);
+        eval.add(3, true);
+        eval.add(0, false);
+        eval.add(1, true);
+        eval.add(2, false);
+        assert_eq!(eval.evaluate(), Some(true));
+    }
+}
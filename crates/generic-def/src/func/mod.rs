@@ -3,15 +3,18 @@ mod constant;
 mod maj;
 mod xor;
 
-use std::fmt::Display;
+use alloc::vec::Vec;
+use core::fmt::Display;
 
 use delegate::delegate;
+#[cfg(feature = "std")]
 use eggmock::GateFunction;
 use strum::EnumString;
 
+pub use self::maj::MajEval;
 use crate::{
     BoolHint, display_maybe_inverted,
-    func::{and::AndEval, constant::ConstEval, maj::MajEval, xor::XorEval},
+    func::{and::AndEval, constant::ConstEval, xor::XorEval},
 };
 
 // Gate type without input/output inverters
@@ -35,6 +38,7 @@ impl Gate {
         }
     }
 
+    #[cfg(feature = "std")]
     pub fn gate_function(&self) -> Option<GateFunction> {
         match self {
             Self::And => Some(GateFunction::And),
@@ -46,7 +50,7 @@ impl Gate {
 }
 
 impl Display for Gate {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         match self {
             Self::And => write!(f, "and"),
             Self::Maj => write!(f, "maj"),
@@ -61,20 +65,40 @@ impl Display for Gate {
 pub struct Function {
     pub inverted: bool,
     pub gate: Gate,
+    /// index of the input resolving ties of a majority with an even arity, see
+    /// [`MajEval::with_tie_breaker`]. Without one, ties do not evaluate to any value.
+    pub tie_breaker: Option<u8>,
 }
 
 impl Function {
+    /// Evaluates the function for the given number of inputs.
     pub fn evaluate(self, arity: usize) -> FunctionEvaluation {
+        let gate = match (self.gate, self.tie_breaker) {
+            (Gate::Maj, Some(tie_breaker)) => {
+                GateEvaluation::Maj(MajEval::with_tie_breaker(tie_breaker.into()))
+            }
+            (gate, _) => gate.evaluate(),
+        };
         FunctionEvaluation {
             inverted: self.inverted,
-            gate: self.gate.evaluate(),
+            gate,
             arity,
         }
     }
+
+    /// Returns the inputs of the gate computing this function without a tie-breaker, i.e. the
+    /// inputs with the tie-breaker repeated, as it is counted twice.
+    pub fn untied_inputs<T: Copy>(self, inputs: &[T]) -> Vec<T> {
+        let mut inputs = inputs.to_vec();
+        if let Some(tie_breaker) = self.tie_breaker {
+            inputs.push(inputs[usize::from(tie_breaker)]);
+        }
+        inputs
+    }
 }
 
 impl Display for Function {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         display_maybe_inverted(f, self.inverted)?;
         write!(f, "{}", self.gate)
     }
@@ -84,8 +108,8 @@ trait EvaluationMethods {
     fn hint(&self, arity: usize, target: bool) -> Option<BoolHint>;
     fn hint_id(&self, arity: usize, inverted: bool) -> Option<BoolHint>;
     fn id_inverted(&self) -> Option<bool>;
-    fn add(&mut self, value: bool);
-    fn add_unknown(&mut self);
+    fn add(&mut self, input: usize, value: bool);
+    fn add_unknown(&mut self, input: usize);
     fn count(&self) -> usize;
     fn evaluate(&self) -> Option<bool>;
 }
@@ -127,13 +151,14 @@ impl FunctionEvaluation {
             .id_inverted()
             .map(|inverted| inverted ^ self.inverted)
     }
-    pub fn add(&mut self, value: bool) {
-        assert!(self.count() < self.arity);
-        self.gate.add(value);
+    /// Adds the value of the input with the given index, inputs may be added in any order.
+    pub fn add(&mut self, input: usize, value: bool) {
+        assert!(self.count() < self.arity && input < self.arity);
+        self.gate.add(input, value);
     }
-    pub fn add_unknown(&mut self) {
-        assert!(self.count() < self.arity);
-        self.gate.add_unknown();
+    pub fn add_unknown(&mut self, input: usize) {
+        assert!(self.count() < self.arity && input < self.arity);
+        self.gate.add_unknown(input);
     }
     pub fn evaluate(&self) -> Option<bool> {
         self.gate.evaluate().map(|v| v ^ self.inverted)
@@ -162,8 +187,8 @@ impl GateEvaluation {
             pub fn hint(&self, arity: usize, target: bool) -> Option<BoolHint>;
             pub fn hint_id(&self, arity: usize, inverted: bool) -> Option<BoolHint>;
             pub fn id_inverted(&self) -> Option<bool>;
-            pub fn add(&mut self, value: bool);
-            pub fn add_unknown(&mut self);
+            pub fn add(&mut self, input: usize, value: bool);
+            pub fn add_unknown(&mut self, input: usize);
             pub fn evaluate(&self) -> Option<bool>;
             pub fn count(&self) -> usize;
         }
@@ -185,13 +210,42 @@ mod tests {
             let mut eval = Function {
                 gate: Gate::Maj,
                 inverted: false,
+                tie_breaker: None,
             }
             .evaluate(values.len());
-            values.iter().for_each(|value| eval.add(*value));
+            for (i, value) in values.iter().enumerate() {
+                eval.add(i, *value);
+            }
             assert_eq!(eval.evaluate(), Some(result), "invalid result")
         }
     }
 
+    #[test]
+    pub fn evaluate_maj_tie_breaker() {
+        let function = |tie_breaker| Function {
+            gate: Gate::Maj,
+            inverted: false,
+            tie_breaker,
+        };
+        for (values, tie_breaker, result) in [
+            ([true, true, false, false], None, None),
+            ([true, true, false, false], Some(0), Some(true)),
+            ([true, true, false, false], Some(3), Some(false)),
+            ([true, true, true, false], Some(3), Some(true)),
+        ] {
+            let mut eval = function(tie_breaker).evaluate(values.len());
+            for (i, value) in values.iter().enumerate() {
+                eval.add(i, *value);
+            }
+            assert_eq!(eval.evaluate(), result, "invalid result")
+        }
+        assert_eq!(function(None).untied_inputs(&[0, 1, 2, 3]), [0, 1, 2, 3]);
+        assert_eq!(
+            function(Some(1)).untied_inputs(&[0, 1, 2, 3]),
+            [0, 1, 2, 3, 1]
+        );
+    }
+
     #[test]
     pub fn evaluate_and() {
         for (values, result) in [
@@ -203,9 +257,12 @@ mod tests {
             let mut eval = Function {
                 gate: Gate::And,
                 inverted: false,
+                tie_breaker: None,
             }
             .evaluate(values.len());
-            values.iter().for_each(|value| eval.add(*value));
+            for (i, value) in values.iter().enumerate() {
+                eval.add(i, *value);
+            }
             assert_eq!(eval.evaluate(), Some(result), "invalid result")
         }
     }
@@ -221,9 +278,12 @@ mod tests {
             let mut eval = Function {
                 gate: Gate::Constant(c),
                 inverted: false,
+                tie_breaker: None,
             }
             .evaluate(values.len());
-            values.iter().for_each(|value| eval.add(*value));
+            for (i, value) in values.iter().enumerate() {
+                eval.add(i, *value);
+            }
             assert_eq!(eval.evaluate(), Some(c), "invalid result")
         }
     }
@@ -44,7 +44,7 @@ impl EvaluationMethods for XorEval {
         self.val
     }
 
-    fn add(&mut self, value: bool) {
+    fn add(&mut self, _input: usize, value: bool) {
         match &mut self.val {
             None => {}
             Some(val) => *val ^= value,
@@ -52,7 +52,7 @@ impl EvaluationMethods for XorEval {
         self.num += 1
     }
 
-    fn add_unknown(&mut self) {
+    fn add_unknown(&mut self, _input: usize) {
         self.val = None;
         self.num += 1
     }
@@ -84,7 +84,7 @@ mod tests {
         assert_eq!(eval.hint_id(2, true), Some(BoolHint::Require(true)));
         assert_eq!(eval.hint_id(2, false), Some(BoolHint::Require(false)));
 
-        eval.add(true);
+        eval.add(0, true);
 
         assert_eq!(eval.hint(2, true), Some(BoolHint::Require(false)));
         assert_eq!(eval.hint(2, false), Some(BoolHint::Require(true)));
@@ -97,7 +97,7 @@ mod tests {
         assert_eq!(eval.hint_id(3, false), Some(BoolHint::Require(true)));
 
         let mut eval = XorEval::default();
-        eval.add(false);
+        eval.add(0, false);
 
         assert_eq!(eval.hint(2, true), Some(BoolHint::Require(true)));
         assert_eq!(eval.hint(2, false), Some(BoolHint::Require(false)));
@@ -1,13 +1,15 @@
-use std::{
-    borrow::Cow,
+use alloc::{borrow::Cow, sync::Arc, vec::Vec};
+use core::{
     fmt::{self, Display},
     hash::{Hash, Hasher},
     iter::once,
-    sync::Arc,
 };
 
 use derive_more::Deref;
+#[cfg(feature = "std")]
+use eggmock::GateFunction;
 use itertools::{Either, Itertools};
+#[cfg(feature = "std")]
 use rustc_hash::FxHashMap;
 
 use crate::{
@@ -16,8 +18,15 @@ use crate::{
 };
 
 #[derive(Debug, Clone, Deref)]
-#[deref(forward)]
-pub struct InstructionTypes<CT>(Arc<[InstructionType<CT>]>);
+pub struct InstructionTypes<CT> {
+    #[deref(forward)]
+    types: Arc<[InstructionType<CT>]>,
+    /// ids of the instructions computing a gate function, in ascending order. Instructions are
+    /// listed for their [secondary function](InstructionType::secondary) as well, majorities
+    /// breaking ties are skipped, see [`InstructionTypes::gates`].
+    #[cfg(feature = "std")]
+    by_gate: Arc<FxHashMap<GateFunction, Vec<u8>>>,
+}
 
 impl<CT> InstructionTypes<CT> {
     pub fn new(mut types: Vec<InstructionType<CT>>) -> Self {
@@ -26,22 +35,48 @@ impl<CT> InstructionTypes<CT> {
             .iter()
             .enumerate()
             .for_each(|(i, instr)| assert_eq!(instr.id, i as u8));
-        Self(types.into())
+        #[cfg(feature = "std")]
+        let mut by_gate = FxHashMap::<_, Vec<_>>::default();
+        #[cfg(feature = "std")]
+        for typ in &types {
+            if typ.function.tie_breaker.is_none() {
+                let functions = once(typ.function).chain(typ.secondary);
+                for function in functions.filter_map(|function| function.gate.gate_function()) {
+                    let ids = by_gate.entry(function).or_default();
+                    if ids.last() != Some(&typ.id) {
+                        ids.push(typ.id);
+                    }
+                }
+            }
+        }
+        Self {
+            types: types.into(),
+            #[cfg(feature = "std")]
+            by_gate: Arc::new(by_gate),
+        }
     }
     pub fn cell_types(&self) -> impl Iterator<Item = CT>
     where
         CT: CellType,
     {
-        self.0.iter().flat_map(|typ| typ.cell_types())
+        self.types.iter().flat_map(|typ| typ.cell_types())
     }
+    /// Returns the gates computed by instructions with more than one input, including
+    /// [secondary functions](InstructionType::secondary). Majorities breaking ties are skipped as
+    /// they compute another function than the majority gates of networks.
     pub fn gates(&self) -> impl Iterator<Item = Gate> {
-        self.0
+        self.types
             .iter()
-            .filter(|typ| typ.arity() != Some(1))
-            .map(|typ| typ.function.gate)
+            .filter(|typ| typ.arity() != Some(1) && typ.function.tie_breaker.is_none())
+            .flat_map(|typ| once(typ.function.gate).chain(typ.secondary.map(|f| f.gate)))
     }
     pub fn by_id(&self, id: u8) -> &InstructionType<CT> {
-        &self.0[id as usize]
+        &self.types[id as usize]
+    }
+    /// Returns the ids of all instructions computing the given gate function.
+    #[cfg(feature = "std")]
+    pub fn ids_by_gate(&self, function: GateFunction) -> &[u8] {
+        self.by_gate.get(&function).map_or(&[], Vec::as_slice)
     }
 }
 
@@ -57,6 +92,13 @@ pub struct InstructionType<CT> {
 
     pub function: Function,
     pub outputs: Outputs<CT>,
+    /// Function computed into the second output operand instead of
+    /// [`function`](Self::function), e.g. the carry of a full adder computing the sum into its
+    /// first output. Instructions with a secondary function have exactly two output operands.
+    pub secondary: Option<Function>,
+    /// Whether the output operands have to be placed in cells distinct from the input operands,
+    /// even if the compiler does not require this for all instructions.
+    pub requires_disjoint_io: bool,
 }
 
 impl<CT> InstructionType<CT> {
@@ -72,6 +114,66 @@ impl<CT> InstructionType<CT> {
             .arity()
             .map(|arity| self.input_range.num_elements_in(arity))
     }
+
+    /// Returns a [`Display`]able, single-line description of this instruction type listing its
+    /// function, operands and properties, e.g. `RC: and((T) | (D)) -> (T) | ()`.
+    pub fn describe(&self) -> InstructionDescription<'_, CT> {
+        InstructionDescription(self)
+    }
+
+    /// Builds an instruction of this type operating on the given cells, failing if they do not
+    /// match the operands of this type, see [`Instruction::validate`].
+    pub fn build(
+        &self,
+        inputs: Vec<Cell<CT>>,
+        outputs: Vec<Operand<CT>>,
+    ) -> Result<Instruction<CT>, ()>
+    where
+        CT: CellType,
+    {
+        let instruction = Instruction {
+            typ: self.clone(),
+            inputs,
+            outputs,
+        };
+        instruction.validate()?;
+        Ok(instruction)
+    }
+}
+
+/// See [`InstructionType::describe`].
+#[derive(Debug, Clone, Copy)]
+pub struct InstructionDescription<'a, CT>(&'a InstructionType<CT>);
+
+impl<CT: CellType> Display for InstructionDescription<'_, CT> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let typ = self.0;
+        let outputs = typ.outputs.iter().format(" | ");
+        write!(
+            f,
+            "{}: {}({}) -> {outputs}",
+            typ.name, typ.function, typ.input
+        )?;
+        if typ.input_range.start != 0 {
+            write!(f, "; reads inputs {}..", typ.input_range.start)?;
+        }
+        if typ.input_override != InputIndices::None {
+            write!(f, "; overrides inputs {}", typ.input_override)?;
+        }
+        if typ.input_inverted != InputIndices::None {
+            write!(f, "; inverts inputs {}", typ.input_inverted)?;
+        }
+        if typ.requires_disjoint_io {
+            write!(f, "; disjoint io")?;
+        }
+        if let Some(tie_breaker) = typ.function.tie_breaker {
+            write!(f, "; breaks ties by input {tie_breaker}")?;
+        }
+        if let Some(secondary) = typ.secondary {
+            write!(f, "; computes {secondary} into output 1")?;
+        }
+        Ok(())
+    }
 }
 
 impl<CT> PartialEq for InstructionType<CT> {
@@ -83,13 +185,13 @@ impl<CT> PartialEq for InstructionType<CT> {
 impl<CT> Eq for InstructionType<CT> {}
 
 impl<CT> PartialOrd for InstructionType<CT> {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+    fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
         Some(self.cmp(other))
     }
 }
 
 impl<CT> Ord for InstructionType<CT> {
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+    fn cmp(&self, other: &Self) -> core::cmp::Ordering {
         self.name.cmp(&other.name)
     }
 }
@@ -100,6 +202,40 @@ impl<CT> Hash for InstructionType<CT> {
     }
 }
 
+/// Fields defining the behaviour of an [`InstructionType`], see [`InstructionType::definition`].
+pub(crate) type InstructionDefinition<'a, CT> = (
+    u8,
+    &'a str,
+    &'a TuplesDef<CellPat<CT>>,
+    InputIndices,
+    InputIndices,
+    Range,
+    Function,
+    &'a Outputs<CT>,
+    Option<Function>,
+    bool,
+);
+
+impl<CT> InstructionType<CT> {
+    /// Returns all fields of this instruction type. Unlike the [`PartialEq`] implementation, which
+    /// only compares names, comparing definitions distinguishes equally named instruction types
+    /// with different operands or functions.
+    pub(crate) fn definition(&self) -> InstructionDefinition<'_, CT> {
+        (
+            self.id,
+            &*self.name,
+            &self.input,
+            self.input_override,
+            self.input_inverted,
+            self.input_range,
+            self.function,
+            &self.outputs,
+            self.secondary,
+            self.requires_disjoint_io,
+        )
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Instruction<CT, TypCT = CT> {
     pub typ: InstructionType<TypCT>,
@@ -126,15 +262,13 @@ impl<CT: CellType> Instruction<CT, CT> {
     }
 
     // returns (cell, inverted) pairs for all output cells, normalized to cell `false` for constants
+    #[cfg(feature = "std")]
     pub fn write_cell_inverted_map(&self) -> FxHashMap<Cell<CT>, bool> {
         // write_operands returns input operands first, so output operands override input operands
         // as expected
         FxHashMap::from_iter(self.write_operands().map(|op| {
-            if op.cell == CT::constant(true) {
-                (CT::constant(false), !op.inverted)
-            } else {
-                (op.cell, op.inverted)
-            }
+            let op = op.normalize_constant();
+            (op.cell, op.inverted)
         }))
     }
 
@@ -142,7 +276,14 @@ impl<CT: CellType> Instruction<CT, CT> {
         match self.typ.input_override {
             InputIndices::All => Either::Left(self.inputs.iter().enumerate()),
             InputIndices::None => Either::Left([].iter().enumerate()),
-            InputIndices::Index(idx) => Either::Right(once((idx, &self.inputs[idx]))),
+            InputIndices::Index(idx) => Either::Right(Either::Left(once((idx, &self.inputs[idx])))),
+            // n-ary operands may have fewer inputs than the range covers
+            InputIndices::Range { start, end } => Either::Right(Either::Right(
+                self.inputs[start.min(self.inputs.len())..end.min(self.inputs.len())]
+                    .iter()
+                    .enumerate()
+                    .map(move |(i, cell)| (start + i, cell)),
+            )),
         }
         .map(|(i, &cell)| Operand {
             cell,
@@ -155,6 +296,12 @@ impl<CT: CellType> Instruction<CT, CT> {
             .chain(self.outputs.iter().copied())
     }
 
+    /// Returns the output operand the [secondary function](InstructionType::secondary) is computed
+    /// into, if any. All other written operands hold the primary function.
+    pub fn secondary_output(&self) -> Option<Operand<CT>> {
+        self.typ.secondary.and(self.outputs.get(1).copied())
+    }
+
     pub fn write_cells(&self) -> impl Iterator<Item = Cell<CT>> {
         self.write_operands().map(|op| op.cell)
     }
@@ -164,15 +311,58 @@ impl<CT: CellType> Instruction<CT, CT> {
     }
 }
 
+impl<CT, TypCT> Instruction<CT, TypCT> {
+    /// Returns a [`Display`]able version of this instruction that renders its cells using `namer`
+    /// instead of their [`Display`] implementation.
+    pub fn display_with_namer<F, D>(&self, namer: F) -> NamedInstruction<'_, CT, TypCT, F>
+    where
+        F: Fn(Cell<CT>) -> D,
+        D: Display,
+    {
+        NamedInstruction {
+            instruction: self,
+            namer,
+        }
+    }
+}
+
 impl<CT, TypCT> Display for Instruction<CT, TypCT>
 where
     CT: CellType,
     TypCT: CellType,
 {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}({})", self.typ.name, self.inputs.iter().format(", "),)?;
-        if !self.outputs.is_empty() {
-            write!(f, " -> ({})", self.outputs.iter().format(", "))?;
+        Display::fmt(&self.display_with_namer(|cell| cell), f)
+    }
+}
+
+/// See [`Instruction::display_with_namer`].
+pub struct NamedInstruction<'i, CT, TypCT, F> {
+    instruction: &'i Instruction<CT, TypCT>,
+    namer: F,
+}
+
+impl<CT, TypCT, F, D> Display for NamedInstruction<'_, CT, TypCT, F>
+where
+    CT: CellType,
+    F: Fn(Cell<CT>) -> D,
+    D: Display,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Instruction {
+            typ,
+            inputs,
+            outputs,
+        } = self.instruction;
+        let namer = &self.namer;
+        let inputs = inputs.iter().map(|&cell| namer(cell));
+        write!(f, "{}({})", typ.name, inputs.format(", "))?;
+        if !outputs.is_empty() {
+            let outputs = outputs.iter().format_with(", ", |op, f| {
+                let inv = if op.inverted { "!" } else { "" };
+                f(&format_args!("{inv}{}", namer(op.cell)))
+            });
+            write!(f, " -> ({outputs})")?;
         }
         Ok(())
     }
@@ -183,6 +373,22 @@ pub enum InputIndices {
     All,
     None,
     Index(usize),
+    /// All indices `i` with `start <= i < end`.
+    Range {
+        start: usize,
+        end: usize,
+    },
+}
+
+impl Display for InputIndices {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::All => write!(f, "all"),
+            Self::None => write!(f, "none"),
+            Self::Index(i) => write!(f, "{i}"),
+            Self::Range { start, end } => write!(f, "{start}..{end}"),
+        }
+    }
 }
 
 impl Set<usize> for InputIndices {
@@ -191,6 +397,107 @@ impl Set<usize> for InputIndices {
             Self::None => false,
             Self::All => true,
             Self::Index(i) => *e == i,
+            Self::Range { start, end } => (start..end).contains(e),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+
+    use crate::{NaryPat, Operand, OperandPat, Pats, TuplePat, TuplePats, tests::DummyCellType};
+
+    use super::*;
+
+    #[test]
+    fn overridden_input_range() {
+        let typ = InstructionType {
+            id: 0,
+            name: "TEST".into(),
+            input: TuplesDef::Nary(NaryPat(Pats::new(vec![CellPat::Type(DummyCellType::A)]))),
+            input_override: InputIndices::Range { start: 1, end: 3 },
+            input_inverted: InputIndices::Index(2),
+            input_range: Range { start: 0 },
+            function: Function {
+                inverted: false,
+                gate: Gate::And,
+                tie_breaker: None,
+            },
+            outputs: Outputs::new(vec![]),
+            secondary: None,
+            requires_disjoint_io: false,
+        };
+        let instruction = Instruction {
+            typ: typ.clone(),
+            inputs: (0..4).map(|i| Cell::new(DummyCellType::A, i)).collect(),
+            outputs: vec![],
+        };
+        assert_eq!(
+            instruction.overridden_input_operands().collect_vec(),
+            vec![
+                Operand {
+                    cell: Cell::new(DummyCellType::A, 1),
+                    inverted: false,
+                },
+                Operand {
+                    cell: Cell::new(DummyCellType::A, 2),
+                    inverted: true,
+                },
+            ]
+        );
+
+        // the range is clamped to the inputs of the instruction
+        for num_inputs in [0, 1, 2] {
+            let instruction = Instruction {
+                typ: typ.clone(),
+                inputs: (0..num_inputs)
+                    .map(|i| Cell::new(DummyCellType::A, i))
+                    .collect(),
+                outputs: vec![],
+            };
+            assert_eq!(
+                instruction.overridden_input_operands().count(),
+                num_inputs.saturating_sub(1) as usize
+            );
+        }
+    }
+
+    #[test]
+    fn build_validates_operands() {
+        let a = |i| Cell::new(DummyCellType::A, i);
+        let typ = InstructionType {
+            id: 0,
+            name: "TEST".into(),
+            input: TuplesDef::Nary(NaryPat(Pats::new(vec![CellPat::Type(DummyCellType::A)]))),
+            input_override: InputIndices::None,
+            input_inverted: InputIndices::None,
+            input_range: Range { start: 0 },
+            function: Function {
+                inverted: false,
+                gate: Gate::And,
+                tie_breaker: None,
+            },
+            outputs: Outputs::new(vec![TuplesDef::Tuples(TuplePats::new(vec![
+                TuplePat::new(vec![Pats::new(vec![OperandPat {
+                    cell: CellPat::Type(DummyCellType::A),
+                    inverted: false,
+                }])]),
+            ]))]),
+            secondary: None,
+            requires_disjoint_io: false,
+        };
+        let output = Operand {
+            cell: a(3),
+            inverted: false,
+        };
+        let instruction = typ.build(vec![a(0), a(1)], vec![output]).unwrap();
+        assert_eq!(instruction.inputs, [a(0), a(1)]);
+        assert_eq!(instruction.outputs, [output]);
+        // missing output operand
+        assert!(typ.build(vec![a(0), a(1)], vec![]).is_err());
+        // input of the wrong type
+        let b = Cell::new(DummyCellType::B, 0);
+        assert!(typ.build(vec![a(0), b], vec![output]).is_err());
+    }
+}
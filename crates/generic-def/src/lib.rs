@@ -1,5 +1,10 @@
+#![no_std]
 #![allow(clippy::result_unit_err)]
 
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
+
 mod boolhint;
 mod boolset;
 mod cell;
@@ -11,16 +16,50 @@ mod patterns;
 mod range;
 pub mod set;
 
-use std::fmt::{Display, Formatter};
+use alloc::{collections::BTreeSet, vec::Vec};
+use core::{
+    fmt::{Display, Formatter},
+    hash::{Hash, Hasher},
+};
 
+#[cfg(feature = "std")]
+use eggmock::GateFunction;
 use itertools::Itertools;
+#[cfg(feature = "std")]
+use rustc_hash::FxHashSet;
 
 pub use self::{
     boolhint::BoolHint, boolset::BoolSet, cell::*, func::*, instruction::*, operand::*, outputs::*,
     patterns::*, range::*,
 };
 
+/// Costs of the instructions of an architecture as declared by `cost = <expr>` in
+/// `define_generic_architecture!`.
+pub trait InstructionCosts {
+    type CellType: CellType;
+
+    /// Returns the declared cost of the instruction with the given id, `1` if none was declared.
+    fn instruction_cost(id: u8) -> f64;
+}
+
+/// Gate functions that networks are rewritten into when compiling them, each listed with the gate
+/// functions it can be expressed by (together with inverters). The conversion rewrites of
+/// `create_rewrites` in `lime_generic::untyped_ntk` are added for every entry whose gate functions
+/// it is expressed by are synthesizable.
+#[cfg(feature = "std")]
+pub const GATE_CONVERSIONS: &[(GateFunction, &[GateFunction])] = &[
+    // and(a, b) = maj(a, b, f)
+    (GateFunction::And, &[GateFunction::Maj]),
+    // maj(x, y, z) = !and(!and(x, y), !and(y, z), !and(x, z))
+    (GateFunction::Maj, &[GateFunction::And]),
+    // xor(x, y) = !and(!and(x, !y), !and(!x, y))
+    (GateFunction::Xor, &[GateFunction::And]),
+];
+
 /// Abstractly describes a Logic-in-Memory architecture.
+///
+/// Architectures compare equal iff their instruction types are defined identically, including ids,
+/// names, operands and functions.
 #[derive(Clone)]
 pub struct Architecture<CT> {
     instructions: InstructionTypes<CT>,
@@ -37,6 +76,43 @@ impl<CT: CellType> Architecture<CT> {
             types,
         }
     }
+
+    /// Combines this architecture with another architecture on the same cell types, connected by
+    /// the given bridge instructions (e.g. copies between the cells of both architectures).
+    ///
+    /// Instructions are re-indexed in the order `self`, `other`, `bridge_instructions`, hence the
+    /// instruction ids of the composed architecture may differ from the ids of its parts.
+    pub fn compose(
+        self,
+        other: Architecture<CT>,
+        bridge_instructions: InstructionTypes<CT>,
+    ) -> Self {
+        let instructions = [self.instructions, other.instructions, bridge_instructions]
+            .iter()
+            .flat_map(|types| types.iter().cloned())
+            .enumerate()
+            .map(|(id, typ)| InstructionType {
+                id: id.try_into().expect("too many instructions"),
+                ..typ
+            })
+            .collect_vec();
+        assert!(
+            all_unique(instructions.iter().map(|typ| &typ.name)),
+            "instruction names of composed architectures must be unique"
+        );
+        Self::new(InstructionTypes::new(instructions))
+    }
+
+    /// Returns every cell of the cell types of this architecture, ordered by type and index.
+    ///
+    /// Cell types with an infinite number of cells (see [`CellType::count`]) are skipped, hence
+    /// the cells of e.g. Ambit's `D` type, which holds the inputs, are not included.
+    pub fn all_cells(&self) -> impl Iterator<Item = Cell<CT>> + '_ {
+        self.types
+            .iter()
+            .filter(|typ| typ.count().is_some())
+            .flat_map(|typ| typ.cell_iter())
+    }
 }
 
 impl<CT> Architecture<CT> {
@@ -46,29 +122,98 @@ impl<CT> Architecture<CT> {
     pub fn types(&self) -> &[CT] {
         &self.types
     }
+
+    /// Returns the gate functions a network may consist of to be compiled for this architecture,
+    /// i.e. the gates computed by an instruction (see [`InstructionTypes::gates`]) and all gates
+    /// that can be converted into those by the [`GATE_CONVERSIONS`].
+    #[cfg(feature = "std")]
+    pub fn synthesizable_gates(&self) -> FxHashSet<GateFunction> {
+        let mut gates = self
+            .instructions
+            .gates()
+            .filter_map(|gate| gate.gate_function())
+            .collect::<FxHashSet<_>>();
+        loop {
+            let known = gates.len();
+            for (gate, via) in GATE_CONVERSIONS {
+                if via.iter().all(|via| gates.contains(via)) {
+                    gates.insert(*gate);
+                }
+            }
+            if gates.len() == known {
+                return gates;
+            }
+        }
+    }
+}
+
+/// Lists the cell types in the syntax of `define_generic_architecture!`, e.g. `[T; 4]` or `[D]` for
+/// cell types with an infinite number of cells, followed by the
+/// [descriptions](InstructionType::describe) of all instructions.
+impl<CT: CellType> Display for Architecture<CT> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        let cells = self
+            .types
+            .iter()
+            .filter(|typ| **typ != CT::CONSTANT)
+            .format_with(", ", |typ, f| match typ.count() {
+                Some(count) => f(&format_args!("[{}; {count}]", typ.name())),
+                None => f(&format_args!("[{}]", typ.name())),
+            });
+        writeln!(f, "cells {cells}")?;
+        writeln!(f, "instructions")?;
+        for typ in self.instructions.iter() {
+            writeln!(f, "  {}", typ.describe())?;
+        }
+        Ok(())
+    }
+}
+
+impl<CT: PartialEq> PartialEq for Architecture<CT> {
+    fn eq(&self, other: &Self) -> bool {
+        self.types == other.types
+            && self
+                .instructions
+                .iter()
+                .map(InstructionType::definition)
+                .eq(other.instructions.iter().map(InstructionType::definition))
+    }
+}
+
+impl<CT: Eq> Eq for Architecture<CT> {}
+
+impl<CT: Hash> Hash for Architecture<CT> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.types.hash(state);
+        self.instructions.len().hash(state);
+        for typ in self.instructions.iter() {
+            typ.definition().hash(state);
+        }
+    }
 }
 
-fn display_maybe_inverted(f: &mut Formatter<'_>, inverted: bool) -> std::fmt::Result {
+fn display_maybe_inverted(f: &mut Formatter<'_>, inverted: bool) -> core::fmt::Result {
     if inverted { write!(f, "!") } else { Ok(()) }
 }
 
-fn display_index<D: Display>(f: &mut Formatter<'_>, idx: D) -> std::fmt::Result {
+fn display_index<D: Display>(f: &mut Formatter<'_>, idx: D) -> core::fmt::Result {
     write!(f, "[{idx}]")
 }
 
 fn check_no_duplicate_cells<'a, CT: CellType>(
     ops: impl IntoIterator<Item = &'a Cell<CT>>,
 ) -> Result<(), ()> {
-    if ops.into_iter().duplicates().next().is_some() {
-        Err(())
-    } else {
-        Ok(())
-    }
+    if all_unique(ops) { Ok(()) } else { Err(()) }
+}
+
+fn all_unique<T: Ord>(items: impl IntoIterator<Item = T>) -> bool {
+    let mut seen = BTreeSet::new();
+    items.into_iter().all(|item| seen.insert(item))
 }
 
 #[cfg(test)]
 mod tests {
-    use std::borrow::Cow;
+    use alloc::borrow::Cow;
 
     use super::*;
 
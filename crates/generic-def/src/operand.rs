@@ -1,4 +1,4 @@
-use std::fmt::{Debug, Display};
+use core::fmt::{Debug, Display};
 
 use crate::{BoolHint, Cell, CellIndex, CellPat, CellType, PatBase, display_maybe_inverted};
 
@@ -15,13 +15,26 @@ impl<CT: CellType> Operand<CT> {
             inverted: self.inverted,
         }
     }
+
+    /// Returns an equivalent operand that references the `false` constant instead of the `true`
+    /// constant, so that operands on constants can be compared regardless of the constant cell.
+    pub fn normalize_constant(self) -> Self {
+        if self.cell == CT::constant(true) {
+            Self {
+                cell: CT::constant(false),
+                inverted: !self.inverted,
+            }
+        } else {
+            self
+        }
+    }
 }
 
 impl<CT> Display for Operand<CT>
 where
     CT: CellType,
 {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         display_maybe_inverted(f, self.inverted)?;
         write!(f, "{}", self.cell)
     }
@@ -111,13 +124,23 @@ impl<CT: CellType> PatBase for OperandPat<CT> {
     fn matches(&self, op: &Self::Instance) -> bool {
         op.inverted == self.inverted && self.cell.matches(&op.cell)
     }
+
+    fn intersect(&self, other: &Self) -> Option<Self> {
+        if self.inverted != other.inverted {
+            return None;
+        }
+        Some(Self {
+            cell: self.cell.intersect(&other.cell)?,
+            inverted: self.inverted,
+        })
+    }
 }
 
 impl<CT> Display for OperandPat<CT>
 where
     CT: CellType,
 {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         display_maybe_inverted(f, self.inverted)?;
         Display::fmt(&self.cell, f)
     }
@@ -167,6 +190,32 @@ mod tests {
         );
     }
 
+    #[test]
+    fn normalize_constant() {
+        for cell in [
+            DummyCellType::constant(false),
+            DummyCellType::constant(true),
+            Cell::new(DummyCellType::A, 1),
+        ] {
+            for inverted in [false, true] {
+                let operand = Operand { cell, inverted };
+                let normalized = operand.normalize_constant();
+                assert_eq!(normalized.normalize_constant(), normalized);
+                assert_ne!(normalized.cell, DummyCellType::constant(true));
+                match cell.constant_value() {
+                    Some(value) => assert_eq!(
+                        normalized
+                            .cell
+                            .constant_value()
+                            .map(|v| v ^ normalized.inverted),
+                        Some(value ^ inverted)
+                    ),
+                    None => assert_eq!(normalized, operand),
+                }
+            }
+        }
+    }
+
     #[test]
     pub fn try_fit_constant() {
         for inverted in [true, false] {
@@ -1,10 +1,10 @@
-use std::sync::Arc;
+use alloc::{sync::Arc, vec, vec::Vec};
 
 use derive_more::Deref;
 
 use crate::{BoolSet, Cell, CellType, OperandPat, TuplePat, TuplePats, TuplesDef};
 
-#[derive(Debug, Deref, Clone)]
+#[derive(Debug, Deref, Clone, PartialEq, Eq, Hash)]
 #[deref(forward)]
 pub struct Outputs<CT>(Arc<[TuplesDef<OperandPat<CT>>]>);
 
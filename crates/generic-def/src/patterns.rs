@@ -1,4 +1,5 @@
-use std::{fmt::Display, ops::Index, sync::Arc};
+use alloc::{sync::Arc, vec, vec::Vec};
+use core::{fmt::Display, ops::Index};
 
 use derive_more::{Deref, From};
 use itertools::{Either, Itertools};
@@ -12,9 +13,12 @@ pub trait PatBase: Copy {
     fn cell_type(&self) -> Self::CellType;
     fn cell_index(&self) -> Option<CellIndex>;
     fn matches(&self, instance: &Self::Instance) -> bool;
+    /// Returns the pattern matching exactly the instances matched by both patterns or `None` if
+    /// there are no such instances.
+    fn intersect(&self, other: &Self) -> Option<Self>;
 }
 
-#[derive(Deref, Debug, Clone)]
+#[derive(Deref, Debug, Clone, PartialEq, Eq, Hash)]
 #[deref(forward)]
 pub struct Pats<P>(pub Arc<[P]>);
 
@@ -39,6 +43,37 @@ impl<P> Pats<P> {
     {
         self.0.iter().any(|typ| typ.matches(op))
     }
+
+    /// Returns the patterns matching exactly the values matched by both `self` and `other`.
+    /// Patterns that are already covered by another resulting pattern are omitted.
+    pub fn intersect(&self, other: &Self) -> Self
+    where
+        P: PatBase + PartialEq,
+    {
+        let mut pats: Vec<P> = Vec::new();
+        for (a, b) in self.iter().cartesian_product(other.iter()) {
+            let Some(pat) = a.intersect(b) else {
+                continue;
+            };
+            if pats.iter().any(|other| pat.intersect(other) == Some(pat)) {
+                continue;
+            }
+            pats.retain(|other| other.intersect(&pat) != Some(*other));
+            pats.push(pat);
+        }
+        Self::new(pats)
+    }
+
+    /// Returns whether there is a value matched by both `self` and `other`, i.e. whether their
+    /// [intersection](Self::intersect) is non-empty.
+    pub fn intersects(&self, other: &Self) -> bool
+    where
+        P: PatBase,
+    {
+        self.iter()
+            .cartesian_product(other.iter())
+            .any(|(a, b)| a.intersect(b).is_some())
+    }
 }
 
 impl<CT: CellType> Pats<OperandPat<CT>> {
@@ -53,12 +88,12 @@ impl<CT: CellType> Pats<OperandPat<CT>> {
 }
 
 impl<P: Display> Display for Pats<P> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         write!(f, "{}", self.0.iter().format(" | "))
     }
 }
 
-#[derive(Deref, From, Debug, Clone)]
+#[derive(Deref, From, Debug, Clone, PartialEq, Eq, Hash)]
 #[deref(forward)]
 pub struct TuplePat<P>(Vec<Pats<P>>);
 
@@ -118,12 +153,12 @@ impl<P> TuplePat<P> {
 }
 
 impl<P: Display> Display for TuplePat<P> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         write!(f, "({})", self.0.iter().format(", "))
     }
 }
 
-#[derive(Debug, Clone, Deref)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deref)]
 pub struct TuplePats<P> {
     arity: usize,
     #[deref(forward)]
@@ -189,7 +224,7 @@ impl<CT: CellType> TuplePats<OperandPat<CT>> {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct NaryPat<P>(pub Pats<P>);
 
 impl<P> NaryPat<P> {
@@ -210,7 +245,7 @@ impl<P> Index<usize> for NaryPat<P> {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum TuplesDef<P> {
     Nary(NaryPat<P>),
     Tuples(TuplePats<P>),
@@ -285,3 +320,52 @@ impl<CT: CellType> TuplesDef<OperandPat<CT>> {
         }
     }
 }
+
+/// Lists the alternative tuples separated by `|`. Operands of n-ary descriptions all match the same
+/// patterns, which is rendered as `(<patterns>, ...)`.
+impl<P: Display> Display for TuplesDef<P> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Nary(NaryPat(pats)) => write!(f, "({pats}, ...)"),
+            Self::Tuples(tuples) => write!(f, "{}", tuples.iter().format(" | ")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{CellPat, tests::DummyCellType};
+
+    use super::*;
+
+    fn pats(pats: &[CellPat<DummyCellType>]) -> Pats<CellPat<DummyCellType>> {
+        Pats::new(pats.to_vec())
+    }
+
+    #[test]
+    fn intersect() {
+        let a = CellPat::Type(DummyCellType::A);
+        let b = CellPat::Type(DummyCellType::B);
+        let a1 = CellPat::Cell(Cell::new(DummyCellType::A, 1));
+        let a2 = CellPat::Cell(Cell::new(DummyCellType::A, 2));
+        let b1 = CellPat::Cell(Cell::new(DummyCellType::B, 1));
+
+        // type ∩ type
+        assert_eq!(pats(&[a]).intersect(&pats(&[a])), pats(&[a]));
+        assert!(pats(&[a]).intersect(&pats(&[b])).is_empty());
+        assert!(!pats(&[a]).intersects(&pats(&[b])));
+        // type ∩ cell
+        assert_eq!(pats(&[a]).intersect(&pats(&[a1])), pats(&[a1]));
+        assert_eq!(pats(&[a1]).intersect(&pats(&[a])), pats(&[a1]));
+        assert!(pats(&[b]).intersect(&pats(&[a1])).is_empty());
+        // cell ∩ cell, including cells of different types with the same index
+        assert_eq!(pats(&[a1]).intersect(&pats(&[a1])), pats(&[a1]));
+        assert!(pats(&[a1]).intersect(&pats(&[a2])).is_empty());
+        assert!(!pats(&[a1]).intersects(&pats(&[b1])));
+
+        // covered patterns are omitted
+        assert_eq!(pats(&[a, b1]).intersect(&pats(&[a1, a, b])), pats(&[a, b1]));
+        assert_eq!(pats(&[a1, a2]).intersect(&pats(&[a, b])), pats(&[a1, a2]));
+        assert!(pats(&[a1, b]).intersects(&pats(&[b1])));
+    }
+}
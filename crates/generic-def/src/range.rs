@@ -1,6 +1,6 @@
-use std::{cmp::min, ops::Index};
+use core::{cmp::min, ops::Index};
 
-#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
 pub struct Range {
     pub start: usize,
 }
@@ -1,7 +1,7 @@
-use std::{
-    collections::HashSet,
-    hash::{BuildHasher, Hash},
-};
+#[cfg(feature = "std")]
+use core::hash::{BuildHasher, Hash};
+#[cfg(feature = "std")]
+use std::collections::HashSet;
 
 pub trait Set<E> {
     fn contains(&self, e: &E) -> bool;
@@ -33,6 +33,7 @@ impl<E> Set<E> for AllOrNone {
     }
 }
 
+#[cfg(feature = "std")]
 impl<E: Hash + Eq, H: BuildHasher> Set<E> for HashSet<E, H> {
     fn contains(&self, e: &E) -> bool {
         HashSet::contains(self, e)
@@ -0,0 +1,137 @@
+//! Compiles a single network read from a file without the C++ harness, e.g.
+//!
+//! ```sh
+//! lime --arch ambit --input network.txt --mode greedy
+//! ```
+//!
+//! The file contains the network as S-expressions, see [`parse_network`].
+
+use std::{env::args, fs, process::ExitCode, str::FromStr};
+
+use itertools::Itertools;
+use lime_generic::{
+    compilation::{CandidateSelection, CompilationMode},
+    compile_network,
+    cost::{AmbitCost, DeclaredCosts, EqualCosts, OperationCost},
+    definitions::{Ambit, FELIX, IMPLY, PLiM},
+    eggmock::Network,
+    lime_generic_def::{Architecture, CellType},
+    untyped_ntk::{UntypedNetwork, parse_network},
+    usage_summary_json,
+};
+use strum::IntoEnumIterator;
+
+const USAGE: &str = "usage: lime --arch <ambit|felix|imply|plim> --input <network-file> \
+                     [--mode <mode>] [--candidates <selection>]";
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run() -> Result<(), String> {
+    let mut arch = None;
+    let mut input = None;
+    let mut mode = CompilationMode::Greedy;
+    let mut candidate_selection = CandidateSelection::All;
+    let mut args = args().skip(1);
+    while let Some(arg) = args.next() {
+        let value = args
+            .next()
+            .ok_or_else(|| format!("{arg} requires a value\n{USAGE}"))?;
+        match arg.as_str() {
+            "--arch" => arch = Some(value),
+            "--input" => input = Some(value),
+            "--mode" => mode = parse_setting(&value)?,
+            "--candidates" => candidate_selection = parse_setting(&value)?,
+            _ => return Err(format!("unknown argument {arg}\n{USAGE}")),
+        }
+    }
+    let arch = arch.ok_or_else(|| format!("missing --arch\n{USAGE}"))?;
+    let input = input.ok_or_else(|| format!("missing --input\n{USAGE}"))?;
+
+    let source = fs::read_to_string(&input).map_err(|err| format!("cannot read {input}: {err}"))?;
+    let network = parse_network(&source).map_err(|err| format!("cannot parse {input}: {err}"))?;
+    // the same costs as the FFI entry points, FELIX requires disjunct input and output operands,
+    // see gp_compile_felix
+    match arch.to_ascii_lowercase().as_str() {
+        "ambit" => run_for(
+            Ambit::new(),
+            AmbitCost,
+            network,
+            mode,
+            candidate_selection,
+            false,
+        ),
+        "felix" => run_for(
+            FELIX::new(),
+            DeclaredCosts::<FELIX>::default(),
+            network,
+            mode,
+            candidate_selection,
+            true,
+        ),
+        "imply" => run_for(
+            IMPLY::new(),
+            EqualCosts,
+            network,
+            mode,
+            candidate_selection,
+            false,
+        ),
+        "plim" => run_for(
+            PLiM::new(),
+            EqualCosts,
+            network,
+            mode,
+            candidate_selection,
+            false,
+        ),
+        _ => Err(format!("unknown architecture {arch}\n{USAGE}")),
+    }
+}
+
+fn parse_setting<S: IntoEnumIterator + ToString + FromStr>(value: &str) -> Result<S, String> {
+    value.parse().map_err(|_| {
+        format!(
+            "unknown setting {value}, expected one of {}",
+            S::iter().map(|setting| setting.to_string()).join(", ")
+        )
+    })
+}
+
+fn run_for<CT: CellType, C: OperationCost<CT>>(
+    arch: Architecture<CT>,
+    cost: C,
+    network: Network<UntypedNetwork>,
+    mode: CompilationMode,
+    candidate_selection: CandidateSelection,
+    disjunct_input_output: bool,
+) -> Result<(), String> {
+    let result = compile_network(
+        arch,
+        cost.clone(),
+        network,
+        mode,
+        candidate_selection,
+        disjunct_input_output,
+    )?;
+    println!("=== program:");
+    println!("{}", result.program);
+    println!("=== outputs:");
+    println!("{}", result.outputs.iter().join("\n"));
+    println!("=== statistics:");
+    println!("cost: {}", cost.program_cost(&result.program));
+    println!("cells: {}", result.program.num_cells());
+    println!("instructions: {}", result.program.instructions().count());
+    println!("usage: {}", usage_summary_json(&result.program));
+    for warning in &result.warnings {
+        eprintln!("warning: {warning}");
+    }
+    Ok(())
+}
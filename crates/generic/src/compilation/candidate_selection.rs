@@ -3,9 +3,10 @@ use std::iter;
 use eggmock::Id;
 use either::Either;
 use itertools::{Itertools, MinMaxResult};
+use rand::Rng;
 use tracing::warn;
 
-use crate::program::ProgramVersion;
+use crate::program::{ProgramVersion, state::CellStates};
 
 pub trait CandidateSelector {
     fn select_candidates<V: ProgramVersion>(&self, version: &V) -> impl Iterator<Item = Id>;
@@ -19,6 +20,45 @@ impl CandidateSelector for AllCandidates {
     }
 }
 
+/// Selects the candidates whose computation frees the most cells, i.e. that are the last use of
+/// the most values still held in cells. Keeping the number of live values low reduces the register
+/// pressure when the order of independent gates is arbitrary, e.g. for reconvergent fanout.
+pub struct MinimalPressureCandidateSelection;
+
+impl CandidateSelector for MinimalPressureCandidateSelection {
+    fn select_candidates<V: ProgramVersion>(&self, version: &V) -> impl Iterator<Item = Id> {
+        let freed = version
+            .candidates()
+            .iter()
+            .map(|id| (*id, get_freed_cells(version, *id)))
+            .collect_vec();
+        let max = freed.iter().map(|(_, freed)| *freed).max().unwrap_or(0);
+        freed
+            .into_iter()
+            .filter(move |(_, freed)| *freed == max)
+            .map(|(id, _)| id)
+    }
+}
+
+/// Selects a single candidate uniformly at random using the generator of the compilation
+/// parameters, see [`CompilationParameters::rng`](crate::compilation::CompilationParameters::rng).
+pub struct RandomCandidateSelection;
+
+impl CandidateSelector for RandomCandidateSelection {
+    fn select_candidates<V: ProgramVersion>(&self, version: &V) -> impl Iterator<Item = Id> {
+        let candidates = version.candidates();
+        if candidates.is_empty() {
+            return None.into_iter();
+        }
+        let idx = version
+            .parameters()
+            .rng
+            .get()
+            .random_range(0..candidates.len());
+        candidates.iter().nth(idx).copied().into_iter()
+    }
+}
+
 // Soeken, Mathias, et al. "An MIG-based compiler for programmable logic-in-memory architectures."
 // Proceedings of the 53rd Annual Design Automation Conference. 2016.
 pub struct MIGBasedCompilerCandidateSelection;
@@ -92,3 +132,15 @@ fn get_releasing_children<V: ProgramVersion>(version: &V, node: Id) -> usize {
         .filter(|fanin| ntk.node_output_ids(fanin.node_id()).len() == 1)
         .count()
 }
+
+fn get_freed_cells<V: ProgramVersion>(version: &V, node: Id) -> usize {
+    let ntk = &version.parameters().network;
+    ntk.node(node)
+        .inputs()
+        .iter()
+        .map(|fanin| fanin.node_id())
+        .unique()
+        .filter(|fanin| version.is_last_use(*fanin))
+        .map(|fanin| version.state().cells_with_id(fanin).count())
+        .sum()
+}
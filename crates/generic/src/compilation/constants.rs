@@ -0,0 +1,94 @@
+use std::rc::Rc;
+
+use eggmock::{Gate, Node, Signal};
+use lime_generic_def::{CellPat, CellType};
+use ordered_float::OrderedFloat;
+use rustc_hash::FxHashSet;
+
+use crate::{
+    compilation::CompilationParameters,
+    copy::{copy_cost, copy_cost_with_path, perform_copy},
+    cost::{Cost, CostExt, OperationCost},
+    program::{
+        DummyProgramVersion, ProgramVersion,
+        state::{CellStates, StateSavepoint},
+    },
+};
+
+/// Copies constants read by multiple gates into scratch cells before compiling if
+/// [`CompilationParameters::prematerialize_constants`] is set. Afterwards, the constants can be
+/// copied from the scratch cells instead of the constant cells, which pays off for architectures
+/// on which accessing the constant cells is expensive.
+///
+/// A constant is only materialized if this is estimated to reduce the total cost, i.e. if copying
+/// it once into the scratch cell and from there for every use is cheaper than copying it from the
+/// constant cell for every use. Uses are assumed to require the constant in a cell of the same
+/// type as the scratch cell. Returns the number of materialized constants.
+pub fn prematerialize_constants<CT: CellType, G: Gate, C: OperationCost<CT>>(
+    state: &mut StateSavepoint<CT, G>,
+    params: &Rc<CompilationParameters<CT, G, C>>,
+) -> usize {
+    if !params.prematerialize_constants {
+        return 0;
+    }
+    let network = &params.network;
+    let Some(&false_id) = network
+        .leaves()
+        .iter()
+        .find(|id| matches!(network.node(**id), Node::False))
+    else {
+        return 0;
+    };
+    let graph = &params.arch.copy_graph;
+    let none = FxHashSet::default();
+    let mut materialized = 0;
+    for value in [false, true] {
+        let signal = Signal::new(false_id, value);
+        let uses = network
+            .node_outputs(false_id)
+            .iter()
+            .filter(|fanout| network.node(fanout.node_id()).inputs().contains(&signal))
+            .count();
+        if uses < 2 {
+            continue;
+        }
+        let uses = OrderedFloat(uses as f64);
+        let constant = CT::constant(value);
+
+        let mut segment = state.savepoint();
+        let mut version = DummyProgramVersion::new(&mut segment, params);
+        let scratch = params
+            .arch
+            .types()
+            .iter()
+            .filter(|typ| **typ != CT::CONSTANT)
+            .filter_map(|&typ| {
+                let scratch = version.find_preferred_free_cell_for_type(typ, &none)?;
+                if version.state().cell(scratch).is_some() {
+                    return None;
+                }
+                let direct = copy_cost(graph, constant, CellPat::Type(typ), false, &none, None)?;
+                let materialize =
+                    copy_cost(graph, constant, CellPat::Cell(scratch), false, &none, None)?;
+                let forbidden = FxHashSet::from_iter([scratch]);
+                let reuse = copy_cost(graph, scratch, CellPat::Type(typ), false, &forbidden, None)?;
+                let savings = direct * uses - materialize - reuse * uses;
+                (savings > Cost::ZERO).then_some((savings, scratch))
+            })
+            .max_by_key(|(savings, _)| *savings)
+            .map(|(_, scratch)| scratch);
+        let Some(scratch) = scratch else {
+            continue;
+        };
+        let Some((_, path)) =
+            copy_cost_with_path(graph, constant, CellPat::Cell(scratch), false, &none, None)
+        else {
+            continue;
+        };
+        if perform_copy(path, &mut version, constant, CellPat::Cell(scratch), &none).is_ok() {
+            segment.retain();
+            materialized += 1;
+        }
+    }
+    materialized
+}
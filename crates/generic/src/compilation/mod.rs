@@ -1,41 +1,69 @@
-mod candidate_selection;
+pub(crate) mod candidate_selection;
+pub mod constants;
 pub mod optimization;
+pub mod profile;
+pub mod rng;
 pub mod step;
+pub mod trace;
+pub mod warm_start;
 
-use std::rc::Rc;
+use std::{
+    fmt::{self, Display, Formatter},
+    rc::Rc,
+    time::Instant,
+};
 
-use eggmock::{Gate, Network};
+use eggmock::{Gate, Id, Network, Node, Signal};
 use itertools::Itertools;
-use lime_generic_def::{Cell, CellPat, CellType, InputIndices, NaryPat, Pats};
-use rustc_hash::FxHashSet;
+use lime_generic_def::{
+    Cell, CellPat, CellType, InputIndices, Instruction, NaryPat, Operand, Pats,
+};
+use ordered_float::OrderedFloat;
+use rustc_hash::{FxHashMap, FxHashSet};
+use strum::{EnumIter, EnumString};
 
 use crate::{
     ArchitectureMeta,
     compilation::{
-        candidate_selection::{AllCandidates, MIGBasedCompilerCandidateSelection},
+        candidate_selection::{
+            AllCandidates, MIGBasedCompilerCandidateSelection, MinimalPressureCandidateSelection,
+            RandomCandidateSelection,
+        },
+        constants::prematerialize_constants,
         optimization::optimize_outputs,
+        profile::{Phase, PhaseTimer, ProfileReport, Profiler},
+        rng::CompilerRng,
         step::{DefaultStepFn, place_signals},
+        trace::{SearchTracer, Trace},
+        warm_start::replay_warm_start,
     },
-    cost::{Cost, OperationCost},
+    cost::{Cost, CostExt, OperationCost},
     program::{
         DummyProgramVersion, ProgramVersion,
         collection::DeltaCollectionProgramVersion,
-        state::{Program, State, StateDelta, StateSavepoint},
+        state::{CellStates, Operation, Program, State, StateDelta, StateSavepoint},
     },
+    validation::detect_cycle,
 };
 
-#[derive(Clone, Copy, PartialEq, Eq)]
+#[derive(Clone, Copy, PartialEq, Eq, strum::Display, EnumString, EnumIter)]
+#[strum(serialize_all = "snake_case")]
 #[repr(C)]
 pub enum CompilationMode {
     Greedy,
     Exhaustive,
 }
 
-#[derive(Clone, Copy, PartialEq, Eq)]
+#[derive(Clone, Copy, PartialEq, Eq, strum::Display, EnumString, EnumIter)]
+#[strum(serialize_all = "snake_case")]
 #[repr(C)]
 pub enum CandidateSelection {
     All,
+    #[strum(serialize = "plim_compiler")]
     MIGBasedCompiler,
+    MinimalPressure,
+    /// selects a single candidate at random, see [`CompilationParameters::rng`]
+    Random,
 }
 
 pub struct CompilationParameters<CT: CellType, G, C: OperationCost<CT>> {
@@ -45,7 +73,158 @@ pub struct CompilationParameters<CT: CellType, G, C: OperationCost<CT>> {
     pub cost: C,
     pub mode: CompilationMode,
     pub candidate_selection: CandidateSelection,
+    /// Whether the outputs of every instruction should be placed in cells distinct from its inputs.
+    /// If this is infeasible, the outputs may still override the inputs. Only instructions marked
+    /// with [`requires_disjoint_io`](lime_generic_def::InstructionType::requires_disjoint_io)
+    /// always place them in distinct cells.
     pub disjunct_input_output: bool,
+    /// Maximum recursion depth of the exhaustive search. Deeper branches are completed greedily and
+    /// a warning is added to the [`CompilationResult`]. Defaults to the size of the network plus
+    /// [`MAX_DEPTH_MARGIN`].
+    pub max_depth: Option<usize>,
+    /// Program of a previous compilation whose placements are reused as far as the network still
+    /// matches, see [`replay_warm_start`].
+    pub warm_start: Option<Program<CT>>,
+    /// Ids of the instructions that may be used, all if `None`. The copy graph of `arch` has to be
+    /// built for the same instructions, see
+    /// [`CopyGraph::build_with_instructions`](crate::copy::CopyGraph::build_with_instructions).
+    pub enabled_instructions: Option<FxHashSet<u8>>,
+    /// Lower bound of the cost of any program for the network, e.g. the number of gates if every
+    /// instruction costs at least `1`. If set, the exhaustive search terminates as soon as it finds
+    /// a program of this cost and prunes branches that are already more expensive than the best
+    /// program found so far. Greedy compilation follows a single path and is not affected.
+    pub cost_lower_bound: Option<Cost>,
+    /// If set, a warning is added to the [`CompilationResult`] if more than this fraction of the
+    /// operations of the compiled program are spills.
+    pub spill_warning_fraction: Option<f64>,
+    /// Whether constants read by multiple gates are copied into scratch cells before compiling,
+    /// see [`prematerialize_constants`].
+    pub prematerialize_constants: bool,
+    /// Generator used for all randomized decisions, e.g. by [`CandidateSelection::Random`].
+    pub rng: CompilerRng,
+    /// If set, the time spent in the individual phases of the compilation is measured and reported
+    /// in [`CompilationResult::profile`].
+    pub profiler: Option<Profiler>,
+    /// Cost added for every cell used by a program when the search compares programs, trading
+    /// instructions for fewer cells the higher it is. `0` compares programs by their cost only.
+    pub cell_reuse_bias: f64,
+    /// Cells the given signals are pinned to, e.g. for floor planning. Pinned inputs are placed in
+    /// their pin instead of their cell of [`input_cells`](Self::input_cells). Other signals are
+    /// moved to their pin if it is free whenever they are placed as an operand the pin matches.
+    /// Compilation fails with [`CompileError::InfeasiblePin`] if a pin can not be satisfied.
+    pub signal_pins: FxHashMap<Signal, Cell<CT>>,
+    /// Whether the exhaustive search skips partial programs that can not be completed to a program
+    /// cheaper than the best program found so far, see
+    /// [`search_cost_lower_bound`](Self::search_cost_lower_bound). The result is the same as
+    /// without pruning as long as instructions do not get cheaper when finalizing adds output
+    /// operands to them. Always enabled if [`cost_lower_bound`](Self::cost_lower_bound) is set.
+    pub branch_and_bound: bool,
+}
+
+/// Recursion depth granted to the exhaustive search in addition to the size of the network. As
+/// every recursion computes one gate, the default maximum depth is never reached.
+pub const MAX_DEPTH_MARGIN: usize = 16;
+
+impl<CT: CellType, G: Gate, C: OperationCost<CT>> CompilationParameters<CT, G, C> {
+    pub fn max_depth(&self) -> usize {
+        self.max_depth
+            .unwrap_or_else(|| self.network.size() + MAX_DEPTH_MARGIN)
+    }
+
+    pub fn is_instruction_enabled(&self, id: u8) -> bool {
+        self.enabled_instructions
+            .as_ref()
+            .is_none_or(|enabled| enabled.contains(&id))
+    }
+
+    /// Measures the time spent in the given phase until the returned timer is dropped, if
+    /// profiling is enabled.
+    pub fn time(&self, phase: Phase) -> Option<PhaseTimer<'_>> {
+        self.profiler.as_ref().map(|profiler| profiler.time(phase))
+    }
+
+    /// Returns the cells the inputs of the network are placed in initially, i.e. the
+    /// [`input_cells`](Self::input_cells) with the cells of pinned inputs replaced by their pins.
+    pub fn initial_input_cells(&self) -> Vec<Cell<CT>> {
+        let mut cells = self.input_cells.clone();
+        for (signal, cell) in &self.signal_pins {
+            if let Node::Input(i) = self.network.node(signal.node_id()) {
+                cells[*i as usize] = *cell;
+            }
+        }
+        cells
+    }
+
+    /// Checks that every [pin](Self::signal_pins) refers to a non-constant cell of the architecture
+    /// and that pinned inputs are neither inverted nor share their cell with another input.
+    fn check_signal_pins(&self) -> Result<(), CompileError> {
+        let input_cells = self.initial_input_cells();
+        for (&signal, &cell) in &self.signal_pins {
+            let typ = cell.typ();
+            let exists = typ != CT::CONSTANT
+                && self.arch.types().contains(&typ)
+                && typ.count().is_none_or(|count| cell.index() < count);
+            let is_input = matches!(self.network.node(signal.node_id()), Node::Input(_));
+            let shared = input_cells.iter().filter(|other| **other == cell).count() > 1;
+            if !exists || (is_input && (signal.is_inverted() || shared)) {
+                return Err(CompileError::InfeasiblePin(signal));
+            }
+        }
+        Ok(())
+    }
+
+    /// Cost of the given program as compared by the search, see
+    /// [`cell_reuse_bias`](Self::cell_reuse_bias).
+    pub fn search_cost(&self, program: &Program<CT>) -> Cost {
+        let cost = self.cost.program_cost(program);
+        if self.cell_reuse_bias == 0.0 {
+            return cost;
+        }
+        cost.checked_add(OrderedFloat(
+            self.cell_reuse_bias * program.num_cells() as f64,
+        ))
+    }
+
+    /// Lower bound of the [`search_cost`](Self::search_cost) of every finalized program completing
+    /// the given partial program. Finalizing may remove or merge copies (see [`optimize_outputs`]
+    /// and [`Program::minimize_inversions`]), hence only the remaining operations are accounted
+    /// for, which are kept with all their operands.
+    pub fn search_cost_lower_bound(&self, program: &Program<CT>) -> Cost {
+        let kept = program
+            .0
+            .iter()
+            .filter(|op| !matches!(op, Operation::Copy { .. }))
+            .cloned()
+            .collect();
+        self.search_cost(&Program(kept))
+    }
+
+    /// Cost of appending `delta` to `program` as compared by the search, i.e. the cost of `delta`
+    /// (see [`OperationCost::program_cost_with_context`]) plus the
+    /// [`cell_reuse_bias`](Self::cell_reuse_bias) of every cell `program` does not use yet.
+    pub fn delta_cost(&self, program: &Program<CT>, delta: &Program<CT>) -> Cost {
+        let cost = self.cost.program_cost_with_context(program, delta);
+        if self.cell_reuse_bias == 0.0 {
+            return cost;
+        }
+        let used = program.cells();
+        let new_cells = delta.cells().difference(&used).count();
+        cost.checked_add(OrderedFloat(self.cell_reuse_bias * new_cells as f64))
+    }
+
+    /// Resets the profiler, if any, and returns a function attaching its report to results.
+    fn start_profile(&self) -> impl Fn(&mut CompilationResult<CT>) + '_ {
+        let start = Instant::now();
+        if let Some(profiler) = &self.profiler {
+            profiler.reset();
+        }
+        move |result| {
+            result.profile = self
+                .profiler
+                .as_ref()
+                .map(|profiler| profiler.report(start.elapsed()));
+        }
+    }
 }
 
 pub trait StepFn<CT: CellType, G: Gate, C: OperationCost<CT>> {
@@ -58,7 +237,46 @@ pub trait StepFn<CT: CellType, G: Gate, C: OperationCost<CT>> {
 
 pub struct CompilationResult<CT> {
     pub program: Program<CT>,
-    pub outputs: Vec<Cell<CT>>,
+    /// cells holding the outputs of the network, `inverted` if a cell holds the inverse of its
+    /// output, i.e. the output is obtained by inverting the value read from the cell. Constant
+    /// outputs refer to the constant cells if the architecture is able to read them.
+    pub outputs: Vec<Operand<CT>>,
+    /// hints on problems of the compiled program, e.g. an under-provisioned architecture
+    pub warnings: Vec<String>,
+    /// cells holding the signals of the network at the end of the program
+    pub signal_placement: FxHashMap<Signal, Vec<Cell<CT>>>,
+    /// time spent in the phases of the compilation if
+    /// [`CompilationParameters::profiler`] is set
+    pub profile: Option<ProfileReport>,
+}
+
+/// Error returned by [`compile`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CompileError {
+    /// The network contains a combinational cycle through the given nodes, see [`detect_cycle`].
+    CyclicNetwork(Vec<Id>),
+    /// The search did not find a program computing all outputs.
+    NoProgramFound,
+    /// The signal can not be placed in the cell it is pinned to, see
+    /// [`CompilationParameters::signal_pins`].
+    InfeasiblePin(Signal),
+}
+
+impl Display for CompileError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::CyclicNetwork(cycle) => {
+                write!(f, "network contains a cycle through nodes {cycle:?}")
+            }
+            Self::NoProgramFound => write!(f, "no program found"),
+            Self::InfeasiblePin(signal) => {
+                write!(
+                    f,
+                    "signal {signal:?} can not be placed in the cell it is pinned to"
+                )
+            }
+        }
+    }
 }
 
 pub fn compile<
@@ -68,27 +286,144 @@ pub fn compile<
     P: Into<Rc<CompilationParameters<CT, G, C>>>,
 >(
     params: P,
-) -> Option<CompilationResult<CT>> {
+) -> Result<CompilationResult<CT>, CompileError> {
+    compile_traced(params, None)
+}
+
+/// Like [`compile`], but reports the nodes of the exhaustive search tree to the given tracer.
+/// Greedy compilation does not branch, hence nothing is traced.
+pub fn compile_traced<
+    CT: CellType,
+    G: Gate,
+    C: OperationCost<CT>,
+    P: Into<Rc<CompilationParameters<CT, G, C>>>,
+>(
+    params: P,
+    tracer: Option<&mut dyn SearchTracer>,
+) -> Result<CompilationResult<CT>, CompileError> {
     let params = params.into();
-    let result = match &(params.mode, &params.candidate_selection) {
+    compile_with_mode(&params, params.mode, tracer)
+}
+
+fn compile_with_mode<CT: CellType, G: Gate, C: OperationCost<CT>>(
+    params: &Rc<CompilationParameters<CT, G, C>>,
+    mode: CompilationMode,
+    tracer: Option<&mut dyn SearchTracer>,
+) -> Result<CompilationResult<CT>, CompileError> {
+    if let Some(cycle) = detect_cycle(&params.network) {
+        return Err(CompileError::CyclicNetwork(cycle));
+    }
+    params.check_signal_pins()?;
+    let attach_profile = params.start_profile();
+    let mut result = match &(mode, &params.candidate_selection) {
         (CompilationMode::Exhaustive, CandidateSelection::All) => {
-            exhaustive_search(&params, DefaultStepFn(AllCandidates))
+            exhaustive_search(params, DefaultStepFn(AllCandidates), tracer)
         }
-        (CompilationMode::Exhaustive, CandidateSelection::MIGBasedCompiler) => {
-            exhaustive_search(&params, DefaultStepFn(MIGBasedCompilerCandidateSelection))
+        (CompilationMode::Exhaustive, CandidateSelection::MIGBasedCompiler) => exhaustive_search(
+            params,
+            DefaultStepFn(MIGBasedCompilerCandidateSelection),
+            tracer,
+        ),
+        (CompilationMode::Exhaustive, CandidateSelection::MinimalPressure) => exhaustive_search(
+            params,
+            DefaultStepFn(MinimalPressureCandidateSelection),
+            tracer,
+        ),
+        (CompilationMode::Exhaustive, CandidateSelection::Random) => {
+            exhaustive_search(params, DefaultStepFn(RandomCandidateSelection), tracer)
         }
         (CompilationMode::Greedy, CandidateSelection::All) => {
-            greedy_search(&params, &DefaultStepFn(AllCandidates))
+            greedy_search(params, &DefaultStepFn(AllCandidates))
         }
         (CompilationMode::Greedy, CandidateSelection::MIGBasedCompiler) => {
-            greedy_search(&params, &DefaultStepFn(MIGBasedCompilerCandidateSelection))
+            greedy_search(params, &DefaultStepFn(MIGBasedCompilerCandidateSelection))
+        }
+        (CompilationMode::Greedy, CandidateSelection::MinimalPressure) => {
+            greedy_search(params, &DefaultStepFn(MinimalPressureCandidateSelection))
         }
-    }?;
+        (CompilationMode::Greedy, CandidateSelection::Random) => {
+            greedy_search(params, &DefaultStepFn(RandomCandidateSelection))
+        }
+    }
+    .ok_or(CompileError::NoProgramFound)?;
     if result.outputs.len() != params.network.outputs().len() {
-        None
+        Err(CompileError::NoProgramFound)
     } else {
-        Some(result)
+        attach_profile(&mut result);
+        Ok(result)
+    }
+}
+
+/// Compiles the network of `params` both greedily and exhaustively, ignoring
+/// [`CompilationParameters::mode`], e.g. to quantify how much the exhaustive search improves on the
+/// greedy one. Both compilations share the same parameters, hence also the same copy graph.
+/// Returns the greedy and the exhaustive result, `None` where [`compile`] would fail.
+pub fn compile_both<
+    CT: CellType,
+    G: Gate,
+    C: OperationCost<CT>,
+    P: Into<Rc<CompilationParameters<CT, G, C>>>,
+>(
+    params: P,
+) -> (Option<CompilationResult<CT>>, Option<CompilationResult<CT>>) {
+    let params = params.into();
+    let compile = |mode| compile_with_mode(&params, mode, None).ok();
+    (
+        compile(CompilationMode::Greedy),
+        compile(CompilationMode::Exhaustive),
+    )
+}
+
+/// Like [`compile`], but returns up to `limit` distinct programs that all achieve the minimum cost
+/// when compiling exhaustively. The programs are ordered by their number of used cells. For greedy
+/// compilation, this returns at most the single program found by [`compile`]. Cyclic networks and
+/// infeasible [pins](CompilationParameters::signal_pins) yield no programs.
+pub fn compile_all_optimal<
+    CT: CellType,
+    G: Gate,
+    C: OperationCost<CT>,
+    P: Into<Rc<CompilationParameters<CT, G, C>>>,
+>(
+    params: P,
+    limit: usize,
+) -> Vec<CompilationResult<CT>> {
+    let params = params.into();
+    if detect_cycle(&params.network).is_some() || params.check_signal_pins().is_err() {
+        return Vec::new();
     }
+    let attach_profile = params.start_profile();
+    let mut results = match (params.mode, params.candidate_selection) {
+        (CompilationMode::Exhaustive, CandidateSelection::All) => {
+            exhaustive_search_all(&params, DefaultStepFn(AllCandidates), limit, None)
+        }
+        (CompilationMode::Exhaustive, CandidateSelection::MIGBasedCompiler) => {
+            exhaustive_search_all(
+                &params,
+                DefaultStepFn(MIGBasedCompilerCandidateSelection),
+                limit,
+                None,
+            )
+        }
+        (CompilationMode::Exhaustive, CandidateSelection::MinimalPressure) => {
+            exhaustive_search_all(
+                &params,
+                DefaultStepFn(MinimalPressureCandidateSelection),
+                limit,
+                None,
+            )
+        }
+        (CompilationMode::Exhaustive, CandidateSelection::Random) => exhaustive_search_all(
+            &params,
+            DefaultStepFn(RandomCandidateSelection),
+            limit,
+            None,
+        ),
+        (CompilationMode::Greedy, _) => return compile(params.clone()).into_iter().collect(),
+    };
+    results.retain(|result| result.outputs.len() == params.network.outputs().len());
+    results.sort_by_key(|result| result.program.num_cells());
+    results.iter_mut().for_each(attach_profile);
+    results
 }
 
 fn greedy_search<CT: CellType, G: Gate, C: OperationCost<CT>>(
@@ -96,10 +431,21 @@ fn greedy_search<CT: CellType, G: Gate, C: OperationCost<CT>>(
     step: &impl StepFn<CT, G, C>,
 ) -> Option<CompilationResult<CT>> {
     let mut state = State::initialize(params);
+    let mut savepoint = state.savepoint();
+    replay_warm_start(&mut savepoint, params);
+    prematerialize_constants(&mut savepoint, params);
+    greedy_complete(&mut savepoint, params, step)
+}
+
+/// Greedily compiles the remaining candidates of the given state.
+fn greedy_complete<CT: CellType, G: Gate, C: OperationCost<CT>>(
+    state: &mut StateSavepoint<CT, G>,
+    params: &Rc<CompilationParameters<CT, G, C>>,
+    step: &impl StepFn<CT, G, C>,
+) -> Option<CompilationResult<CT>> {
     loop {
         if state.candidates().is_empty() {
-            let mut state = state.savepoint();
-            return finalize(&mut state, params);
+            return finalize(state, params);
         } else {
             let mut deltas = Vec::new();
             step.step(
@@ -108,7 +454,7 @@ fn greedy_search<CT: CellType, G: Gate, C: OperationCost<CT>>(
             );
             let delta = deltas
                 .into_iter()
-                .min_by_key(|delta| params.cost.program_cost(delta.program_delta()))?;
+                .min_by_key(|delta| params.delta_cost(state.program(), delta.program_delta()))?;
             let mut state_sp = state.savepoint();
             state_sp.replay(delta);
             state_sp.retain();
@@ -119,51 +465,188 @@ fn greedy_search<CT: CellType, G: Gate, C: OperationCost<CT>>(
 fn exhaustive_search<CT: CellType, G: Gate, C: OperationCost<CT>>(
     params: &Rc<CompilationParameters<CT, G, C>>,
     strategy: impl StepFn<CT, G, C>,
+    tracer: Option<&mut dyn SearchTracer>,
 ) -> Option<CompilationResult<CT>> {
+    exhaustive_search_all(params, strategy, 1, tracer).pop()
+}
+
+fn exhaustive_search_all<CT: CellType, G: Gate, C: OperationCost<CT>>(
+    params: &Rc<CompilationParameters<CT, G, C>>,
+    strategy: impl StepFn<CT, G, C>,
+    limit: usize,
+    tracer: Option<&mut dyn SearchTracer>,
+) -> Vec<CompilationResult<CT>> {
     let mut state = State::initialize(params);
-    let mut result = None;
-    exhaustive_search_recurse(
+    let mut savepoint = state.savepoint();
+    replay_warm_start(&mut savepoint, params);
+    prematerialize_constants(&mut savepoint, params);
+    let mut search = ExhaustiveSearch {
         params,
-        &mut result,
-        state.savepoint(),
-        vec![Default::default()],
-        &strategy,
-    );
-    result.map(|(_, result)| result)
+        step: &strategy,
+        best: BestResults::new(limit, params.cost_lower_bound),
+        trace: tracer.map(Trace::new),
+    };
+    search.recurse(savepoint, vec![Default::default()], 0, None);
+    let BestResults {
+        mut results,
+        pruned,
+        ..
+    } = search.best;
+    if pruned > 0 {
+        let warning = format!(
+            "exhaustive search exceeded maximum depth {}, completed {pruned} branches greedily",
+            params.max_depth(),
+        );
+        for result in &mut results {
+            result.warnings.push(warning.clone());
+        }
+    }
+    results
 }
 
-fn exhaustive_search_recurse<CT: CellType, G: Gate, C: OperationCost<CT>>(
-    params: &Rc<CompilationParameters<CT, G, C>>,
-    best: &mut Option<(Cost, CompilationResult<CT>)>,
-    mut state: StateSavepoint<CT, G>,
-    deltas: Vec<StateDelta<CT>>,
-    step: &impl StepFn<CT, G, C>,
-) {
-    if state.candidates().is_empty() {
-        let result = finalize(&mut state, params).expect("output placement should be possible");
-        let cost = params.cost.program_cost(&result.program);
-        if best
-            .as_ref()
-            .map(|(prev_cost, best)| {
-                cost < *prev_cost
-                    || (cost == *prev_cost && best.program.num_cells() > result.program.num_cells())
-            })
-            .unwrap_or(true)
-        {
-            *best = Some((cost, result));
+/// Collects the cost-optimal results of the exhaustive search.
+struct BestResults<CT> {
+    cost: Option<Cost>,
+    results: Vec<CompilationResult<CT>>,
+    /// maximum number of tied results to retain, ties are broken by number of cells if this is `1`
+    limit: usize,
+    /// number of branches that exceeded the maximum depth
+    pruned: usize,
+    /// see [`CompilationParameters::cost_lower_bound`]
+    lower_bound: Option<Cost>,
+}
+
+impl<CT: CellType> BestResults<CT> {
+    fn new(limit: usize, lower_bound: Option<Cost>) -> Self {
+        Self {
+            cost: None,
+            results: Vec::new(),
+            limit: limit.max(1),
+            pruned: 0,
+            lower_bound,
         }
-    } else {
-        for delta in deltas {
-            let mut deltas = Vec::new();
+    }
+
+    /// Whether a program of the lower bound's cost was found. If tied programs are collected, the
+    /// search has to continue regardless.
+    fn reached_lower_bound(&self) -> bool {
+        self.limit == 1
+            && matches!((self.cost, self.lower_bound), (Some(cost), Some(bound)) if cost <= bound)
+    }
+
+    /// Whether every completion of the given partial program is more expensive than the best
+    /// program found so far.
+    /// Only checked if branch and bound is requested, see
+    /// [`CompilationParameters::branch_and_bound`]. Programs as expensive as the best one are kept,
+    /// as they may use fewer cells or be collected as tied results.
+    fn exceeds_best<G: Gate, C: OperationCost<CT>>(
+        &self,
+        program: &Program<CT>,
+        params: &CompilationParameters<CT, G, C>,
+    ) -> bool {
+        (params.branch_and_bound || self.lower_bound.is_some())
+            && self
+                .cost
+                .is_some_and(|best| params.search_cost_lower_bound(program) > best)
+    }
+
+    fn consider(&mut self, cost: Cost, result: CompilationResult<CT>) {
+        match self.cost {
+            Some(best_cost) if cost > best_cost => {}
+            Some(best_cost) if cost == best_cost => {
+                if self.limit == 1 {
+                    if self.results[0].program.num_cells() > result.program.num_cells() {
+                        self.results[0] = result;
+                    }
+                } else if self.results.len() < self.limit {
+                    let program = result.program.to_string();
+                    if self
+                        .results
+                        .iter()
+                        .all(|other| other.program.to_string() != program)
+                    {
+                        self.results.push(result);
+                    }
+                }
+            }
+            _ => {
+                self.cost = Some(cost);
+                self.results = vec![result];
+            }
+        }
+    }
+}
+
+/// Parts of the exhaustive search shared by all levels of the search tree.
+struct ExhaustiveSearch<'s, 't, CT: CellType, G, C: OperationCost<CT>, S> {
+    params: &'s Rc<CompilationParameters<CT, G, C>>,
+    step: &'s S,
+    best: BestResults<CT>,
+    trace: Option<Trace<'t>>,
+}
+
+impl<CT: CellType, G: Gate, C: OperationCost<CT>, S: StepFn<CT, G, C>>
+    ExhaustiveSearch<'_, '_, CT, G, C, S>
+{
+    /// Searches all ways of applying the given `deltas` to `state`, `node` being the trace node of
+    /// `state`.
+    fn recurse(
+        &mut self,
+        mut state: StateSavepoint<CT, G>,
+        deltas: Vec<StateDelta<CT>>,
+        depth: usize,
+        node: Option<usize>,
+    ) {
+        let params = self.params;
+        if self.best.reached_lower_bound() || self.best.exceeds_best(state.program(), params) {
+            return;
+        }
+        if state.candidates().is_empty() {
+            let result = finalize(&mut state, params).expect("output placement should be possible");
+            let cost = params.search_cost(&result.program);
+            self.best.consider(cost, result);
+        } else if depth >= params.max_depth() {
+            // stop branching and complete the cheapest option greedily
+            self.best.pruned += 1;
+            let Some(delta) = deltas
+                .into_iter()
+                .min_by_key(|delta| params.delta_cost(state.program(), delta.program_delta()))
+            else {
+                return;
+            };
+            if let Some(trace) = &mut self.trace {
+                trace.visit(node, &delta, &params.cost);
+            }
             let mut state = state.savepoint();
             state.replay(delta);
+            if let Some(result) = greedy_complete(&mut state, params, self.step) {
+                let cost = params.search_cost(&result.program);
+                self.best.consider(cost, result);
+            }
+        } else {
+            for delta in deltas {
+                if self.best.reached_lower_bound() {
+                    return;
+                }
+                let child = self
+                    .trace
+                    .as_mut()
+                    .map(|trace| trace.visit(node, &delta, &params.cost));
+                let mut deltas = Vec::new();
+                let mut state = state.savepoint();
+                state.replay(delta);
+                // the child can not improve on the best program, avoid computing its branches
+                if self.best.exceeds_best(state.program(), params) {
+                    continue;
+                }
 
-            step.step(
-                params,
-                DeltaCollectionProgramVersion::new(state.savepoint(), params, &mut deltas),
-            );
+                self.step.step(
+                    params,
+                    DeltaCollectionProgramVersion::new(state.savepoint(), params, &mut deltas),
+                );
 
-            exhaustive_search_recurse(params, best, state, deltas, step);
+                self.recurse(state, deltas, depth + 1, child);
+            }
         }
     }
 }
@@ -172,6 +655,7 @@ fn finalize<CT: CellType, G: Gate, C: OperationCost<CT>>(
     state: &mut StateSavepoint<CT, G>,
     params: &Rc<CompilationParameters<CT, G, C>>,
 ) -> Option<CompilationResult<CT>> {
+    let _timer = params.time(Phase::Finalize);
     let mut version = DummyProgramVersion::new(state, params);
     let ops = NaryPat(Pats(
         params
@@ -183,15 +667,172 @@ fn finalize<CT: CellType, G: Gate, C: OperationCost<CT>>(
             .collect_vec()
             .into(),
     ));
-    let outputs = place_signals(
+    // constant outputs can be read from the constant cells directly instead of wasting a cell and
+    // a copy on them, if the architecture is able to read constant cells at all
+    let constants_readable = params
+        .arch
+        .instructions()
+        .iter()
+        .any(|typ| typ.input.cell_types().any(|typ| typ == CT::CONSTANT));
+    let is_constant = |signal: &Signal| {
+        constants_readable && matches!(params.network.node(signal.node_id()), Node::False)
+    };
+    let signals = params
+        .network
+        .outputs()
+        .iter()
+        .copied()
+        .filter(|signal| !is_constant(signal))
+        .collect_vec();
+    let mut output_cells = place_signals(
         &ops,
         InputIndices::None,
-        params.network.outputs(),
+        &signals,
         params,
         &mut version,
         &mut FxHashSet::default(),
-    )?;
+    )?
+    .into_iter();
+    let outputs = params
+        .network
+        .outputs()
+        .iter()
+        .map(|signal| {
+            if is_constant(signal) {
+                return Operand {
+                    cell: CT::constant(signal.is_inverted()),
+                    inverted: false,
+                };
+            }
+            let cell = output_cells
+                .next()
+                .expect("every non-constant output should be placed");
+            let stored = version
+                .state()
+                .cell(cell)
+                .expect("output cell should hold a signal");
+            debug_assert_eq!(stored.node_id(), signal.node_id());
+            Operand {
+                cell,
+                inverted: stored != *signal,
+            }
+        })
+        .collect_vec();
     let mut program = state.program().clone();
     optimize_outputs(&mut program);
-    Some(CompilationResult { program, outputs })
+    program.minimize_inversions(&params.arch.copy_graph);
+    let signal_placement = simulate_placement(&program, params);
+    #[cfg(debug_assertions)]
+    if let Err(err) = program.check_def_use(&params.initial_input_cells()) {
+        panic!("{err} in compiled program:\n{program}");
+    }
+    let warnings = spill_warning(&program, params.spill_warning_fraction)
+        .into_iter()
+        .collect();
+    Some(CompilationResult {
+        program,
+        outputs,
+        warnings,
+        signal_placement,
+        profile: None,
+    })
+}
+
+/// Returns the cells holding each signal after executing the finalized `program`, found by
+/// simulating it starting from the leaves of the network. Instructions computing a candidate write
+/// its signal and instructions computing the identity of a single input (e.g. copies) propagate the
+/// signal read. Cells written by any other instruction are assumed to hold no signal, like the
+/// secondary output of an instruction not computing a second candidate.
+fn simulate_placement<CT: CellType, G: Gate, C: OperationCost<CT>>(
+    program: &Program<CT>,
+    params: &CompilationParameters<CT, G, C>,
+) -> FxHashMap<Signal, Vec<Cell<CT>>> {
+    let mut cells = FxHashMap::default();
+    let input_cells = params.initial_input_cells();
+    for &leaf_id in params.network.leaves() {
+        match params.network.node(leaf_id) {
+            Node::False => {
+                for value in [false, true] {
+                    cells.insert(CT::constant(value), Signal::new(leaf_id, value));
+                }
+            }
+            Node::Input(i) => {
+                cells.insert(input_cells[*i as usize], Signal::new(leaf_id, false));
+            }
+            Node::Gate(_) => unreachable!("gate cannot be a leaf"),
+        }
+    }
+    for op in &program.0 {
+        for instruction in op.instructions() {
+            let (written, secondary) = match op {
+                Operation::Candidate(_, id, secondary_id) => (
+                    Some(Signal::new(*id, instruction.typ.function.inverted)),
+                    secondary_id
+                        .zip(instruction.typ.secondary)
+                        .map(|(id, function)| Signal::new(id, function.inverted)),
+                ),
+                _ => (copied_signal(instruction, &cells), None),
+            };
+            let secondary_output = instruction.secondary_output();
+            for operand in instruction.write_operands() {
+                let written = if Some(operand) == secondary_output {
+                    secondary
+                } else {
+                    written
+                };
+                match written {
+                    Some(signal) => cells.insert(operand.cell, signal ^ operand.inverted),
+                    None => cells.remove(&operand.cell),
+                };
+            }
+        }
+    }
+    let mut placement = FxHashMap::<_, Vec<_>>::default();
+    for (cell, signal) in cells {
+        placement.entry(signal).or_default().push(cell);
+    }
+    for cells in placement.values_mut() {
+        cells.sort();
+    }
+    placement
+}
+
+/// Returns the signal written by the given instruction if it computes the identity of a single
+/// input that is not constant, see [`simulate_placement`].
+fn copied_signal<CT: CellType>(
+    instruction: &Instruction<CT>,
+    cells: &FxHashMap<Cell<CT>, Signal>,
+) -> Option<Signal> {
+    let (offset, inputs, _) = instruction.typ.input_range.slice(&instruction.inputs);
+    let mut evaluation = instruction.typ.function.evaluate(inputs.len());
+    let mut copied = None;
+    for (idx, cell) in inputs.iter().enumerate() {
+        let inverted = instruction.typ.input_inverted.contains(&(offset + idx));
+        match cell.constant_value() {
+            Some(value) => evaluation.add(idx, value ^ inverted),
+            None if copied.is_none() => {
+                let signal = cells.get(cell)?;
+                copied = Some(*signal ^ inverted);
+            }
+            None => return None,
+        }
+    }
+    Some(copied? ^ evaluation.id_inverted()?)
+}
+
+fn spill_warning<CT: CellType>(program: &Program<CT>, fraction: Option<f64>) -> Option<String> {
+    let fraction = fraction?;
+    let spills = program
+        .0
+        .iter()
+        .filter(|op| matches!(op, Operation::Copy { spill: true, .. }))
+        .count();
+    if spills as f64 <= fraction * program.0.len() as f64 {
+        return None;
+    }
+    Some(format!(
+        "{spills} of {} operations are spills, the architecture may not provide enough cells for \
+         this network",
+        program.0.len()
+    ))
 }
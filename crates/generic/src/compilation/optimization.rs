@@ -1,12 +1,18 @@
 use std::{cmp::Reverse, mem::take};
 
 use itertools::Itertools;
-use lime_generic_def::{CellPat, CellType, Operand, PatBase, TuplesDef};
+use lime_generic_def::{
+    Architecture, Cell, CellPat, CellType, InputIndices, Instruction, Operand, PatBase, TuplesDef,
+};
 use rustc_hash::{FxHashMap, FxHashSet};
 
-use crate::program::state::{Operation, Program};
+use crate::{
+    copy::CopyGraph,
+    program::state::{Operation, Program},
+};
 
 pub fn optimize_outputs<CT: CellType>(program: &mut Program<CT>) {
+    remove_copy_round_trips(program);
     let mut source_op_i = 0;
     loop {
         if source_op_i == program.0.len() {
@@ -16,6 +22,10 @@ pub fn optimize_outputs<CT: CellType>(program: &mut Program<CT>) {
             let source_op = &program.0[source_op_i];
             let source_op_instr = source_op.instructions();
             let instr = &source_op_instr[source_instr_i];
+            // the outputs of instructions with a secondary function hold different values
+            if instr.typ.secondary.is_some() {
+                continue;
+            }
             // cell -> inverted
             let mut output_cells = instr.write_cell_inverted_map();
 
@@ -28,14 +38,12 @@ pub fn optimize_outputs<CT: CellType>(program: &mut Program<CT>) {
                     ..
                 } = source_op
             {
-                if *from == CT::constant(true) {
-                    // normalize to false
-                    output_cells
-                        .entry(CT::constant(false))
-                        .or_insert(!*computes_from_inverted);
-                } else {
-                    output_cells.entry(*from).or_insert(*computes_from_inverted);
+                let from = Operand {
+                    cell: *from,
+                    inverted: *computes_from_inverted,
                 }
+                .normalize_constant();
+                output_cells.entry(from.cell).or_insert(from.inverted);
             }
 
             // handle rest of the current operation
@@ -60,11 +68,14 @@ pub fn optimize_outputs<CT: CellType>(program: &mut Program<CT>) {
                     from, to, inverted, ..
                 } = op
                 {
-                    let (from, inverted) = if *from == CT::constant(true) {
-                        (CT::constant(false), !*inverted)
-                    } else {
-                        (*from, *inverted)
-                    };
+                    let Operand {
+                        cell: from,
+                        inverted,
+                    } = Operand {
+                        cell: *from,
+                        inverted: *inverted,
+                    }
+                    .normalize_constant();
                     let (from, inverted) = (&from, &inverted);
                     if let Some(&inverted_out) = output_cells.get(from) {
                         if !rw_between.contains(to) {
@@ -181,3 +192,325 @@ pub fn optimize_outputs<CT: CellType>(program: &mut Program<CT>) {
         source_op_i += 1;
     }
 }
+
+/// Removes copies `b -> a` directly following a copy `a -> b` of the same polarity, as they restore
+/// the value `a` holds anyway. No operation in between may access `a` or `b`. The first copy is
+/// removed as well if the value it writes to `b` is never read. Copies are only removed if all
+/// other cells they write are overwritten before being read.
+pub fn remove_copy_round_trips<CT: CellType>(program: &mut Program<CT>) {
+    let mut first_i = 0;
+    while first_i < program.0.len() {
+        let Some(second_i) = copy_round_trip(program, first_i) else {
+            first_i += 1;
+            continue;
+        };
+        program.0.remove(second_i);
+        if writes_are_dead(program, first_i, None) {
+            program.0.remove(first_i);
+        } else {
+            first_i += 1;
+        }
+    }
+}
+
+/// Returns the index of the copy that restores the source of the copy `first_i`, see
+/// [`remove_copy_round_trips`].
+fn copy_round_trip<CT: CellType>(program: &Program<CT>, first_i: usize) -> Option<usize> {
+    let Operation::Copy {
+        from: a,
+        to: b,
+        inverted,
+        ..
+    } = program.0[first_i]
+    else {
+        return None;
+    };
+    // copies clobbering their source have to be followed by the copy restoring it
+    if program.0[first_i]
+        .instructions()
+        .iter()
+        .any(|instr| instr.write_cells().contains(&a))
+    {
+        return None;
+    }
+    let second_i = (first_i + 1..program.0.len()).find(|&i| {
+        program.0[i].instructions().iter().any(|instr| {
+            instr
+                .read_cells()
+                .chain(instr.write_cells())
+                .any(|cell| cell == a || cell == b)
+        })
+    })?;
+    let Operation::Copy {
+        from,
+        to,
+        inverted: second_inverted,
+        ..
+    } = program.0[second_i]
+    else {
+        return None;
+    };
+    // two inverting copies cancel each other out as well
+    if from != b || to != a || second_inverted != inverted {
+        return None;
+    }
+    writes_are_dead(program, second_i, Some(a)).then_some(second_i)
+}
+
+/// Checks whether all values written by operation `op_i`, except for the one written to `except`,
+/// are overwritten before being read by any later operation.
+fn writes_are_dead<CT: CellType>(
+    program: &Program<CT>,
+    op_i: usize,
+    except: Option<Cell<CT>>,
+) -> bool {
+    program.0[op_i]
+        .instructions()
+        .iter()
+        .flat_map(|instr| instr.write_cells())
+        .filter(|&cell| Some(cell) != except)
+        .all(|cell| is_dead_after(program, cell, op_i, op_i, cell))
+}
+
+/// Merges runs of consecutive copies from the same source cell, e.g. `RC(<src>) -> (<dst1>);
+/// RC(<src>) -> (<dst2>)`, into a single instruction writing all destinations if the architecture
+/// provides an instruction with a matching output tuple. Unlike [`optimize_outputs`], this also
+/// merges copies whose source was not written by a preceding instruction of the program.
+pub fn coalesce_broadcasts<CT: CellType>(program: &mut Program<CT>, arch: &Architecture<CT>) {
+    let mut first_i = 0;
+    while first_i < program.0.len() {
+        let mut merged = None;
+        let mut last_i = first_i;
+        while let Some(instruction) = broadcast_instruction(program, arch, first_i, last_i + 1) {
+            merged = Some(instruction);
+            last_i += 1;
+        }
+        if let Some(instruction) = merged {
+            let Operation::Copy { from, .. } = &program.0[first_i] else {
+                unreachable!("broadcasts should consist of copies");
+            };
+            let comment = format!(
+                "broadcast copy from {from} to ({})",
+                instruction.outputs.iter().format(", ")
+            );
+            program.0.splice(
+                first_i..=last_i,
+                [Operation::Other {
+                    instructions: vec![instruction],
+                    comment: Some(comment),
+                }],
+            );
+        }
+        first_i += 1;
+    }
+}
+
+/// Returns a single instruction performing all copies `first_i..=last_i` of the program if they
+/// are single-instruction copies reading the same source, none of them writes a cell read by the
+/// others and the architecture has an instruction type writing all their destinations at once.
+fn broadcast_instruction<CT: CellType>(
+    program: &Program<CT>,
+    arch: &Architecture<CT>,
+    first_i: usize,
+    last_i: usize,
+) -> Option<Instruction<CT>> {
+    let mut source: Option<(Cell<CT>, &Instruction<CT>)> = None;
+    let mut outputs = Vec::new();
+    for op in program.0.get(first_i..=last_i)? {
+        let Operation::Copy {
+            from, instructions, ..
+        } = op
+        else {
+            return None;
+        };
+        // copies overriding their inputs write more than their destination
+        let [instr] = instructions.as_slice() else {
+            return None;
+        };
+        if instr.typ.input_override != InputIndices::None {
+            return None;
+        }
+        match source {
+            None => source = Some((*from, instr)),
+            Some((source_cell, first))
+                if source_cell == *from
+                    && first.inputs == instr.inputs
+                    && first.typ.function == instr.typ.function
+                    && first.typ.input_inverted == instr.typ.input_inverted
+                    && first.typ.input_range == instr.typ.input_range => {}
+            Some(_) => return None,
+        }
+        outputs.extend(instr.outputs.iter().copied());
+    }
+    let (_, first) = source?;
+
+    // the source has to stay untouched by all copies
+    if !outputs.iter().map(|op| op.cell).all_unique()
+        || outputs
+            .iter()
+            .any(|op| first.read_cells().contains(&op.cell))
+    {
+        return None;
+    }
+
+    arch.instructions()
+        .iter()
+        .filter(|typ| {
+            typ.function == first.typ.function
+                && typ.secondary.is_none()
+                && typ.input_override == first.typ.input_override
+                && typ.input_inverted == first.typ.input_inverted
+                && typ.input_range == first.typ.input_range
+                && typ.input.matches(&first.inputs)
+        })
+        .find_map(|typ| {
+            let outputs = outputs
+                .iter()
+                .copied()
+                .permutations(outputs.len())
+                .find(|outputs| typ.outputs.iter().any(|tuple| tuple.matches(outputs)))?;
+            Some(Instruction {
+                typ: typ.clone(),
+                inputs: first.inputs.clone(),
+                outputs,
+            })
+        })
+}
+
+/// Replaces pairs of inverting copies `a -> !b; b -> !c` by a single non-inverting copy `a -> c` if
+/// the copy graph contains a direct non-inverting edge that is at most as expensive as both
+/// inverting edges combined. The net inversion is preserved, hence this is only done if all cells
+/// written by the first copy (including `b`) are not read anywhere else before being overwritten.
+pub fn minimize_inversions<CT: CellType>(program: &mut Program<CT>, graph: &CopyGraph<CT>) {
+    let mut first_i = 0;
+    while first_i < program.0.len() {
+        match merge_inverting_copies(program, graph, first_i) {
+            Some((second_i, merged)) => {
+                program.0[second_i] = merged;
+                program.0.remove(first_i);
+            }
+            None => first_i += 1,
+        }
+    }
+}
+
+fn merge_inverting_copies<CT: CellType>(
+    program: &Program<CT>,
+    graph: &CopyGraph<CT>,
+    first_i: usize,
+) -> Option<(usize, Operation<CT>)> {
+    let Operation::Copy {
+        from: a,
+        to: b,
+        inverted: true,
+        instructions: first_instructions,
+        ..
+    } = &program.0[first_i]
+    else {
+        return None;
+    };
+    let (a, b) = (*a, *b);
+
+    // the next operation that accesses b has to be the second inverting copy, and a has to stay
+    // untouched until then
+    let second_i = (first_i + 1..program.0.len()).find(|&i| {
+        program.0[i]
+            .instructions()
+            .iter()
+            .any(|instr| instr.read_cells().chain(instr.write_cells()).contains(&b))
+    })?;
+    if program.0[first_i + 1..second_i]
+        .iter()
+        .flat_map(Operation::instructions)
+        .any(|instr| instr.write_cells().contains(&a))
+    {
+        return None;
+    }
+    let Operation::Copy {
+        from,
+        to: c,
+        inverted: true,
+        instructions: second_instructions,
+        spill,
+        ..
+    } = &program.0[second_i]
+    else {
+        return None;
+    };
+    if *from != b {
+        return None;
+    }
+
+    // all cells written by the first copy have to be dead after the second copy read b
+    let first_writes: FxHashSet<Cell<CT>> = first_instructions
+        .iter()
+        .flat_map(|instr| instr.write_cells())
+        .collect();
+    for &cell in &first_writes {
+        if !is_dead_after(program, cell, first_i, second_i, b) {
+            return None;
+        }
+    }
+
+    let optimal_edge = |from: Cell<CT>, to: Cell<CT>, inverted: bool| {
+        graph
+            .all_optimal_edges_matching(CellPat::Cell(from), CellPat::Cell(to), inverted)
+            .next()
+            .map(|(_, _, edge)| edge)
+    };
+    let previous_cost = optimal_edge(a, b, true)?.cost + optimal_edge(b, *c, true)?.cost;
+    let edge = optimal_edge(a, *c, false)?;
+    if edge.cost > previous_cost {
+        return None;
+    }
+
+    // the new copy may only clobber cells that are clobbered by the replaced copies anyway
+    let instructions = edge.instantiate(a, *c).collect_vec();
+    let allowed_writes: FxHashSet<Cell<CT>> = second_instructions
+        .iter()
+        .flat_map(|instr| instr.write_cells())
+        .chain(first_writes)
+        .collect();
+    if instructions
+        .iter()
+        .flat_map(|instr| instr.write_cells())
+        .any(|cell| !allowed_writes.contains(&cell))
+    {
+        return None;
+    }
+
+    Some((
+        second_i,
+        Operation::Copy {
+            from: a,
+            to: *c,
+            inverted: false,
+            instructions,
+            spill: *spill,
+            computes_from_inverted: edge.computes_from_inverted,
+        },
+    ))
+}
+
+/// Checks whether the value written to `cell` by operation `written_i` is not read by any
+/// operation except for `reader_i` reading `allowed_read`.
+fn is_dead_after<CT: CellType>(
+    program: &Program<CT>,
+    cell: Cell<CT>,
+    written_i: usize,
+    reader_i: usize,
+    allowed_read: Cell<CT>,
+) -> bool {
+    for (op_i, op) in program.0.iter().enumerate().skip(written_i + 1) {
+        for instr in op.instructions() {
+            if instr.read_cells().contains(&cell) && !(op_i == reader_i && cell == allowed_read) {
+                return false;
+            }
+            if instr.write_cells().contains(&cell) {
+                return true;
+            }
+        }
+    }
+    // cells of types with infinite cells may hold outputs of the program
+    cell.typ().count().is_some()
+}
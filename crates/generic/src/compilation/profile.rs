@@ -0,0 +1,104 @@
+use std::{
+    cell::Cell,
+    fmt::{self, Display, Formatter},
+    time::{Duration, Instant},
+};
+
+/// Phases of a compilation whose time is measured by a [`Profiler`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    /// Assigning the inputs of a gate to the operands of an instruction.
+    PositionSignals,
+    /// Placing the inputs of an instruction into its operand cells, including the required copies.
+    PlaceSignals,
+    /// Placing the outputs of the network into readable cells once all gates are computed.
+    Finalize,
+}
+
+/// Accumulates the time spent in the individual [`Phase`]s of a compilation, see
+/// [`CompilationParameters::profiler`].
+///
+/// [`CompilationParameters::profiler`]: crate::compilation::CompilationParameters::profiler
+#[derive(Debug, Default)]
+pub struct Profiler {
+    position_signals: Cell<Duration>,
+    place_signals: Cell<Duration>,
+    finalize: Cell<Duration>,
+}
+
+impl Profiler {
+    /// Measures the time until the returned timer is dropped and adds it to the given phase.
+    pub fn time(&self, phase: Phase) -> PhaseTimer<'_> {
+        PhaseTimer {
+            accumulated: self.phase(phase),
+            start: Instant::now(),
+        }
+    }
+
+    pub fn reset(&self) {
+        for phase in [Phase::PositionSignals, Phase::PlaceSignals, Phase::Finalize] {
+            self.phase(phase).set(Duration::ZERO);
+        }
+    }
+
+    /// Returns the times accumulated so far, attributing the remainder of the `total` time to
+    /// [`ProfileReport::other`].
+    pub fn report(&self, total: Duration) -> ProfileReport {
+        let position_signals = self.position_signals.get();
+        let place_signals = self.place_signals.get();
+        let finalize = self.finalize.get();
+        ProfileReport {
+            position_signals,
+            place_signals,
+            finalize,
+            other: total.saturating_sub(position_signals + place_signals + finalize),
+        }
+    }
+
+    fn phase(&self, phase: Phase) -> &Cell<Duration> {
+        match phase {
+            Phase::PositionSignals => &self.position_signals,
+            Phase::PlaceSignals => &self.place_signals,
+            Phase::Finalize => &self.finalize,
+        }
+    }
+}
+
+/// See [`Profiler::time`].
+pub struct PhaseTimer<'p> {
+    accumulated: &'p Cell<Duration>,
+    start: Instant,
+}
+
+impl Drop for PhaseTimer<'_> {
+    fn drop(&mut self) {
+        self.accumulated
+            .set(self.accumulated.get() + self.start.elapsed());
+    }
+}
+
+/// Time spent in the individual phases of a compilation.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ProfileReport {
+    pub position_signals: Duration,
+    pub place_signals: Duration,
+    pub finalize: Duration,
+    /// time not spent in any of the other phases, e.g. selecting candidates and replaying states
+    pub other: Duration,
+}
+
+impl ProfileReport {
+    pub fn total(&self) -> Duration {
+        self.position_signals + self.place_signals + self.finalize + self.other
+    }
+}
+
+impl Display for ProfileReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "position signals {:?}, place signals {:?}, finalize {:?}, other {:?}",
+            self.position_signals, self.place_signals, self.finalize, self.other
+        )
+    }
+}
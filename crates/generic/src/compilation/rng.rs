@@ -0,0 +1,27 @@
+use std::cell::{RefCell, RefMut};
+
+use rand::{SeedableRng, rngs::SmallRng};
+
+/// Source of randomness of a compilation, see [`CompilationParameters::rng`]. All randomized
+/// decisions draw from this single generator, hence compiling with the same seed reproduces the
+/// same program.
+///
+/// [`CompilationParameters::rng`]: crate::compilation::CompilationParameters::rng
+pub struct CompilerRng(RefCell<SmallRng>);
+
+impl CompilerRng {
+    pub fn new(seed: u64) -> Self {
+        Self(RefCell::new(SmallRng::seed_from_u64(seed)))
+    }
+
+    pub fn get(&self) -> RefMut<'_, SmallRng> {
+        self.0.borrow_mut()
+    }
+}
+
+impl Default for CompilerRng {
+    /// Returns a generator seeded with `0`.
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
@@ -6,17 +6,16 @@ use lime_generic_def::{
     Cell, CellPat, CellType, InputIndices, Instruction, InstructionType, Operand, PatBase, Pats,
     TuplesDef, set::Set,
 };
-use ordered_float::OrderedFloat;
 use pathfinding::{matrix::Matrix, prelude::kuhn_munkres_min};
 use rustc_hash::{FxHashMap, FxHashSet};
 
 use crate::{
-    compilation::{StepFn, candidate_selection::CandidateSelector},
+    compilation::{StepFn, candidate_selection::CandidateSelector, profile::Phase},
     copy::{
-        copy_cost, copy_cost_with_path, perform_copy,
+        copy_cost, copy_cost_with_path, perform_copy, repair_copy,
         spilling::{estimate_spill_cost_operand_pats, force_spill},
     },
-    cost::{Cost, OperationCost},
+    cost::{Cost, CostExt, OperationCost},
     program::{
         ProgramVersion,
         state::{CellStates, Operation},
@@ -42,15 +41,22 @@ impl<CT: CellType, G: Gate, C: OperationCost<CT>, CS: CandidateSelector> StepFn<
                 _ => continue,
             };
 
-            for instruction in params.arch.instructions().iter() {
-                if instruction.function.gate.gate_function() != Some(candidate_gate.function()) {
+            let instructions = params.arch.instructions();
+            for &instruction_id in instructions.ids_by_gate(candidate_gate.function()) {
+                if !params.is_instruction_enabled(instruction_id) {
                     continue;
                 }
+                let instruction = instructions.by_id(instruction_id);
                 if let Some(arity) = instruction.arity()
                     && arity != candidate_gate.inputs().len()
                 {
                     continue;
                 }
+                let Some(computed) =
+                    computed_candidates(candidate_id, candidate_gate, instruction, &version)
+                else {
+                    continue;
+                };
                 match &instruction.input {
                     TuplesDef::Tuples(tuples) => {
                         for tuple in tuples.iter() {
@@ -63,17 +69,14 @@ impl<CT: CellType, G: Gate, C: OperationCost<CT>, CS: CandidateSelector> StepFn<
                             ) else {
                                 continue;
                             };
-                            let mut version = version.branch();
-                            if let Some(version) = perform_operation(
-                                candidate_id,
+                            consider_operation(
+                                computed,
                                 &mut version,
                                 instruction,
                                 tuple.as_slice(),
                                 &signals,
                                 params,
-                            ) {
-                                version.consider();
-                            }
+                            );
                         }
                     }
                     TuplesDef::Nary(operands) => {
@@ -86,17 +89,14 @@ impl<CT: CellType, G: Gate, C: OperationCost<CT>, CS: CandidateSelector> StepFn<
                         ) else {
                             continue;
                         };
-                        let mut version = version.branch();
-                        if let Some(version) = perform_operation(
-                            candidate_id,
+                        consider_operation(
+                            computed,
                             &mut version,
                             instruction,
                             operands,
                             &signals,
                             params,
-                        ) {
-                            version.consider();
-                        }
+                        );
                     }
                 };
             }
@@ -104,33 +104,137 @@ impl<CT: CellType, G: Gate, C: OperationCost<CT>, CS: CandidateSelector> StepFn<
     }
 }
 
+/// Candidates computed by a single instruction.
+#[derive(Debug, Clone, Copy)]
+struct ComputedCandidates {
+    /// candidate computed into all operands written by the instruction except the secondary output
+    primary: Id,
+    /// candidate computed into the [secondary output](Instruction::secondary_output), if any
+    secondary: Option<Id>,
+}
+
+/// Returns the candidates computed when computing the given candidate with the instruction. If the
+/// instruction has a [secondary function](InstructionType::secondary), the candidate is paired
+/// with another candidate reading the same signals and computing the other function of the
+/// instruction, e.g. the sum and the carry of a full adder. Without such a candidate, only a
+/// candidate computing the primary function can be computed, leaving the secondary output unused.
+fn computed_candidates<V: ProgramVersion>(
+    candidate_id: Id,
+    candidate_gate: &V::G,
+    instruction: &InstructionType<V::CT>,
+    version: &V,
+) -> Option<ComputedCandidates> {
+    let Some(secondary) = instruction.secondary else {
+        return Some(ComputedCandidates {
+            primary: candidate_id,
+            secondary: None,
+        });
+    };
+    let is_primary = instruction.function.gate.gate_function() == Some(candidate_gate.function());
+    let partner_function = if is_primary {
+        secondary.gate.gate_function()
+    } else {
+        instruction.function.gate.gate_function()
+    };
+    let inputs = candidate_gate.inputs().iter().counts();
+    let partner = version.candidates().iter().copied().find(|&id| {
+        id != candidate_id
+            && match version.parameters().network.node(id) {
+                Node::Gate(gate) => {
+                    Some(gate.function()) == partner_function
+                        && gate.inputs().iter().counts() == inputs
+                }
+                _ => false,
+            }
+    });
+    match (is_primary, partner) {
+        (true, partner) => Some(ComputedCandidates {
+            primary: candidate_id,
+            secondary: partner,
+        }),
+        (false, Some(partner)) => Some(ComputedCandidates {
+            primary: partner,
+            secondary: Some(candidate_id),
+        }),
+        (false, None) => None,
+    }
+}
+
+/// Considers the version resulting from computing the candidate with the given instruction. If the
+/// operation is infeasible while [`CompilationParameters::disjunct_input_output`] is set, it is
+/// retried allowing the outputs to override the inputs, unless the instruction
+/// [requires disjoint inputs and outputs](InstructionType::requires_disjoint_io). Input signals
+/// overridden this way that are still needed elsewhere are spilled.
+fn consider_operation<V: ProgramVersion>(
+    computed: ComputedCandidates,
+    version: &mut V,
+    instruction: &InstructionType<V::CT>,
+    input: &(impl Index<usize, Output = Pats<CellPat<V::CT>>> + ?Sized),
+    signals: &[Signal],
+    params: &CompilationParameters<V::CT, V::G, V::C>,
+) {
+    let mut strict = version.branch();
+    if let Some(strict) = perform_operation(
+        computed,
+        &mut strict,
+        instruction,
+        input,
+        signals,
+        params,
+        params.disjunct_input_output,
+    ) {
+        strict.consider();
+        return;
+    }
+    drop(strict);
+    if !params.disjunct_input_output || instruction.requires_disjoint_io {
+        return;
+    }
+    let mut relaxed = version.branch();
+    if let Some(relaxed) = perform_operation(
+        computed,
+        &mut relaxed,
+        instruction,
+        input,
+        signals,
+        params,
+        false,
+    ) {
+        relaxed.consider();
+    }
+}
+
 #[must_use]
 fn perform_operation<'v, V: ProgramVersion>(
-    candidate_id: Id,
+    computed: ComputedCandidates,
     version: &'v mut V,
     instruction: &InstructionType<V::CT>,
     input: &(impl Index<usize, Output = Pats<CellPat<V::CT>>> + ?Sized),
     signals: &[Signal],
     params: &CompilationParameters<V::CT, V::G, V::C>,
+    disjunct_input_output: bool,
 ) -> Option<impl ProgramVersion<CT = V::CT, G = V::G, C = V::C> + 'v> {
     let mut used_cells = FxHashSet::default();
 
     // == place inputs
-    let inputs = place_signals(
-        input,
-        instruction.input_inverted,
-        signals,
-        params,
-        version,
-        &mut used_cells,
-    )?;
+    let inputs = {
+        let _timer = params.time(Phase::PlaceSignals);
+        place_signals(
+            input,
+            instruction.input_inverted,
+            signals,
+            params,
+            version,
+            &mut used_cells,
+        )
+    }?;
     let mut result = Instruction {
         typ: instruction.clone(),
         inputs,
         outputs: Vec::new(),
     };
 
-    if !params.disjunct_input_output {
+    if !disjunct_input_output && !instruction.requires_disjoint_io {
         used_cells.clear();
     }
 
@@ -201,8 +305,12 @@ fn perform_operation<'v, V: ProgramVersion>(
     result.outputs = outputs;
 
     spill_necessary(version, &result);
-    apply_state(candidate_id, version, &result);
-    version.append(Operation::Candidate(result, candidate_id));
+    apply_state(computed, version, &result);
+    version.append(Operation::Candidate(
+        result,
+        computed.primary,
+        computed.secondary,
+    ));
     Some(version.branch())
 }
 
@@ -225,12 +333,16 @@ fn spill_necessary<V: ProgramVersion>(version: &mut V, instruction: &Instruction
     }
 }
 
-fn apply_state<V: ProgramVersion>(computed: Id, version: &mut V, instruction: &Instruction<V::CT>) {
+fn apply_state<V: ProgramVersion>(
+    computed: ComputedCandidates,
+    version: &mut V,
+    instruction: &Instruction<V::CT>,
+) {
     for operand in instruction.overridden_input_operands() {
         version.state_mut().set(
             operand.cell,
             Signal::new(
-                computed,
+                computed.primary,
                 operand.inverted ^ instruction.typ.function.inverted,
             ),
         );
@@ -239,11 +351,18 @@ fn apply_state<V: ProgramVersion>(computed: Id, version: &mut V, instruction: &I
         version.state_mut().set(
             output.cell,
             Signal::new(
-                computed,
+                computed.primary,
                 output.inverted ^ instruction.typ.function.inverted,
             ),
         );
     }
+    if let Some(output) = instruction.secondary_output() {
+        let signal = computed
+            .secondary
+            .zip(instruction.typ.secondary)
+            .map(|(id, function)| Signal::new(id, output.inverted ^ function.inverted));
+        version.state_mut().set(output.cell, signal);
+    }
 }
 
 pub(super) fn place_signals<V: ProgramVersion>(
@@ -257,6 +376,10 @@ pub(super) fn place_signals<V: ProgramVersion>(
     let mut placed_signals = vec![false; signals.len()];
     let mut cells = FxHashMap::default();
     for _ in 0..signals.len() {
+        let pins = signals
+            .iter()
+            .map(|signal| available_pin(*signal, params, version, used_cells))
+            .collect_vec();
         // place the next cheapest signal
         let sig = signals
             .iter()
@@ -264,30 +387,61 @@ pub(super) fn place_signals<V: ProgramVersion>(
             .filter(|(input_idx, _)| !placed_signals[*input_idx])
             .flat_map(|(input_idx, signal)| {
                 let target_inverted = input_invert.contains(&input_idx);
+                let pin = pins[input_idx];
                 input[input_idx]
                     .iter()
                     // convince the borrow checker that this is fine
-                    .map(move |cell_pat| (cell_pat, target_inverted))
+                    .map(move |&cell_pat| {
+                        // move the signal to its pin if the operand can be placed there
+                        let pat = pin
+                            .filter(|pin| cell_pat.matches(pin))
+                            .map_or(cell_pat, CellPat::Cell);
+                        (pat, target_inverted)
+                    })
                     .flat_map(|(target_cell_pat, target_inverted)| {
                         version
                             .state()
                             .all_cells_with(*signal)
                             .filter_map(|(source_cell, source_cell_inverted)| {
                                 let requires_inversion = source_cell_inverted ^ target_inverted;
+                                let source_cost = params.cost.cell_access_cost(source_cell);
                                 if !requires_inversion
                                     && target_cell_pat.matches(&source_cell)
                                     && !used_cells.contains(&source_cell)
                                 {
-                                    Some((OrderedFloat(0.0), source_cell, None))
+                                    Some(((source_cost, false), source_cell, None))
                                 } else {
+                                    // if the signal is already placed as another operand (e.g. in
+                                    // the opposite polarity), prefer copying from there over
+                                    // materializing the signal again from an equally cheap source
+                                    let fresh_source = !used_cells.contains(&source_cell);
+                                    // the copy target is chosen in the same way by perform_copy
+                                    let target_cost = version
+                                        .find_preferred_free_cell_for_pat(
+                                            target_cell_pat,
+                                            &*used_cells,
+                                        )
+                                        .map(|cell| params.cost.cell_access_cost(cell))
+                                        .unwrap_or_default();
                                     copy_cost_with_path(
                                         &params.arch.copy_graph,
                                         source_cell,
-                                        *target_cell_pat,
+                                        target_cell_pat,
                                         requires_inversion,
                                         used_cells,
+                                        None,
                                     )
-                                    .map(|(cost, path)| (cost, source_cell, Some(path)))
+                                    .map(|(cost, path)| {
+                                        (
+                                            (
+                                                cost.checked_add(source_cost)
+                                                    .checked_add(target_cost),
+                                                fresh_source,
+                                            ),
+                                            source_cell,
+                                            Some(path),
+                                        )
+                                    })
                                 }
                             })
                             .map(move |(cost, from, path)| (cost, target_cell_pat, from, path))
@@ -301,7 +455,11 @@ pub(super) fn place_signals<V: ProgramVersion>(
         let (_, signal_idx, target_cell_pat, from, path) = sig?;
         placed_signals[signal_idx] = true;
         let target_cell = if let Some(path) = path {
-            perform_copy(path, version, from, *target_cell_pat, used_cells)?
+            match perform_copy(path, version, from, target_cell_pat, used_cells) {
+                Ok(cell) => cell,
+                // keep the part of the path that was already copied instead of giving up
+                Err(partial) => repair_copy(partial, version, target_cell_pat, used_cells).ok()?,
+            }
         } else {
             from
         };
@@ -316,6 +474,21 @@ pub(super) fn place_signals<V: ProgramVersion>(
     Some(result)
 }
 
+/// Returns the cell the signal is [pinned](CompilationParameters::signal_pins) to if it can be
+/// placed there, i.e. if the cell is not used as another operand and either free or already holds
+/// the signal.
+fn available_pin<V: ProgramVersion>(
+    signal: Signal,
+    params: &CompilationParameters<V::CT, V::G, V::C>,
+    version: &V,
+    used_cells: &FxHashSet<Cell<V::CT>>,
+) -> Option<Cell<V::CT>> {
+    let pin = *params.signal_pins.get(&signal)?;
+    let available =
+        !used_cells.contains(&pin) && version.state().cell(pin).is_none_or(|held| held == signal);
+    available.then_some(pin)
+}
+
 fn position_signals<V: ProgramVersion>(
     instruction: &InstructionType<V::CT>,
     input: &(impl Index<usize, Output = Pats<CellPat<V::CT>>> + ?Sized),
@@ -323,13 +496,14 @@ fn position_signals<V: ProgramVersion>(
     params: &CompilationParameters<V::CT, V::G, V::C>,
     version: &V,
 ) -> Option<Vec<Signal>> {
+    let _timer = params.time(Phase::PositionSignals);
     let input = instruction.input_range.index_view(input);
     let input_offset = instruction.input_range.start_offset();
     let arity = gate.inputs().len();
 
-    fn cost_to_f64<I: Into<Option<Cost>>>(cost: I) -> OrderedFloat<f64> {
+    fn cost_to_f64<I: Into<Option<Cost>>>(cost: I) -> Cost {
         match cost.into() {
-            None => OrderedFloat(f64::INFINITY),
+            None => Cost::INFINITY,
             Some(value) => value,
         }
     }
@@ -340,6 +514,8 @@ fn position_signals<V: ProgramVersion>(
         .collect_vec();
 
     let mut matrix = Matrix::new_square(arity, Default::default());
+    // whether the signal is already available in a cell matching the operand
+    let mut direct_matches = Matrix::new_square(arity, false);
     for operand_idx in 0..arity {
         for signal_idx in 0..arity {
             let signal = gate.inputs()[signal_idx];
@@ -355,9 +531,10 @@ fn position_signals<V: ProgramVersion>(
                         .all_cells_with(signal)
                         .filter_map(|(source_cell, source_cell_inverted)| {
                             let requires_inversion = source_cell_inverted ^ target_cell_inverted;
+                            let source_cost = params.cost.cell_access_cost(source_cell);
                             if !requires_inversion && target_cell_pat.matches(&source_cell) {
                                 has_match = true;
-                                Some(OrderedFloat(0.0))
+                                Some(source_cost)
                             } else {
                                 copy_cost(
                                     &params.arch.copy_graph,
@@ -365,7 +542,9 @@ fn position_signals<V: ProgramVersion>(
                                     *target_cell_pat,
                                     requires_inversion,
                                     &FxHashSet::default(),
+                                    None,
                                 )
+                                .map(|cost| cost.checked_add(source_cost))
                             }
                         })
                         .min()
@@ -373,9 +552,10 @@ fn position_signals<V: ProgramVersion>(
                 .min();
             // add estimated spilling cost for replacing current value
             if !has_match && !version.has_free_cell_for_cell_pats(&input[operand_idx]) {
-                min_cost = min_cost.map(|cost| cost + spilling_costs[operand_idx]);
+                min_cost = min_cost.map(|cost| cost.checked_add(spilling_costs[operand_idx]));
             }
             matrix[(operand_idx, signal_idx)] = cost_to_f64(min_cost);
+            direct_matches[(operand_idx, signal_idx)] = has_match;
         }
     }
 
@@ -391,7 +571,7 @@ fn position_signals<V: ProgramVersion>(
             // - the value does not exist somewhere and
             // - we will need it again later and
             // then we will most likely need to spill it
-            if matrix[(operand_idx, signal_idx)] == 0.0
+            if direct_matches[(operand_idx, signal_idx)]
                 && version
                     .state()
                     .cells_with_id(signal.node_id())
@@ -404,16 +584,16 @@ fn position_signals<V: ProgramVersion>(
         }
     }
 
-    // check that matrix has an optimal selection
+    // check that matrix has an optimal selection, otherwise the instruction is not applicable
     for i in 0..arity {
         let mut row_has_sol = false;
         let mut col_has_sol = false;
         for j in 0..arity {
-            row_has_sol |= matrix[(i, j)] != f64::INFINITY;
-            col_has_sol |= matrix[(j, i)] != f64::INFINITY;
+            row_has_sol |= matrix[(i, j)] != Cost::INFINITY;
+            col_has_sol |= matrix[(j, i)] != Cost::INFINITY;
         }
         if !row_has_sol || !col_has_sol {
-            panic!("impossible {}\n{gate:?}", version.program());
+            return None;
         }
     }
 
@@ -0,0 +1,93 @@
+use std::io::Write;
+
+use eggmock::Id;
+use lime_generic_def::CellType;
+
+use crate::{
+    cost::{Cost, OperationCost},
+    program::state::{Operation, StateDelta},
+};
+
+/// A node of the exhaustive search tree, i.e. a state reached by applying one delta to the state
+/// of its parent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SearchNode {
+    pub id: usize,
+    /// `None` for the root, which represents the initial state
+    pub parent: Option<usize>,
+    /// candidate computed by the delta
+    pub candidate: Option<Id>,
+    /// cost of the delta
+    pub cost: Cost,
+}
+
+/// Receives the nodes of the exhaustive search tree in the order in which they are visited.
+pub trait SearchTracer {
+    fn visit(&mut self, node: SearchNode);
+}
+
+impl SearchTracer for Vec<SearchNode> {
+    fn visit(&mut self, node: SearchNode) {
+        self.push(node);
+    }
+}
+
+/// Streams the visited nodes to a writer, one JSON object per line.
+pub struct JsonTracer<W>(pub W);
+
+impl<W: Write> SearchTracer for JsonTracer<W> {
+    fn visit(&mut self, node: SearchNode) {
+        let parent = node
+            .parent
+            .map_or_else(|| "null".to_string(), |parent| parent.to_string());
+        let candidate = node
+            .candidate
+            .map_or_else(|| "null".to_string(), |id| usize::from(id).to_string());
+        let result = writeln!(
+            self.0,
+            r#"{{"id":{},"parent":{parent},"candidate":{candidate},"cost":{}}}"#,
+            node.id, node.cost
+        );
+        if let Err(err) = result {
+            eprintln!("could not write search trace: {err}");
+        }
+    }
+}
+
+/// Assigns ids to the visited nodes and forwards them to a [`SearchTracer`].
+pub(super) struct Trace<'t> {
+    tracer: &'t mut dyn SearchTracer,
+    num_nodes: usize,
+}
+
+impl<'t> Trace<'t> {
+    pub fn new(tracer: &'t mut dyn SearchTracer) -> Self {
+        Self {
+            tracer,
+            num_nodes: 0,
+        }
+    }
+
+    /// Records the node reached by applying the delta and returns its id.
+    pub fn visit<CT: CellType>(
+        &mut self,
+        parent: Option<usize>,
+        delta: &StateDelta<CT>,
+        cost: &impl OperationCost<CT>,
+    ) -> usize {
+        let id = self.num_nodes;
+        self.num_nodes += 1;
+        let program = delta.program_delta();
+        let candidate = program.0.iter().find_map(|op| match op {
+            Operation::Candidate(_, id, _) => Some(*id),
+            _ => None,
+        });
+        self.tracer.visit(SearchNode {
+            id,
+            parent,
+            candidate,
+            cost: cost.program_cost(program),
+        });
+        id
+    }
+}
@@ -0,0 +1,160 @@
+use std::rc::Rc;
+
+use eggmock::{Gate, Id, Node, Signal};
+use lime_generic_def::{Cell, CellType, Function, Instruction};
+use rustc_hash::FxHashSet;
+
+use crate::{
+    compilation::CompilationParameters,
+    cost::OperationCost,
+    program::{
+        DummyProgramVersion, ProgramVersion,
+        state::{CellStates, Operation, StateSavepoint},
+    },
+};
+
+/// Seeds the given state with the operations of [`CompilationParameters::warm_start`].
+///
+/// The program is replayed in segments, each consisting of the copies leading up to a computed
+/// candidate and the candidate itself. Replaying stops at the first segment that does not match
+/// the network, e.g. because a gate was changed or a value would be overridden that is still
+/// required. Such a segment is discarded entirely, the rest of the network is then compiled as
+/// usual. Returns the number of candidates that were replayed.
+pub fn replay_warm_start<CT: CellType, G: Gate, C: OperationCost<CT>>(
+    state: &mut StateSavepoint<CT, G>,
+    params: &Rc<CompilationParameters<CT, G, C>>,
+) -> usize {
+    let Some(program) = &params.warm_start else {
+        return 0;
+    };
+    let mut operations = program.0.iter();
+    let mut replayed = 0;
+    loop {
+        let mut segment = state.savepoint();
+        let mut version = DummyProgramVersion::new(&mut segment, params);
+        let complete = loop {
+            match operations.next() {
+                Some(operation @ Operation::Copy { .. }) => {
+                    if !replay_copy(&mut version, operation) {
+                        break false;
+                    }
+                }
+                Some(operation @ Operation::Candidate(..)) => {
+                    break replay_candidate(&mut version, operation);
+                }
+                // optimized operations cannot be attributed to the network anymore
+                Some(Operation::Other { .. }) | None => break false,
+            }
+        };
+        if !complete {
+            return replayed;
+        }
+        segment.retain();
+        replayed += 1;
+    }
+}
+
+fn replay_copy<V: ProgramVersion>(version: &mut V, operation: &Operation<V::CT>) -> bool {
+    let Operation::Copy {
+        from, to, inverted, ..
+    } = operation
+    else {
+        unreachable!("operation should be a copy");
+    };
+    let Some(signal) = version.state().cell(*from) else {
+        return false;
+    };
+    // spills are part of the replayed program, values that are still required must not be lost
+    if let Some(previous) = version.state().cell(*to)
+        && !is_stored_elsewhere(version, previous, &FxHashSet::from_iter([*to]))
+    {
+        return false;
+    }
+    version.state_mut().set(*to, signal ^ *inverted);
+    version.append(operation.clone());
+    true
+}
+
+fn replay_candidate<V: ProgramVersion>(version: &mut V, operation: &Operation<V::CT>) -> bool {
+    let Operation::Candidate(instruction, id, secondary_id) = operation else {
+        unreachable!("operation should be a candidate");
+    };
+    let computes = |version: &V, id: Id, function: Option<Function>| {
+        if !version.candidates().contains(&id) {
+            return false;
+        }
+        let Node::Gate(gate) = version.parameters().network.node(id) else {
+            return false;
+        };
+        function.and_then(|function| function.gate.gate_function()) == Some(gate.function())
+            && reads_signals(version, instruction, gate.inputs())
+    };
+    if !computes(version, *id, Some(instruction.typ.function))
+        || secondary_id
+            .is_some_and(|secondary_id| !computes(version, secondary_id, instruction.typ.secondary))
+    {
+        return false;
+    }
+    // the computed value may only replace values that are not required anymore
+    let written = instruction.write_cells().collect::<FxHashSet<_>>();
+    for &cell in &written {
+        if let Some(previous) = version.state().cell(cell)
+            && !version.is_last_use(previous.node_id())
+            && !is_stored_elsewhere(version, previous, &written)
+        {
+            return false;
+        }
+    }
+    let inverted = instruction.typ.function.inverted;
+    for operand in instruction.overridden_input_operands() {
+        version
+            .state_mut()
+            .set(operand.cell, Signal::new(*id, operand.inverted ^ inverted));
+    }
+    for output in &instruction.outputs {
+        version
+            .state_mut()
+            .set(output.cell, Signal::new(*id, output.inverted ^ inverted));
+    }
+    if let Some(output) = instruction.secondary_output() {
+        let signal = secondary_id
+            .zip(instruction.typ.secondary)
+            .map(|(id, function)| Signal::new(id, output.inverted ^ function.inverted));
+        version.state_mut().set(output.cell, signal);
+    }
+    version.append(operation.clone());
+    true
+}
+
+/// Whether the instruction reads exactly the given signals, in any order.
+fn reads_signals<V: ProgramVersion>(
+    version: &V,
+    instruction: &Instruction<V::CT>,
+    signals: &[Signal],
+) -> bool {
+    let (offset, read, _) = instruction.typ.input_range.slice(&instruction.inputs);
+    let mut expected = signals.to_vec();
+    for (i, cell) in read.iter().enumerate() {
+        let Some(signal) = version.state().cell(*cell) else {
+            return false;
+        };
+        let signal = signal ^ instruction.typ.input_inverted.contains(&(offset + i));
+        let Some(idx) = expected.iter().position(|expected| *expected == signal) else {
+            return false;
+        };
+        expected.swap_remove(idx);
+    }
+    expected.is_empty()
+}
+
+/// Whether the value of the signal is also available in a cell that is not written.
+fn is_stored_elsewhere<V: ProgramVersion>(
+    version: &V,
+    signal: Signal,
+    written: &FxHashSet<Cell<V::CT>>,
+) -> bool {
+    version
+        .state()
+        .cells_with_id(signal.node_id())
+        .any(|(other, _)| !written.contains(&other))
+}
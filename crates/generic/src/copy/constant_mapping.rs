@@ -151,7 +151,7 @@ where
                     return None;
                 }
                 let mut new_eval = eval;
-                new_eval.add(*value ^ self.inverted.contains(&index));
+                new_eval.add(index, *value ^ self.inverted.contains(&index));
                 Some((cell, new_eval))
             })
         })
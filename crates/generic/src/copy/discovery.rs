@@ -1,9 +1,7 @@
 use std::cmp::min;
 
-use itertools::Itertools;
 use lime_generic_def::{
-    BoolHint, Cell, CellPat, CellType, InputIndices, Instruction, InstructionType, Operand,
-    set::Set,
+    BoolHint, Cell, CellType, InputIndices, Instruction, InstructionType, Operand, set::Set,
 };
 use tracing::warn;
 
@@ -13,7 +11,7 @@ use crate::{
         graph::{Edge, FROM_VAR, FindParams, TO_VAR},
         placeholder::CellOrVar,
     },
-    cost::OperationCost,
+    cost::{CostExt, OperationCost},
 };
 
 pub fn find_copy_instructions<CT: CellType, CF: OperationCost<CT>>(
@@ -21,6 +19,10 @@ pub fn find_copy_instructions<CT: CellType, CF: OperationCost<CT>>(
 ) {
     let arch = params.arch.clone();
     for instruction in arch.instructions().iter() {
+        // the secondary output of such instructions does not receive the copied value
+        if !params.is_enabled(instruction) || instruction.secondary.is_some() {
+            continue;
+        }
         for inverted in [true, false] {
             find_using_input_override(params, instruction, inverted);
             find_using_output(params, instruction, inverted);
@@ -63,24 +65,15 @@ fn find_using_input_override<CT: CellType, CF: OperationCost<CT>>(
                 //    constant value)
                 let mut templates = Vec::new();
                 if let BoolHint::Require(to_value) = to_hint {
-                    eval.add(to_value);
+                    eval.add(to_idx, to_value);
 
-                    let const_cell = CT::constant(to_value);
-                    for (_, _, edge) in params.graph.all_optimal_edges_matching(
-                        CellPat::Cell(const_cell),
+                    templates.extend_from_slice(params.optimal_templates(
+                        CT::constant(to_value),
                         to,
                         typ.input_inverted.contains(&to_idx),
-                    ) {
-                        templates.push(
-                            edge.instantiate(
-                                const_cell.map_cell_type(CellOrVar::from),
-                                Cell::new(CellOrVar::Var, TO_VAR),
-                            )
-                            .collect_vec(),
-                        )
-                    }
+                    ));
                 } else {
-                    eval.add_unknown();
+                    eval.add_unknown(to_idx);
                     templates.push(vec![]);
                 };
                 if eval.id_inverted() != Some(inverted) {
@@ -103,12 +96,12 @@ fn find_using_input_override<CT: CellType, CF: OperationCost<CT>>(
                         inputs: mapping.clone(),
                         outputs: vec![],
                     };
-                    let cost = params.cost.cost(&instruction);
-                    let cost = template
-                        .iter()
-                        .fold(cost, |cost, op| cost + params.cost.cost(op));
+                    let cost = params.cost.operation_cost(&instruction);
+                    let cost = template.iter().fold(cost, |cost, op| {
+                        cost.checked_add(params.cost.operation_cost(op))
+                    });
                     template.push(instruction);
-                    params.graph.consider_edge(
+                    params.consider_edge(
                         *from,
                         to,
                         Edge {
@@ -168,8 +161,8 @@ fn find_using_output<CT: CellType, CF: OperationCost<CT>>(
                             inverted: output.inverted,
                         }],
                     };
-                    let cost = params.cost.cost(&instruction);
-                    params.graph.consider_edge(
+                    let cost = params.cost.operation_cost(&instruction);
+                    params.consider_edge(
                         *from,
                         output.cell,
                         Edge {
@@ -18,6 +18,10 @@ use crate::{
 pub fn find_set_constant<CT: CellType, CF: OperationCost<CT>>(params: &mut FindParams<'_, CT, CF>) {
     let arch = params.arch.clone();
     for instruction in arch.instructions().iter() {
+        // the secondary output of such instructions does not receive the copied value
+        if !params.is_enabled(instruction) || instruction.secondary.is_some() {
+            continue;
+        }
         for value in [true, false] {
             find_for_output(params, instruction, value);
             find_for_input_result(params, instruction, value);
@@ -51,7 +55,7 @@ fn find_for_input_result<CT: CellType, CF: OperationCost<CT>>(
                 None | Some(BoolHint::Require(_)) => continue,
                 _ => {}
             }
-            eval.add_unknown();
+            eval.add_unknown(target_idx);
             let result_value = eval.evaluate();
             if result_value != Some(value) {
                 warn!(
@@ -141,9 +145,8 @@ fn add_edges<CT: CellType, CF: OperationCost<CT>>(
     to: CellPat<CT>,
     eval_value: bool,
 ) {
-    let cost = params.cost.cost(&instruction);
+    let cost = params.cost.operation_cost(&instruction);
     for inverted in [true, false] {
-        let graph = &mut params.graph;
         let from_node = CellPat::Cell(CT::constant(value ^ inverted));
 
         let edge = Edge {
@@ -152,6 +155,6 @@ fn add_edges<CT: CellType, CF: OperationCost<CT>>(
             template: vec![instruction.clone()],
             cost,
         };
-        graph.consider_edge(from_node, to, edge);
+        params.consider_edge(from_node, to, edge);
     }
 }
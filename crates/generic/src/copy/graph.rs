@@ -6,7 +6,8 @@ use std::{
 
 use either::Either;
 use lime_generic_def::{
-    Architecture, Cell, CellIndex, CellPat, CellType, Instruction, Operand, PatBase,
+    Architecture, Cell, CellIndex, CellPat, CellType, Instruction, InstructionType, Operand,
+    PatBase,
 };
 use rustc_hash::{FxHashMap, FxHashSet};
 
@@ -68,27 +69,167 @@ pub struct CopyGraph<CT> {
     pub(super) nodes: TypeNodes<CT, FromTypeNode<CT>>,
 }
 
+pub type Template<CT> = Vec<Instruction<CellOrVar<CT>, CT>>;
+
 pub struct FindParams<'a, CT, OC: OperationCost<CT>> {
     pub arch: &'a Architecture<CT>,
     pub cost: &'a OC,
     pub graph: &'a mut CopyGraph<CT>,
+    /// ids of the instructions that may be used, all if `None`
+    pub enabled: Option<&'a FxHashSet<u8>>,
+    /// cache of [`FindParams::optimal_templates`]
+    templates: FxHashMap<(Cell<CT>, CellPat<CT>, bool), Vec<Template<CT>>>,
+    /// see [`CopyGraph::update_for_instruction`]
+    update: Option<InstructionUpdate<CT>>,
+}
+
+/// Restricts the edges added by [`FindParams::consider_edge`] to the ones that may have changed due
+/// to the changed cost of an instruction.
+struct InstructionUpdate<CT> {
+    instruction: u8,
+    /// (from, to) cell types whose edges were removed and are hence derived again
+    cleared: FxHashSet<(CT, CT)>,
+}
+
+impl<'a, CT: CellType, OC: OperationCost<CT>> FindParams<'a, CT, OC> {
+    pub fn new(
+        arch: &'a Architecture<CT>,
+        cost: &'a OC,
+        graph: &'a mut CopyGraph<CT>,
+        enabled: Option<&'a FxHashSet<u8>>,
+    ) -> Self {
+        Self {
+            arch,
+            cost,
+            graph,
+            enabled,
+            templates: FxHashMap::default(),
+            update: None,
+        }
+    }
+
+    pub fn is_enabled(&self, instruction: &InstructionType<CT>) -> bool {
+        self.enabled
+            .is_none_or(|enabled| enabled.contains(&instruction.id))
+    }
+
+    /// Returns the templates of [`CopyGraph::all_optimal_edges_matching`] from the given cell,
+    /// instantiated with [`TO_VAR`] as the target. The results are cached until an edge from a
+    /// cell of the same type is added via [`FindParams::consider_edge`].
+    pub fn optimal_templates(
+        &mut self,
+        from: Cell<CT>,
+        to: CellPat<CT>,
+        inverted: bool,
+    ) -> &[Template<CT>] {
+        let graph = &*self.graph;
+        self.templates
+            .entry((from, to, inverted))
+            .or_insert_with(|| {
+                graph
+                    .all_optimal_edges_matching(CellPat::Cell(from), to, inverted)
+                    .map(|(_, _, edge)| {
+                        edge.instantiate(
+                            from.map_cell_type(CellOrVar::from),
+                            Cell::new(CellOrVar::Var, TO_VAR),
+                        )
+                        .collect()
+                    })
+                    .collect()
+            })
+    }
+
+    /// Adds the edge to the graph, see [`CopyGraph::consider_edge`].
+    pub fn consider_edge(&mut self, from: CellPat<CT>, to: CellPat<CT>, edge: Edge<CT>) {
+        if let Some(update) = &self.update
+            && !update.cleared.contains(&(from.cell_type(), to.cell_type()))
+            && !edge.uses_instruction(update.instruction)
+        {
+            // the edge was already considered when building the graph
+            return;
+        }
+        self.templates
+            .retain(|(cached, _, _), _| cached.typ() != from.cell_type());
+        self.graph.consider_edge(from, to, edge);
+    }
 }
 
 impl<CT: CellType> CopyGraph<CT> {
     pub fn build(arch: &Architecture<CT>, cost: &impl OperationCost<CT>) -> Self {
+        Self::build_with_instructions(arch, cost, None)
+    }
+
+    /// Like [`CopyGraph::build`], but only uses the instructions with the given ids if `enabled`
+    /// is set.
+    pub fn build_with_instructions(
+        arch: &Architecture<CT>,
+        cost: &impl OperationCost<CT>,
+        enabled: Option<&FxHashSet<u8>>,
+    ) -> Self {
         let mut graph = Self {
             nodes: Default::default(),
         };
-        let mut params = FindParams {
-            arch,
-            cost,
-            graph: &mut graph,
-        };
+        let mut params = FindParams::new(arch, cost, &mut graph, enabled);
         find_set_constant(&mut params);
         find_copy_instructions(&mut params);
         graph
     }
 
+    /// Updates the graph after the cost of the instruction with the given id changed, i.e.
+    /// `cost` differs from the cost function the graph was built with only for this instruction.
+    /// The graph has to be built with all instructions enabled.
+    ///
+    /// Only the edges between cell types that are connected by an edge using the instruction are
+    /// derived again. The remaining ones are only compared to the edges using the instruction,
+    /// which may have become cheaper. The optimal costs equal the ones of a graph built from
+    /// scratch with `cost`.
+    pub fn update_for_instruction(
+        &mut self,
+        arch: &Architecture<CT>,
+        cost: &impl OperationCost<CT>,
+        instruction_id: u8,
+    ) {
+        let mut cleared = FxHashSet::default();
+        for (from_typ, from_typenode) in &self.nodes.0 {
+            for to_typenodes in
+                iter::once(&from_typenode.value).chain(from_typenode.children.values())
+            {
+                for (to_typ, to_typenode) in &to_typenodes.0 {
+                    if to_typenode
+                        .children
+                        .values()
+                        .chain(iter::once(&to_typenode.value))
+                        .flatten()
+                        .flatten()
+                        .any(|edge| edge.uses_instruction(instruction_id))
+                    {
+                        cleared.insert((*from_typ, *to_typ));
+                    }
+                }
+            }
+        }
+        for (from_typ, from_typenode) in &mut self.nodes.0 {
+            let clear = |to_typenodes: &mut FromTypeNode<CT>| {
+                to_typenodes
+                    .0
+                    .retain(|to_typ, _| !cleared.contains(&(*from_typ, *to_typ)));
+            };
+            clear(&mut from_typenode.value);
+            from_typenode.children.retain(|_, to_typenodes| {
+                clear(to_typenodes);
+                !to_typenodes.0.is_empty()
+            });
+        }
+
+        let mut params = FindParams::new(arch, cost, self, None);
+        params.update = Some(InstructionUpdate {
+            instruction: instruction_id,
+            cleared,
+        });
+        find_set_constant(&mut params);
+        find_copy_instructions(&mut params);
+    }
+
     pub fn nodes(&self) -> FxHashSet<CellPat<CT>> {
         let mut result = FxHashSet::default();
         for (src_typ, src_typenode) in &self.nodes.0 {
@@ -186,6 +327,68 @@ impl<CT: CellType> CopyGraph<CT> {
             .flatten()
     }
 
+    /// Enumerates all copy paths from `from` to `to` with at most `max_len` edges that invert the
+    /// value iff `invert` is set. Each path consists of the edges taken together with the pattern
+    /// they lead to. Patterns are not visited twice within a path.
+    ///
+    /// Unlike [`copy_cost_with_path`](crate::copy::copy_cost_with_path), this does not only return
+    /// the cheapest path. The number of paths grows exponentially with `max_len`.
+    pub fn all_paths(
+        &self,
+        from: CellPat<CT>,
+        to: CellPat<CT>,
+        invert: bool,
+        max_len: usize,
+    ) -> Vec<Vec<(CellPat<CT>, &Edge<CT>)>> {
+        let mut paths = Vec::new();
+        let mut path = Vec::new();
+        self.all_paths_recurse(from, to, invert, max_len, &mut path, &mut paths);
+        paths
+    }
+
+    fn all_paths_recurse<'g>(
+        &'g self,
+        from: CellPat<CT>,
+        to: CellPat<CT>,
+        invert: bool,
+        max_len: usize,
+        path: &mut Vec<(CellPat<CT>, &'g Edge<CT>)>,
+        paths: &mut Vec<Vec<(CellPat<CT>, &'g Edge<CT>)>>,
+    ) {
+        if path.len() >= max_len {
+            return;
+        }
+        let current = path.last().map_or(from, |(node, _)| *node);
+        let inverted = path
+            .iter()
+            .fold(false, |inverted, (_, edge)| inverted ^ edge.inverted);
+        let Some(typenode) = self.nodes.0.get(&current.cell_type()) else {
+            return;
+        };
+        // a type pattern may be instantiated with any of its cells
+        let sources = iter::once(&typenode.value).chain(match current {
+            CellPat::Type(_) => Either::Left(typenode.children.values()),
+            CellPat::Cell(cell) => Either::Right(typenode.children.get(&cell.index()).into_iter()),
+        });
+        for (next, edges) in sources.flat_map(|typenodes| typenodes.iter()) {
+            if next == from || path.iter().any(|(node, _)| *node == next) {
+                continue;
+            }
+            for edge in edges.iter().flatten() {
+                path.push((next, edge));
+                let matches = next == to
+                    || (next.cell_type() == to.cell_type()
+                        && (next.index().is_none() || to.index().is_none()));
+                if matches && inverted ^ edge.inverted == invert {
+                    paths.push(path.clone());
+                } else {
+                    self.all_paths_recurse(from, to, invert, max_len, path, paths);
+                }
+                path.pop();
+            }
+        }
+    }
+
     pub fn consider_edge(&mut self, from: CellPat<CT>, to: CellPat<CT>, edge: Edge<CT>)
     where
         Cost: PartialOrd + Clone,
@@ -299,6 +502,13 @@ impl<CT: CellType> CopyGraph<CT> {
 }
 
 impl<CT: CellType> Edge<CT> {
+    /// Whether the template of the edge contains the instruction with the given id.
+    pub fn uses_instruction(&self, id: u8) -> bool {
+        self.template
+            .iter()
+            .any(|instruction| instruction.typ.id == id)
+    }
+
     pub fn instantiate<TargetCT>(
         &self,
         from: Cell<TargetCT>,
@@ -338,28 +548,33 @@ impl<CT: CellType> Edge<CT> {
     }
 }
 
+/// Lists the edges ordered by their source and target nodes, so that the output is stable
+/// independent of the iteration order of the underlying maps.
 impl<CT: CellType> Debug for CopyGraph<CT> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut edges = self
+            .nodes
+            .iter()
+            .flat_map(|(from, to_edges)| {
+                to_edges.iter().flat_map(move |(to, edges)| {
+                    edges.iter().flatten().map(move |edge| (from, to, edge))
+                })
+            })
+            .collect::<Vec<_>>();
+        edges.sort_by_key(|(from, to, edge)| (*from, *to, edge.inverted));
         writeln!(f, "CopyGraph (")?;
-        for (from, to_edges) in self.nodes.iter() {
-            for (to, edges) in to_edges.iter() {
-                for edge in [true, false]
-                    .into_iter()
-                    .filter_map(|inverted| edges[inverted as usize].as_ref())
-                {
-                    write!(f, "  {from} -> ")?;
-                    if edge.inverted {
-                        write!(f, "!")?;
-                    }
-                    writeln!(
-                        f,
-                        "{to} with cost {:?} (computes_from_inverted: {})",
-                        edge.cost, edge.computes_from_inverted
-                    )?;
-                    for instruction in &edge.template {
-                        writeln!(f, "    {instruction}")?;
-                    }
-                }
+        for (from, to, edge) in edges {
+            write!(f, "  {from} -> ")?;
+            if edge.inverted {
+                write!(f, "!")?;
+            }
+            writeln!(
+                f,
+                "{to} with cost {:?} (computes_from_inverted: {})",
+                edge.cost, edge.computes_from_inverted
+            )?;
+            for instruction in &edge.template {
+                writeln!(f, "    {instruction}")?;
             }
         }
         write!(f, ")")?;
@@ -372,3 +587,113 @@ impl<CT, V: Default> Default for TypeNodes<CT, V> {
         Self(Default::default())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        cost::EqualCosts,
+        definitions::{Ambit, AmbitCellType},
+    };
+
+    #[test]
+    fn optimal_templates() {
+        let arch = Ambit::new();
+        let mut graph = CopyGraph::build(&arch, &EqualCosts);
+        let targets = graph.nodes();
+        let expected = |graph: &CopyGraph<_>, from, to, inverted| {
+            graph
+                .all_optimal_edges_matching(CellPat::Cell(from), to, inverted)
+                .map(|(_, _, edge)| {
+                    edge.instantiate(
+                        from.map_cell_type(CellOrVar::from),
+                        Cell::new(CellOrVar::Var, TO_VAR),
+                    )
+                    .collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>()
+        };
+        let uncached = targets
+            .iter()
+            .flat_map(|to| [false, true].map(|value| (value, *to)))
+            .flat_map(|(value, to)| [false, true].map(|inverted| (value, to, inverted)))
+            .map(|(value, to, inverted)| {
+                let from = AmbitCellType::constant(value);
+                ((from, to, inverted), expected(&graph, from, to, inverted))
+            })
+            .collect::<Vec<_>>();
+        let mut params = FindParams::new(&arch, &EqualCosts, &mut graph, None);
+        // the second round is served from the cache
+        for _ in 0..2 {
+            for ((from, to, inverted), expected) in &uncached {
+                assert_eq!(params.optimal_templates(*from, *to, *inverted), expected);
+            }
+        }
+        assert!(uncached.iter().any(|(_, templates)| !templates.is_empty()));
+    }
+
+    #[test]
+    fn stable_debug_output() {
+        let arch = Ambit::new();
+        let graph = CopyGraph::build(&arch, &EqualCosts);
+        let rendered = format!("{graph:?}");
+        assert_eq!(format!("{graph:?}"), rendered);
+        assert_eq!(
+            format!("{:?}", CopyGraph::build(&arch, &EqualCosts)),
+            rendered
+        );
+    }
+
+    /// Costs `1` for every instruction but the one with the given id.
+    #[derive(Clone)]
+    struct InstructionCost(u8, f64);
+
+    impl OperationCost<AmbitCellType> for InstructionCost {
+        fn cost<I: Into<CellOrVar<AmbitCellType>>>(
+            &self,
+            instruction: &Instruction<I, AmbitCellType>,
+        ) -> Cost {
+            if instruction.typ.id == self.0 {
+                self.1.into()
+            } else {
+                1.0.into()
+            }
+        }
+    }
+
+    #[test]
+    fn update_for_instruction() {
+        let arch = Ambit::new();
+        // optimal edges between all pairs of nodes, independent of the iteration order
+        let optimal_edges = |graph: &CopyGraph<_>| {
+            let nodes = graph.nodes();
+            let mut edges = Vec::new();
+            for from in &nodes {
+                for to in &nodes {
+                    for inverted in [false, true] {
+                        edges.extend(graph.all_optimal_edges_matching(*from, *to, inverted).map(
+                            |(from_node, to_node, edge)| {
+                                (from_node, to_node, inverted, edge.cost, edge.inverted)
+                            },
+                        ));
+                    }
+                }
+            }
+            edges.sort();
+            edges
+        };
+        for instruction in arch.instructions().iter() {
+            for value in [0.5, 3.0] {
+                let cost = InstructionCost(instruction.id, value);
+                let mut graph = CopyGraph::build(&arch, &EqualCosts);
+                graph.update_for_instruction(&arch, &cost, instruction.id);
+                assert_eq!(
+                    optimal_edges(&graph),
+                    optimal_edges(&CopyGraph::build(&arch, &cost)),
+                    "{} with cost {value}",
+                    instruction.name
+                );
+            }
+        }
+    }
+}
@@ -20,7 +20,7 @@ use rustc_hash::{FxHashMap, FxHashSet};
 pub use self::graph::CopyGraph;
 use crate::{
     copy::graph::{Edge, TypeNodes},
-    cost::Cost,
+    cost::{Cost, CostExt},
     program::{
         ProgramVersion,
         state::{CellStates, Operation},
@@ -33,6 +33,9 @@ struct INode<CT> {
     node: CellPat<CT>,
     invert: bool,
     jumped_from: Option<CellPat<CT>>,
+    /// number of operations on the path to this node, only tracked if the number of hops is
+    /// limited (otherwise always `0`)
+    hops: u32,
 }
 
 impl<CT: CellType> INode<CT> {
@@ -47,36 +50,45 @@ impl<CT: CellType> INode<CT> {
 
 pub struct PathMemo<'g, CT: CellType>(FindPathResult<CT, PathTracker<'g, CT>>);
 
+/// Returns the cost of the cheapest copy from `from` to `to`, or `None` if `to` is unreachable.
+///
+/// If `max_hops` is given, only paths consisting of at most `max_hops` operations are considered.
 pub fn copy_cost<CT: CellType, F: Into<CellPat<CT>>>(
     graph: &CopyGraph<CT>,
     from: F,
     to: CellPat<CT>,
     invert: bool,
     forbidden: &FxHashSet<Cell<CT>>,
+    max_hops: Option<u32>,
 ) -> Option<Cost> {
     find_path(
         (),
         graph,
         from,
         forbidden,
+        max_hops,
         matches_node(to, invert),
         |_, _, _| {},
     )
     .map(|result| result.cost)
 }
 
+/// Like [`copy_cost`], but additionally returns the path, which can be performed using
+/// [`perform_copy`].
 pub fn copy_cost_with_path<'g, CT: CellType, F: Into<CellPat<CT>>>(
     graph: &'g CopyGraph<CT>,
     from: F,
     to: CellPat<CT>,
     invert: bool,
     forbidden: &FxHashSet<Cell<CT>>,
+    max_hops: Option<u32>,
 ) -> Option<(Cost, PathMemo<'g, CT>)> {
     let result = find_path(
         PathTracker(FxHashMap::default()),
         graph,
         from,
         forbidden,
+        max_hops,
         matches_node(to, invert),
         |tracker, node, via| {
             tracker.0.insert(node, via);
@@ -85,16 +97,27 @@ pub fn copy_cost_with_path<'g, CT: CellType, F: Into<CellPat<CT>>>(
     result.map(|result| (result.cost, PathMemo(result)))
 }
 
-#[must_use]
+/// Progress of a copy operation that could not be completed by [`perform_copy`] because a cell
+/// required by the path was not available.
+#[derive(Debug, Clone, Copy)]
+pub struct PartialCopy<CT> {
+    /// The cell holding the signal after the successfully executed prefix of the path.
+    pub reached: Cell<CT>,
+    /// The target of the edge that could not be performed.
+    pub blocked: CellPat<CT>,
+    /// Whether the remaining copy still has to invert the signal.
+    pub invert: bool,
+}
+
 pub fn perform_copy<V: ProgramVersion>(
     PathMemo(result): PathMemo<'_, V::CT>,
     target: &mut V,
     mut from: Cell<V::CT>,
     to: CellPat<V::CT>,
     forbidden: &FxHashSet<Cell<V::CT>>,
-) -> Option<Cell<V::CT>> {
+) -> Result<Cell<V::CT>, PartialCopy<V::CT>> {
     if to.matches(&from) && !result.to.invert && !forbidden.contains(&from) {
-        return Some(from);
+        return Ok(from);
     }
     let mut path = result.state.reconstruct(result.from, result.to);
     path.1.last_mut().unwrap().1 = to;
@@ -103,9 +126,17 @@ pub fn perform_copy<V: ProgramVersion>(
         .state()
         .cell(from)
         .expect("from cell should have an associated signal");
+    let mut inverted = false;
     for (edge, target_pat) in path.1 {
-        let target_cell =
-            target.make_overridable_cell_for_pat(target_pat, &forbidden.and(&from))?;
+        let Some(target_cell) =
+            target.make_overridable_cell_for_pat(target_pat, &forbidden.and(&from))
+        else {
+            return Err(PartialCopy {
+                reached: from,
+                blocked: target_pat,
+                invert: result.to.invert ^ inverted,
+            });
+        };
         let instructions = edge.instantiate(from, target_cell).collect();
         let operation = Operation::Copy {
             from,
@@ -116,11 +147,51 @@ pub fn perform_copy<V: ProgramVersion>(
             computes_from_inverted: edge.computes_from_inverted,
         };
         from = target_cell;
+        inverted ^= edge.inverted;
         signal = signal ^ edge.inverted;
         target.state_mut().set(target_cell, signal);
         target.append(operation);
     }
-    Some(from)
+    Ok(from)
+}
+
+/// Attempts to complete a copy operation aborted by [`perform_copy`]. Instead of starting over,
+/// the copy continues from the cell reached so far, avoiding the cell that blocked the path.
+///
+/// Fails with the progress made if no path avoiding the blocked cell exists or the new path is
+/// blocked as well. If the blocked pattern is a cell type, all cells of the type are unavailable
+/// and the copy graph can not route around it, hence the copy fails immediately.
+pub fn repair_copy<V: ProgramVersion>(
+    partial: PartialCopy<V::CT>,
+    target: &mut V,
+    to: CellPat<V::CT>,
+    forbidden: &FxHashSet<Cell<V::CT>>,
+) -> Result<Cell<V::CT>, PartialCopy<V::CT>> {
+    let PartialCopy {
+        reached,
+        blocked,
+        invert,
+    } = partial;
+    let CellPat::Cell(blocked) = blocked else {
+        return Err(partial);
+    };
+    let mut forbidden = forbidden.clone();
+    forbidden.insert(blocked);
+    if !invert && to.matches(&reached) && !forbidden.contains(&reached) {
+        return Ok(reached);
+    }
+    let params = target.parameters().clone();
+    let Some((_, path)) = copy_cost_with_path(
+        &params.arch.copy_graph,
+        reached,
+        to,
+        invert,
+        &forbidden,
+        None,
+    ) else {
+        return Err(partial);
+    };
+    perform_copy(path, target, reached, to, &forbidden)
 }
 
 struct FindPathResult<CT: CellType, S> {
@@ -143,10 +214,17 @@ fn find_path<'g, CT: CellType, S, F: Into<CellPat<CT>>>(
     graph: &'g CopyGraph<CT>,
     from: F,
     forbidden: &FxHashSet<Cell<CT>>,
+    max_hops: Option<u32>,
     matches: impl Fn(INode<CT>) -> bool,
     mut visit: impl FnMut(&mut S, INode<CT>, Via<'g, CT>),
 ) -> Option<FindPathResult<CT, S>> {
     let from = from.into();
+    // with a limit, the number of hops is part of the node s.t. a cheap but long path to a node
+    // does not hide a more expensive but shorter one
+    let limit_hops = |node: INode<CT>| match max_hops {
+        Some(max_hops) => (node.hops <= max_hops).then_some(node),
+        None => Some(INode { hops: 0, ..node }),
+    };
     let mut costs = FxHashMap::default();
     let mut new_cheaper = move |node: INode<CT>, cost: Cost| {
         let cost_entry = costs.entry(node);
@@ -168,10 +246,14 @@ fn find_path<'g, CT: CellType, S, F: Into<CellPat<CT>>>(
         invert: false,
         // prevent going to from_cell from the parent node of from_cell
         jumped_from: Some(from),
+        hops: 0,
     };
 
     // we have to start with an operation! we cannot allow Via::FromParent or Via::FromChild
     for (edge, next) in start_operations(graph, from, forbidden) {
+        let Some(next) = limit_hops(next) else {
+            continue;
+        };
         if new_cheaper(next, edge.cost) {
             visit_next.push(Reverse(OrdFirst(edge.cost, next)));
             visit(&mut state, next, Via::Operation { from, edge });
@@ -191,6 +273,9 @@ fn find_path<'g, CT: CellType, S, F: Into<CellPat<CT>>>(
             result = Some((node, cost))
         }
         for (via, next) in neighbours_of_node(graph, node, forbidden) {
+            let Some(next) = limit_hops(next) else {
+                continue;
+            };
             let next_cost = via.add_cost_to(cost);
             if let Some((_, prev_cost)) = &result
                 && *prev_cost < cost
@@ -258,6 +343,7 @@ fn neighbours_of_node<'g, CT: CellType>(
                             node: CellPat::Type(cell.typ()),
                             invert: node.invert,
                             jumped_from: Some(*node),
+                            hops: node.hops,
                         },
                     )))
                 }
@@ -283,6 +369,7 @@ fn neighbours_of_node<'g, CT: CellType>(
                                 node: CellPat::Cell(Cell::new(typ, *idx)),
                                 invert: node.invert,
                                 jumped_from: Some(*node),
+                                hops: node.hops,
                             },
                         )
                     }))
@@ -322,6 +409,7 @@ fn neighbours_for_edges<CT: CellType>(
                 node: to,
                 invert: from.invert ^ edge.inverted,
                 jumped_from: None,
+                hops: from.hops + 1,
             },
         )
     })
@@ -335,6 +423,8 @@ impl<'a, CT: CellType> PathTracker<'a, CT> {
     pub fn reconstruct(&self, from: INode<CT>, to: INode<CT>) -> Path<'a, CT> {
         let mut path = Vec::new();
         let mut curr = to;
+        // every node is reached at most once, a corrupted tracker could loop forever otherwise
+        let mut visited = FxHashSet::default();
         loop {
             if curr == from && !path.is_empty() {
                 break;
@@ -343,6 +433,13 @@ impl<'a, CT: CellType> PathTracker<'a, CT> {
             let Some(via) = self.0.get(&curr) else {
                 break;
             };
+            if !visited.insert(curr) {
+                panic!(
+                    "cycle in path tracker at {:?}, partial path (reversed): {:?}",
+                    *curr,
+                    path.iter().map(|(_, node)| node).collect::<Vec<_>>()
+                );
+            }
             // if we did come from child / parent and not via an operation, we have to take an extra
             // step
             // also, the node in the path should be the cell node of the two, because that node
@@ -358,6 +455,7 @@ impl<'a, CT: CellType> PathTracker<'a, CT> {
                                     node: child,
                                     invert: curr.invert,
                                     jumped_from: None,
+                                    hops: curr.hops,
                                 })
                                 .expect("if we came from a child, there should be a predecessor"),
                         )
@@ -374,6 +472,7 @@ impl<'a, CT: CellType> PathTracker<'a, CT> {
                                 node: CellPat::Type(cell.typ()),
                                 invert: curr.invert,
                                 jumped_from: None,
+                                hops: curr.hops,
                             })
                             .expect("if we came from the parent, there should be a predecessor"),
                     ),
@@ -407,7 +506,7 @@ impl<'a, CT> Via<'a, CT> {
     pub fn add_cost_to(&self, cost: Cost) -> Cost {
         match self {
             Self::FromParent | Self::FromChild(_) => cost,
-            Self::Operation { edge, .. } => cost + edge.cost,
+            Self::Operation { edge, .. } => cost.checked_add(edge.cost),
         }
     }
 }
@@ -433,3 +532,44 @@ impl<O: Ord, V> Ord for OrdFirst<O, V> {
         self.0.cmp(&other.0)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::definitions::AmbitCellType;
+
+    #[test]
+    #[should_panic(expected = "cycle in path tracker")]
+    fn reconstruct_cyclic_path() {
+        let node = |idx| INode {
+            node: CellPat::Cell(Cell::new(AmbitCellType::T, idx)),
+            invert: false,
+            jumped_from: None,
+            hops: 0,
+        };
+        let edge = Edge {
+            inverted: false,
+            computes_from_inverted: false,
+            template: Vec::new(),
+            cost: Cost::ZERO,
+        };
+        // T[0] and T[1] are each other's predecessors, hence T[2] is never reached
+        let tracker = PathTracker(FxHashMap::from_iter([
+            (
+                node(0),
+                Via::Operation {
+                    from: node(1),
+                    edge: &edge,
+                },
+            ),
+            (
+                node(1),
+                Via::Operation {
+                    from: node(0),
+                    edge: &edge,
+                },
+            ),
+        ]));
+        tracker.reconstruct(node(2), node(0));
+    }
+}
@@ -1,15 +1,12 @@
-use std::f64;
-
 use lime_generic_def::{
     Cell, CellPat, CellType, PatBase, Pats,
     set::{AllOrNone, Set},
 };
-use ordered_float::OrderedFloat;
 use rustc_hash::FxHashSet;
 
 use crate::{
     copy::{INode, start_operations},
-    cost::Cost,
+    cost::{Cost, CostExt},
     program::{
         ProgramVersion,
         state::{CellStates, Operation},
@@ -33,6 +30,8 @@ pub fn spill_if_necessary<V: ProgramVersion + ?Sized>(version: &mut V, from_cell
     force_spill(version, from_cell, &AllOrNone::None);
 }
 
+/// Moves the value held by `from_cell` to a free cell not in `not`, preferring the cells of the
+/// [spill region](CellType::spill_region) of its type over cheaper copies to other cells.
 pub fn force_spill<V: ProgramVersion + ?Sized>(
     version: &mut V,
     from_cell: Cell<V::CT>,
@@ -42,26 +41,40 @@ pub fn force_spill<V: ProgramVersion + ?Sized>(
         return;
     };
     let params = version.parameters().clone();
+    let region = from_cell.typ().spill_region();
+    let in_region = |cell: Cell<V::CT>| {
+        region
+            .as_ref()
+            .is_some_and(|(typ, range)| cell.typ() == *typ && range.contains(&cell.index()))
+    };
     let (edge, to_cell) = start_operations(
         &params.arch.copy_graph,
         INode {
             node: CellPat::Cell(from_cell),
             invert: false,
             jumped_from: Some(CellPat::Cell(from_cell)),
+            hops: 0,
         },
         &FxHashSet::default(),
     )
     .filter_map(|(edge, to_node)| {
         // attempt to find a free cell for the target node not in _not_
         let cell = match to_node.node {
-            CellPat::Type(typ) => Cell::new(
-                typ,
-                version
-                    .state()
-                    .free_cells(typ)
-                    .iter()
-                    .find(|idx| !not.contains(&Cell::new(typ, *idx)))?,
-            ),
+            CellPat::Type(typ) => {
+                let free = version.state().free_cells(typ);
+                let is_free =
+                    |cell: &Cell<V::CT>| free.contains(cell.index()) && !not.contains(cell);
+                let region_cell = region
+                    .as_ref()
+                    .filter(|(region_typ, _)| *region_typ == typ)
+                    .and_then(|(_, range)| {
+                        range.clone().map(|idx| Cell::new(typ, idx)).find(is_free)
+                    });
+                match region_cell {
+                    Some(cell) => cell,
+                    None => free.iter().map(|idx| Cell::new(typ, idx)).find(is_free)?,
+                }
+            }
             CellPat::Cell(cell) => {
                 if !not.contains(&cell)
                     && version
@@ -77,7 +90,7 @@ pub fn force_spill<V: ProgramVersion + ?Sized>(
         };
         Some((edge, cell))
     })
-    .min_by_key(|(edge, _)| &edge.cost)
+    .min_by_key(|(edge, cell)| (!in_region(*cell), &edge.cost))
     .expect("a spill target should be available");
 
     version.state_mut().set(to_cell, signal ^ edge.inverted);
@@ -106,6 +119,7 @@ fn estimate_spill_cost_cell_pat<V: ProgramVersion>(
             node: pat,
             invert: false,
             jumped_from: Some(pat),
+            hops: 0,
         },
         &FxHashSet::default(),
     )
@@ -121,5 +135,5 @@ pub fn estimate_spill_cost_operand_pats<V: ProgramVersion>(
     pats.iter()
         .flat_map(|pat| estimate_spill_cost_cell_pat(version, *pat))
         .mean()
-        .unwrap_or(OrderedFloat(f64::INFINITY))
+        .unwrap_or(Cost::INFINITY)
 }
@@ -1,20 +1,89 @@
-use lime_generic_def::Instruction;
+use std::{cell::RefCell, marker::PhantomData, rc::Rc};
+
+use derive_where::derive_where;
+use lime_generic_def::{Cell, CellType, Instruction, InstructionCosts, InstructionType};
 use ordered_float::OrderedFloat;
+use rustc_hash::FxHashMap;
 
-use crate::{copy::placeholder::CellOrVar, program::state::Program};
+use crate::{copy::placeholder::CellOrVar, definitions::AmbitCellType, program::state::Program};
 
 pub type Cost = OrderedFloat<f64>;
 
+/// Constants and checked arithmetic for [`Cost`], which cannot have inherent items as it is an
+/// alias of a foreign type.
+pub trait CostExt: Copy {
+    const ZERO: Self;
+    /// cost of something that is not possible at all, compares greater than any other cost
+    const INFINITY: Self;
+
+    /// Adds the costs, asserting in debug builds that the sum is not `NaN`, which would otherwise
+    /// silently corrupt all comparisons involving it. Infinite costs are allowed.
+    fn checked_add(self, other: Self) -> Self;
+}
+
+impl CostExt for Cost {
+    const ZERO: Self = OrderedFloat(0.0);
+    const INFINITY: Self = OrderedFloat(f64::INFINITY);
+
+    fn checked_add(self, other: Self) -> Self {
+        let sum = self + other;
+        debug_assert!(!sum.is_nan(), "cost {self} + {other} is NaN");
+        sum
+    }
+}
+
 pub trait OperationCost<CT>: Clone {
     fn cost<I: Into<CellOrVar<CT>>>(&self, instruction: &Instruction<I, CT>) -> Cost;
+    /// Additional cost for every access (read or write) of the given cell, e.g. to model rows that
+    /// are further away and hence more expensive to access.
+    fn cell_access_cost(&self, _cell: Cell<CT>) -> Cost {
+        Cost::ZERO
+    }
+    /// Additional cost of an instruction of the given type writing the given number of output
+    /// operands, e.g. to model instructions that get more expensive with every destination written
+    /// by a single operation.
+    fn output_cost(&self, _typ: &InstructionType<CT>, _outputs: usize) -> Cost {
+        Cost::ZERO
+    }
+    /// Cost of keeping a cell of the given type occupied by a value for a single instruction, e.g.
+    /// to model the standby power of cells holding a value, see [`Program::occupancy_cost`].
+    fn occupancy_cost(&self, _typ: CT) -> Cost {
+        Cost::ZERO
+    }
+    /// Returns [`Self::cost`] plus the [`Self::output_cost`] of the outputs written by the
+    /// instruction.
+    fn operation_cost<I: Into<CellOrVar<CT>>>(&self, instruction: &Instruction<I, CT>) -> Cost {
+        self.cost(instruction)
+            .checked_add(self.output_cost(&instruction.typ, instruction.outputs.len()))
+    }
+    fn instruction_cost(&self, instruction: &Instruction<CT>) -> Cost
+    where
+        CT: CellType,
+    {
+        instruction
+            .read_cells()
+            .chain(instruction.write_cells())
+            .map(|cell| self.cell_access_cost(cell))
+            .fold(self.operation_cost(instruction), Cost::checked_add)
+    }
     fn program_cost<'a>(&self, program: &Program<CT>) -> Cost
     where
-        CT: 'a,
+        CT: CellType + 'a,
     {
         program
             .instructions()
-            .map(|op| self.cost(op))
-            .fold(Default::default(), |a, b| a + b)
+            .map(|op| self.instruction_cost(op))
+            .fold(Cost::ZERO, Cost::checked_add)
+    }
+    /// Cost of appending `delta` to the program `base`, by default the
+    /// [`program_cost`](Self::program_cost) of `delta` alone. Costs of a program that are not the
+    /// sum of the costs of its instructions, e.g. the number of cells it uses, charge only what
+    /// `delta` adds to `base` here.
+    fn program_cost_with_context(&self, _base: &Program<CT>, delta: &Program<CT>) -> Cost
+    where
+        CT: CellType,
+    {
+        self.program_cost(delta)
     }
 }
 
@@ -26,3 +95,121 @@ impl<CT> OperationCost<CT> for EqualCosts {
         OrderedFloat(1.0)
     }
 }
+
+/// Minimizes the number of cells used by a program, breaking ties by the number of instructions.
+/// Since a program never uses fewer cells than any of its prefixes, the cost of a partial program
+/// is a lower bound of the cost of its completions.
+#[derive(Clone, Copy, Default)]
+pub struct CellCountCost;
+
+impl CellCountCost {
+    /// cost of a single cell, which exceeds the cost of any realistic number of instructions
+    pub const CELL_COST: f64 = 1e9;
+}
+
+impl<CT> OperationCost<CT> for CellCountCost {
+    fn cost<I: Into<CellOrVar<CT>>>(&self, _instruction: &Instruction<I, CT>) -> Cost {
+        OrderedFloat(1.0)
+    }
+    fn program_cost<'a>(&self, program: &Program<CT>) -> Cost
+    where
+        CT: CellType + 'a,
+    {
+        let instructions = program.instructions().count() as f64;
+        OrderedFloat(program.num_cells() as f64 * Self::CELL_COST + instructions)
+    }
+    fn program_cost_with_context(&self, base: &Program<CT>, delta: &Program<CT>) -> Cost
+    where
+        CT: CellType,
+    {
+        let used = base.cells();
+        let new_cells = delta.cells().difference(&used).count() as f64;
+        let instructions = delta.instructions().count() as f64;
+        OrderedFloat(new_cells * Self::CELL_COST + instructions)
+    }
+}
+
+/// Uses the costs declared in the definition of the architecture `A`, see [`InstructionCosts`].
+#[derive_where(Clone, Copy, Default)]
+pub struct DeclaredCosts<A>(PhantomData<A>);
+
+impl<A: InstructionCosts> OperationCost<A::CellType> for DeclaredCosts<A> {
+    fn cost<I: Into<CellOrVar<A::CellType>>>(
+        &self,
+        instruction: &Instruction<I, A::CellType>,
+    ) -> Cost {
+        OrderedFloat(A::instruction_cost(instruction.typ.id))
+    }
+}
+
+/// Costs of Ambit's instructions: `2/3` for every instruction and another `1/3` if it writes any
+/// output operand.
+#[derive(Clone, Copy, Default)]
+pub struct AmbitCost;
+
+impl OperationCost<AmbitCellType> for AmbitCost {
+    fn cost<I: Into<CellOrVar<AmbitCellType>>>(
+        &self,
+        _instruction: &Instruction<I, AmbitCellType>,
+    ) -> Cost {
+        OrderedFloat(2.0 / 3.0)
+    }
+
+    fn output_cost(&self, _typ: &InstructionType<AmbitCellType>, outputs: usize) -> Cost {
+        if outputs == 0 {
+            Cost::ZERO
+        } else {
+            OrderedFloat(1.0 / 3.0)
+        }
+    }
+}
+
+/// Memoizes the [instruction costs](OperationCost::instruction_cost) computed by `C`, keyed on the
+/// instruction, i.e. its type and operands. Clones share the same cache.
+///
+/// This is only correct if the cost computed by `C` is a pure function of the instruction.
+#[derive_where(Clone; C)]
+pub struct CachingCost<C, CT> {
+    cost: C,
+    cache: Rc<RefCell<FxHashMap<Instruction<CT>, Cost>>>,
+}
+
+impl<C, CT> CachingCost<C, CT> {
+    pub fn new(cost: C) -> Self {
+        Self {
+            cost,
+            cache: Default::default(),
+        }
+    }
+
+    pub fn inner(&self) -> &C {
+        &self.cost
+    }
+}
+
+impl<C: OperationCost<CT>, CT: CellType> OperationCost<CT> for CachingCost<C, CT> {
+    fn cost<I: Into<CellOrVar<CT>>>(&self, instruction: &Instruction<I, CT>) -> Cost {
+        self.cost.cost(instruction)
+    }
+
+    fn instruction_cost(&self, instruction: &Instruction<CT>) -> Cost {
+        if let Some(cost) = self.cache.borrow().get(instruction) {
+            return *cost;
+        }
+        let cost = self.cost.instruction_cost(instruction);
+        self.cache.borrow_mut().insert(instruction.clone(), cost);
+        cost
+    }
+
+    fn cell_access_cost(&self, cell: Cell<CT>) -> Cost {
+        self.cost.cell_access_cost(cell)
+    }
+
+    fn output_cost(&self, typ: &InstructionType<CT>, outputs: usize) -> Cost {
+        self.cost.output_cost(typ, outputs)
+    }
+
+    fn occupancy_cost(&self, typ: CT) -> Cost {
+        self.cost.occupancy_cost(typ)
+    }
+}
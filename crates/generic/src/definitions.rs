@@ -97,14 +97,14 @@ define_generic_architecture! {
         ),
         instructions (
             // or
-            OR = (!and(NARY![0..]) -> (ANY)),
+            OR = (!and(NARY![0..]) -> (ANY)) disjoint_io,
             // nor
-            NOR = (and(NARY![0..]) -> (ANY)),
+            NOR = (and(NARY![0..]) -> (ANY)) disjoint_io,
 
-            NAND2 = (!and(BINARY) -> (ANY)) ,
-            NAND3 = (!and(TERNARY) -> (ANY)),
-            MIN = (!maj(TERNARY) -> (ANY)),
-            XOR = (xor(BINARY) -> (ANY)),
+            NAND2 = (!and(BINARY) -> (ANY)) disjoint_io,
+            NAND3 = (!and(TERNARY) -> (ANY)) disjoint_io,
+            MIN = (!maj(TERNARY) -> (ANY)) disjoint_io,
+            XOR = (xor(BINARY) -> (ANY)) disjoint_io cost = 1.5,
         )
     }
 }
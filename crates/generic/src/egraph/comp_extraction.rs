@@ -1,13 +1,55 @@
-use crate::compilation::{CandidateSelection, CompilationMode, CompilationParameters, compile};
-use crate::cost::{Cost, OperationCost};
+use crate::compilation::{
+    CandidateSelection, CompilationMode, CompilationParameters, compile, rng::CompilerRng,
+};
+use crate::cost::{Cost, CostExt, OperationCost};
 use crate::egraph::opt_extractor::{Choices, OptCostFunction};
+use crate::program::state::{Operation, Program};
 use crate::{ArchitectureMeta, get_input_cells};
 
 use eggmock::egg::{Analysis, EClass};
-use eggmock::{EggExt, NetworkLanguage, NetworkReceiver, Signal};
+use eggmock::{EggExt, GateFunction, NetworkLanguage, NetworkReceiver, Signal};
 use lime_generic_def::CellType;
+use ordered_float::OrderedFloat;
+use rustc_hash::FxHashMap;
+use std::ffi::c_double;
 use std::rc::Rc;
 
+/// Multipliers of the compiled cost of the instructions computing gates of the respective function,
+/// see [`CompilingCostFunction::gate_biases`]. A bias below `1.0` favors networks using the gate, a
+/// bias above `1.0` penalizes them.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[repr(C)]
+pub struct GateBiases {
+    pub and: c_double,
+    pub maj: c_double,
+    pub xor: c_double,
+}
+
+impl GateBiases {
+    pub fn get(&self, function: GateFunction) -> f64 {
+        match function {
+            GateFunction::And => self.and,
+            GateFunction::Maj => self.maj,
+            GateFunction::Xor => self.xor,
+        }
+    }
+
+    /// Returns whether all biases are `1.0`, i.e. do not change any cost.
+    pub fn is_neutral(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+impl Default for GateBiases {
+    fn default() -> Self {
+        Self {
+            and: 1.0,
+            maj: 1.0,
+            xor: 1.0,
+        }
+    }
+}
+
 pub struct CompilingCostFunction<CT: CellType, C: OperationCost<CT>> {
     pub arch: Rc<ArchitectureMeta<CT>>,
     pub cost: C,
@@ -15,6 +57,40 @@ pub struct CompilingCostFunction<CT: CellType, C: OperationCost<CT>> {
     pub candidate_selection: CandidateSelection,
     pub disjunct_input_output: bool,
     pub memusage: bool,
+    /// Eclasses with fewer nodes than this are not compiled, instead their cost is estimated by the
+    /// size of the subnetwork. As all nodes of an eclass are evaluated the same way, their costs
+    /// remain comparable.
+    pub compile_threshold: usize,
+    /// Biases applied to the cost of the instructions computing gates of the compiled subnetworks,
+    /// allowing to nudge extraction towards or away from gate functions beyond the cost model. Not
+    /// applied to the cell count when optimizing for memory usage or to eclasses below the
+    /// `compile_threshold`.
+    pub gate_biases: GateBiases,
+}
+
+impl<CT: CellType, C: OperationCost<CT>> CompilingCostFunction<CT, C> {
+    /// Sums the costs of the instructions of the program, multiplying the cost of instructions
+    /// computing a gate of the network with the bias of the gate's function.
+    fn biased_program_cost(&self, program: &Program<CT>) -> Cost {
+        program
+            .0
+            .iter()
+            .flat_map(|op| {
+                let bias = match op {
+                    Operation::Candidate(instr, ..) => instr
+                        .typ
+                        .function
+                        .gate
+                        .gate_function()
+                        .map_or(1.0, |function| self.gate_biases.get(function)),
+                    _ => 1.0,
+                };
+                op.instructions()
+                    .iter()
+                    .map(move |instr| self.cost.instruction_cost(instr) * OrderedFloat(bias))
+            })
+            .fold(Cost::ZERO, Cost::checked_add)
+    }
 }
 
 impl<L: NetworkLanguage, A: Analysis<L>, CT: CellType, C: OperationCost<CT>> OptCostFunction<L, A>
@@ -39,6 +115,10 @@ impl<L: NetworkLanguage, A: Analysis<L>, CT: CellType, C: OperationCost<CT>> Opt
         };
         ntk.set_outputs(vec![output]);
 
+        if eclass.len() < self.compile_threshold {
+            return Some((ntk.size() as u32).into());
+        }
+
         let result = compile(CompilationParameters {
             arch: self.arch.clone(),
             input_cells: get_input_cells(&self.arch, &ntk),
@@ -47,11 +127,25 @@ impl<L: NetworkLanguage, A: Analysis<L>, CT: CellType, C: OperationCost<CT>> Opt
             mode: self.mode,
             candidate_selection: self.candidate_selection,
             disjunct_input_output: self.disjunct_input_output,
-        })?;
+            max_depth: None,
+            warm_start: None,
+            enabled_instructions: None,
+            cost_lower_bound: None,
+            spill_warning_fraction: None,
+            prematerialize_constants: false,
+            rng: CompilerRng::default(),
+            profiler: None,
+            cell_reuse_bias: 0.0,
+            signal_pins: FxHashMap::default(),
+            branch_and_bound: false,
+        })
+        .ok()?;
         Some(if self.memusage {
             (result.program.num_cells() as u32).into()
-        } else {
+        } else if self.gate_biases.is_neutral() {
             self.cost.program_cost(&result.program)
+        } else {
+            self.biased_program_cost(&result.program)
         })
     }
 }
@@ -1,24 +1,31 @@
 use std::{
+    fs,
+    panic::{self, AssertUnwindSafe},
+    path::PathBuf,
     rc::Rc,
     time::{Duration, Instant},
 };
 
-use egg::{AstDepth, EGraph, Extractor, LpExtractor, Runner};
+use egg::{AstDepth, EGraph, Extractor, Id, LpExtractor, Rewrite, Runner};
 use eggmock::{EggExt, Network, NetworkReceiver, Receiver};
-use lime_generic_def::CellType;
+use itertools::Itertools;
+use lime_generic_def::{Architecture, CellType};
+use rustc_hash::FxHashMap;
+use strum::{Display, EnumIter, EnumString};
 
 use crate::{
     ArchitectureMeta,
     compilation::{CandidateSelection, CompilationMode},
     cost::OperationCost,
     egraph::{
-        analysis::LimeAnalysis,
         comp_extraction::CompilingCostFunction,
         opt_extractor::OptExtractor,
         transform::{LpInversionCostFunction, rebuild_network, transform_egraph},
         trimming::trim_egraph,
     },
-    untyped_ntk::{UntypedNetwork, UntypedNetworkLanguage, create_rewrites},
+    untyped_ntk::{UntypedNetwork, UntypedNetworkLanguage, create_rewrites, format_network},
+    validation::{canonicalize, find_divergence, merge_networks, network_stats},
+    verbosity::Verbosity,
 };
 
 mod analysis;
@@ -27,16 +34,24 @@ mod opt_extractor;
 mod transform;
 mod trimming;
 
+pub use self::{analysis::LimeAnalysis, comp_extraction::GateBiases};
+
+#[derive(Clone, Copy, PartialEq, Eq, Display, EnumString, EnumIter)]
+#[strum(serialize_all = "snake_case")]
 #[repr(C)]
 pub enum RewritingStrategy {
     None,
+    #[strum(serialize = "lp")]
     LP,
     Compiling,
     CompilingMemusage,
+    #[strum(serialize = "greedy")]
     GreedyEstimate,
+    /// extracts the network of minimal depth, ignoring the cost of its gates
+    MinDepth,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 #[repr(C)]
 pub struct RewritingStatistics {
     pub t_runner: u64,
@@ -45,96 +60,250 @@ pub struct RewritingStatistics {
     pub n_nodes_post_trim: u64,
     pub t_extractor: u64,
     pub rebuilt_ntk_cost: std::ffi::c_double,
+    /// number of eclasses that could not be compiled by the compiling strategies
+    pub extraction_none_count: u64,
+    /// number of eclasses considered by the compiling strategies
+    pub extraction_total_classes: u64,
+    /// number of gates on the longest path from an input to an output of the extracted network
+    pub extracted_depth: u64,
+}
+
+/// Number of input assignments on which [`rewriting_receiver`] compares the extracted network to
+/// the received one if the networks have too many inputs to compare them exhaustively.
+const EQUIVALENCE_CHECK_SAMPLES: usize = 256;
+
+/// Settings of [`rewriting_receiver`].
+pub struct RewritingOptions<CT: CellType, C: OperationCost<CT>> {
+    pub arch: Rc<ArchitectureMeta<CT>>,
+    /// strategy used to extract the outputs not contained in `output_strategies`
+    pub strategy: RewritingStrategy,
+    /// strategies used to extract the outputs with the given indices instead of `strategy`
+    pub output_strategies: FxHashMap<usize, RewritingStrategy>,
+    /// the egraph may grow to this factor of the size of the received network while rewriting
+    pub size_factor: usize,
+    /// eclasses with fewer nodes are not compiled by [`RewritingStrategy::Compiling`], their cost
+    /// is estimated by the size of their subnetwork instead
+    pub compile_threshold: usize,
+    pub gate_biases: GateBiases,
+    pub candidate_selection: CandidateSelection,
+    pub compilation_mode: CompilationMode,
+    pub cost: C,
+    pub disjunct_input_output: bool,
+    /// rules applied in addition to the ones generated for the architecture, see
+    /// [`rewriting_rules`]
+    pub extra_rules: Vec<Rewrite<UntypedNetworkLanguage, LimeAnalysis>>,
+    pub verbosity: Verbosity,
+    /// if given, the extracted network is written to it in the format read by
+    /// [`parse_network`](crate::untyped_ntk::parse_network)
+    pub dump_path: Option<PathBuf>,
 }
 
+/// Rewrites the received network and extracts a network from the resulting egraph using the given
+/// strategy. Outputs whose index is contained in `output_strategies` are extracted using the
+/// strategy mapped to them instead, the networks extracted by the different strategies are
+/// [merged](merge_networks) into a single one. The network is [canonicalized](canonicalize) before
+/// building the initial egraph.
+/// Progress is reported according to `verbosity`. If `dump_path` is given, the extracted network
+/// is written to it.
+/// A warning is printed if the extracted network is found to compute different outputs than the
+/// received one, see [`find_divergence`].
 pub fn rewriting_receiver<CT: CellType, C: OperationCost<CT>>(
-    arch: Rc<ArchitectureMeta<CT>>,
-    strategy: RewritingStrategy,
-    size_factor: usize,
-    candidate_selection: CandidateSelection,
-    compilation_mode: CompilationMode,
-    cost: C,
-    disjunct_input_output: bool,
+    options: RewritingOptions<CT, C>,
 ) -> impl Receiver<Gate = UntypedNetwork, Result = (Network<UntypedNetwork>, RewritingStatistics)> {
-    EGraph::<UntypedNetworkLanguage, LimeAnalysis>::default().map(move |(egraph, mut outputs)| {
-        eprintln!("rewriting to size {size_factor}");
-        let rules = create_rewrites(&arch);
+    let RewritingOptions {
+        arch,
+        strategy,
+        output_strategies,
+        size_factor,
+        compile_threshold,
+        gate_biases,
+        candidate_selection,
+        compilation_mode,
+        cost,
+        disjunct_input_output,
+        extra_rules,
+        verbosity,
+        dump_path,
+    } = options;
+    NetworkReceiver::<UntypedNetwork>::default().map(move |received| {
+        let (egraph, mut outputs) =
+            canonicalize(&received).send(EGraph::<UntypedNetworkLanguage, LimeAnalysis>::default());
+        // indices of the outputs extracted by the same strategy, in order of first occurrence
+        let mut groups = Vec::<(RewritingStrategy, Vec<usize>)>::new();
+        for i in 0..outputs.len() {
+            let strategy = output_strategies.get(&i).copied().unwrap_or(strategy);
+            match groups.iter_mut().find(|(other, _)| *other == strategy) {
+                Some((_, idxs)) => idxs.push(i),
+                None => groups.push((strategy, vec![i])),
+            }
+        }
+        let strategies = if groups.is_empty() {
+            vec![strategy]
+        } else {
+            groups.iter().map(|(strategy, _)| *strategy).collect_vec()
+        };
+        verbosity.log(
+            Verbosity::SUMMARY,
+            format_args!("rewriting to size {size_factor}"),
+        );
+        let rules = rewriting_rules(&arch, extra_rules);
 
         let t_runner = Instant::now();
-        let mut egraph = if !matches!(strategy, RewritingStrategy::None) {
+        let mut egraph = if strategies
+            .iter()
+            .any(|strategy| *strategy != RewritingStrategy::None)
+        {
             let runner = Runner::default()
                 .with_node_limit(size_factor * egraph.total_size())
                 .with_egraph(egraph)
                 .with_iter_limit(usize::MAX)
                 .with_time_limit(Duration::new(60 * 5, 0))
                 .run(&rules);
-            eprintln!("Rewriting done! Report: {}", runner.report());
+            verbosity.log(
+                Verbosity::SUMMARY,
+                format_args!("Rewriting done! Report: {}", runner.report()),
+            );
             runner.egraph
         } else {
             egraph
         };
         let t_runner = (Instant::now() - t_runner).as_millis() as u64;
         let mut rebuilt_ntk_cost = 0.0;
+        let mut extraction_none_count = 0;
+        let mut extraction_total_classes = 0;
 
         // canonicalize IDs
         outputs.iter_mut().for_each(|id| *id = egraph.find(*id));
 
         let n_nodes_pre_trim = egraph.total_number_of_nodes() as u64;
         let t_trim = Instant::now();
-        if matches!(strategy, RewritingStrategy::Compiling) {
-            trim_egraph(&mut egraph, &outputs);
+        if strategies.contains(&RewritingStrategy::Compiling) {
+            let removed = trim_egraph(&mut egraph, &outputs);
+            verbosity.log(Verbosity::TRACE, format_args!("removed {removed} nodes"));
         }
         let t_trim = (Instant::now() - t_trim).as_millis() as u64;
         let n_nodes_post_trim = egraph.total_number_of_nodes() as u64;
-        eprintln!("Trimmed to size {}", egraph.total_number_of_nodes());
+        verbosity.log(
+            Verbosity::SUMMARY,
+            format_args!("Trimmed to size {}", egraph.total_number_of_nodes()),
+        );
 
         let t_extractor = Instant::now();
-        let ntk = match strategy {
+        let mut extract = |strategy: RewritingStrategy, outputs: &[Id]| match strategy {
             RewritingStrategy::Compiling | RewritingStrategy::CompilingMemusage => {
                 let extractor = OptExtractor::new(
                     &egraph,
                     CompilingCostFunction {
-                        arch,
+                        arch: arch.clone(),
                         candidate_selection,
                         mode: compilation_mode,
-                        cost,
+                        cost: cost.clone(),
                         disjunct_input_output,
                         memusage: matches!(strategy, RewritingStrategy::CompilingMemusage),
+                        compile_threshold,
+                        gate_biases,
                     },
                 );
+                extraction_none_count += extractor.num_unextractable_classes() as u64;
+                extraction_total_classes += extractor.num_classes() as u64;
                 extractor
                     .choices()
                     .send(NetworkReceiver::default(), outputs.iter().cloned())
                     .unwrap()
             }
             RewritingStrategy::GreedyEstimate => {
-                eprintln!("transforming");
-                let (transformed, outputs) = transform_egraph(&egraph, &arch, &outputs);
-                eprintln!("extracting");
+                verbosity.log(Verbosity::TRACE, format_args!("transforming"));
+                let (transformed, outputs) = transform_egraph(&egraph, &arch, outputs);
+                verbosity.log(Verbosity::TRACE, format_args!("extracting"));
                 let mut cost = LpInversionCostFunction::new(&arch, cost.clone());
                 let extractor = Extractor::new(&transformed, cost.clone());
                 let (cost, ntk) = rebuild_network(&extractor, &outputs, &arch, &mut cost);
-                rebuilt_ntk_cost = cost;
+                log_rebuilt_network(verbosity, cost, &ntk);
+                rebuilt_ntk_cost += cost;
                 ntk
             }
             RewritingStrategy::LP => {
-                eprintln!("transforming");
-                let (transformed, outputs) = transform_egraph(&egraph, &arch, &outputs);
-                eprintln!("extracting");
-                let mut cost = LpInversionCostFunction::new(&arch, cost.clone());
-                let mut extractor = LpExtractor::new(&transformed, cost.clone());
-                let (expr, outputs) = extractor.solve_multiple(&outputs);
-                let (cost, ntk) = rebuild_network(&expr, &outputs, &arch, &mut cost);
-                rebuilt_ntk_cost = cost;
+                verbosity.log(Verbosity::TRACE, format_args!("transforming"));
+                let (transformed, outputs) = transform_egraph(&egraph, &arch, outputs);
+                verbosity.log(Verbosity::TRACE, format_args!("extracting"));
+                let cost = LpInversionCostFunction::new(&arch, cost.clone());
+                let (cost, ntk) = extract_with_fallback(
+                    || {
+                        let mut extractor = LpExtractor::new(&transformed, cost.clone());
+                        let (expr, outputs) = extractor.solve_multiple(&outputs);
+                        rebuild_network(&expr, &outputs, &arch, &mut cost.clone())
+                    },
+                    || {
+                        // same as RewritingStrategy::GreedyEstimate
+                        let extractor = Extractor::new(&transformed, cost.clone());
+                        rebuild_network(&extractor, &outputs, &arch, &mut cost.clone())
+                    },
+                );
+                log_rebuilt_network(verbosity, cost, &ntk);
+                rebuilt_ntk_cost += cost;
+                ntk
+            }
+            RewritingStrategy::None | RewritingStrategy::MinDepth => {
+                Extractor::new(&egraph, AstDepth)
+                    .send(NetworkReceiver::default(), outputs.iter().cloned())
+                    .unwrap()
+            }
+        };
+        let ntk = match groups.as_slice() {
+            [] => extract(strategy, &outputs),
+            [(strategy, _)] => extract(*strategy, &outputs),
+            groups => {
+                // extract the outputs of every strategy separately and merge the resulting
+                // networks, restoring the original order of the outputs afterwards
+                let networks = groups
+                    .iter()
+                    .map(|(strategy, idxs)| {
+                        let outputs = idxs.iter().map(|&i| outputs[i]).collect_vec();
+                        extract(*strategy, &outputs)
+                    })
+                    .collect_vec();
+                let mut ntk = merge_networks(&networks);
+                let mut merged_outputs = vec![None; outputs.len()];
+                for (&signal, &i) in ntk
+                    .outputs()
+                    .iter()
+                    .zip(groups.iter().flat_map(|(_, idxs)| idxs))
+                {
+                    merged_outputs[i] = Some(signal);
+                }
+                ntk.set_outputs(
+                    merged_outputs
+                        .into_iter()
+                        .map(|signal| signal.expect("every output should be extracted"))
+                        .collect(),
+                );
                 ntk
             }
-            RewritingStrategy::None => Extractor::new(&egraph, AstDepth)
-                .send(NetworkReceiver::default(), outputs.iter().cloned())
-                .unwrap(),
         };
         let t_extractor = (Instant::now() - t_extractor).as_millis() as u64;
-        eprintln!("t-extractor: {t_extractor}");
+        verbosity.log(
+            Verbosity::SUMMARY,
+            format_args!("t-extractor: {t_extractor}"),
+        );
+
+        // self-check guarding against rewrites or extractions that change the computed function
+        if let Some(inputs) = find_divergence(&received, &ntk, EQUIVALENCE_CHECK_SAMPLES) {
+            eprintln!(
+                "warning: extracted network differs from the received network for inputs {}",
+                inputs.iter().map(|&value| value as u8).join("")
+            );
+        }
+
+        if let Some(path) = &dump_path
+            && let Err(err) = fs::write(path, format_network(&ntk))
+        {
+            eprintln!(
+                "could not dump extracted network to {}: {err}",
+                path.display()
+            );
+        }
 
+        let extracted_depth = network_stats(&ntk).depth as u64;
         (
             ntk,
             RewritingStatistics {
@@ -144,7 +313,46 @@ pub fn rewriting_receiver<CT: CellType, C: OperationCost<CT>>(
                 t_runner,
                 t_trim,
                 rebuilt_ntk_cost,
+                extraction_none_count,
+                extraction_total_classes,
+                extracted_depth,
             },
         )
     })
 }
+
+fn log_rebuilt_network(verbosity: Verbosity, cost: f64, ntk: &Network<UntypedNetwork>) {
+    verbosity.log(
+        Verbosity::TRACE,
+        format_args!(
+            "rebuilt network with total cost {cost}, size {}",
+            ntk.size()
+        ),
+    );
+}
+
+/// Returns the rules used for rewriting a network for the given architecture, i.e. the rules
+/// generated for its gates followed by the given additional rules.
+pub fn rewriting_rules<CT: CellType>(
+    arch: &Architecture<CT>,
+    extra_rules: Vec<Rewrite<UntypedNetworkLanguage, LimeAnalysis>>,
+) -> Vec<Rewrite<UntypedNetworkLanguage, LimeAnalysis>> {
+    let mut rules = create_rewrites(arch);
+    rules.extend(extra_rules);
+    rules
+}
+
+/// Returns the result of `extract` or, if it panics, the result of `fallback`. egg's LP extractor
+/// panics if the solver does not find a solution.
+pub(crate) fn extract_with_fallback<T>(
+    extract: impl FnOnce() -> T,
+    fallback: impl FnOnce() -> T,
+) -> T {
+    match panic::catch_unwind(AssertUnwindSafe(extract)) {
+        Ok(result) => result,
+        Err(_) => {
+            eprintln!("warning: extraction failed, falling back to greedy extraction");
+            fallback()
+        }
+    }
+}
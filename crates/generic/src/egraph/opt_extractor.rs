@@ -20,12 +20,34 @@ pub trait OptCostFunction<L: Language, A: Analysis<L>>: Sized {
 pub struct Choices<'g, CF: OptCostFunction<L, A>, L: Language, A: Analysis<L>> {
     graph: &'g EGraph<L, A>,
     costs: FxHashMap<Id, (CF::Cost, L)>,
+    /// lowest cost found for every extractable node of an eclass
+    alternatives: FxHashMap<Id, Vec<(CF::Cost, L)>>,
 }
 
 impl<'g, CF: OptCostFunction<L, A>, L: Language, A: Analysis<L>> Choices<'g, CF, L, A> {
     pub fn find_best(&self, class: Id) -> Option<&(CF::Cost, L)> {
         self.costs.get(&self.graph.find(class))
     }
+
+    /// Returns the (up to) `n` cheapest nodes of the given eclass, ordered by their cost.
+    pub fn find_best_n(&self, class: Id, n: usize) -> Vec<(CF::Cost, L)> {
+        let Some(alternatives) = self.alternatives.get(&self.graph.find(class)) else {
+            return Vec::new();
+        };
+        let mut alternatives = alternatives.clone();
+        alternatives.sort_by(|(a, _), (b, _)| a.partial_cmp(b).unwrap());
+        alternatives.truncate(n);
+        alternatives
+    }
+
+    fn record_alternative(&mut self, class: Id, cost: CF::Cost, node: &L) {
+        let alternatives = self.alternatives.entry(class).or_default();
+        match alternatives.iter_mut().find(|(_, other)| other == node) {
+            Some((prev_cost, _)) if cost < *prev_cost => *prev_cost = cost,
+            Some(_) => {}
+            None => alternatives.push((cost, node.clone())),
+        }
+    }
 }
 
 /// An extractor heavily inspired by egg's [Extractor](eggmock::egg::Extractor), which allows
@@ -42,6 +64,7 @@ impl<'g, CF: OptCostFunction<L, A>, L: Language, A: Analysis<L>> OptExtractor<'g
             costs: Choices {
                 graph,
                 costs: Default::default(),
+                alternatives: Default::default(),
             },
         };
         extractor.find_costs();
@@ -52,6 +75,21 @@ impl<'g, CF: OptCostFunction<L, A>, L: Language, A: Analysis<L>> OptExtractor<'g
         &self.costs
     }
 
+    /// Returns the number of eclasses of the graph.
+    pub fn num_classes(&self) -> usize {
+        self.costs.graph.number_of_classes()
+    }
+
+    /// Returns the number of eclasses for which the cost function returned `None` for every node,
+    /// i.e. that cannot be extracted.
+    pub fn num_unextractable_classes(&self) -> usize {
+        self.costs
+            .graph
+            .classes()
+            .filter(|class| !self.costs.costs.contains_key(&class.id))
+            .count()
+    }
+
     fn find_costs(&mut self) {
         let mut changed = true;
         //let mut queue = BTreeSet::from_iter(leaf_eclasses(self.costs.graph));
@@ -84,10 +122,16 @@ impl<'g, CF: OptCostFunction<L, A>, L: Language, A: Analysis<L>> OptExtractor<'g
     }
 
     fn determine_class_costs(&mut self, class: &EClass<L, A::Data>) -> Option<(CF::Cost, L)> {
-        class
+        let costs = class
             .iter()
             .map(|node| (self.opt_node_cost(node, class), node))
             .filter_map(|(cost, node)| cost.map(|cost| (cost, node)))
+            .collect::<Vec<_>>();
+        for (cost, node) in &costs {
+            self.costs.record_alternative(class.id, cost.clone(), node);
+        }
+        costs
+            .into_iter()
             .min_by(|(a, _), (b, _)| a.partial_cmp(b).unwrap())
             .map(|(cost, node)| (cost, node.clone()))
     }
@@ -113,3 +157,91 @@ impl<'a, CF: OptCostFunction<L, A>, L: NetworkLanguage, A: Analysis<L>> EggExt
         &self.find_best(id).expect("class should be extractable").1
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use eggmock::egg::SymbolLang;
+
+    use super::*;
+
+    struct AstSize;
+
+    impl<A: Analysis<SymbolLang>> OptCostFunction<SymbolLang, A> for AstSize {
+        type Cost = usize;
+
+        fn cost(
+            &mut self,
+            _eclass: &EClass<SymbolLang, A::Data>,
+            enode: &SymbolLang,
+            choices: &Choices<Self, SymbolLang, A>,
+        ) -> Option<usize> {
+            let children = enode
+                .children()
+                .iter()
+                .map(|id| choices.find_best(*id).map(|(cost, _)| *cost))
+                .sum::<Option<usize>>()?;
+            Some(children + 1)
+        }
+    }
+
+    /// Like [`AstSize`], but refuses to extract `g`.
+    struct WithoutG;
+
+    impl<A: Analysis<SymbolLang>> OptCostFunction<SymbolLang, A> for WithoutG {
+        type Cost = usize;
+
+        fn cost(
+            &mut self,
+            _eclass: &EClass<SymbolLang, A::Data>,
+            enode: &SymbolLang,
+            choices: &Choices<Self, SymbolLang, A>,
+        ) -> Option<usize> {
+            if enode.op.as_str() == "g" {
+                return None;
+            }
+            let children = enode
+                .children()
+                .iter()
+                .map(|id| choices.find_best(*id).map(|(cost, _)| *cost))
+                .sum::<Option<usize>>()?;
+            Some(children + 1)
+        }
+    }
+
+    #[test]
+    fn num_unextractable_classes() {
+        let mut graph = EGraph::<SymbolLang, ()>::default();
+        graph.add_expr(&"(f (g a))".parse().unwrap());
+        graph.add_expr(&"(h b)".parse().unwrap());
+        graph.rebuild();
+
+        let extractor = OptExtractor::new(&graph, WithoutG);
+        assert_eq!(extractor.num_classes(), 5);
+        // (g a) and (f (g a)) cannot be extracted
+        assert_eq!(extractor.num_unextractable_classes(), 2);
+        assert_eq!(
+            OptExtractor::new(&graph, AstSize).num_unextractable_classes(),
+            0
+        );
+    }
+
+    #[test]
+    fn find_best_n() {
+        let mut graph = EGraph::<SymbolLang, ()>::default();
+        let nested = graph.add_expr(&"(f (g a))".parse().unwrap());
+        let flat = graph.add_expr(&"(h a)".parse().unwrap());
+        let leaf = graph.add_expr(&"b".parse().unwrap());
+        graph.union(nested, flat);
+        graph.union(nested, leaf);
+        graph.rebuild();
+
+        let extractor = OptExtractor::new(&graph, AstSize);
+        let best = extractor.choices().find_best_n(nested, 2);
+        let best = best
+            .iter()
+            .map(|(cost, node)| (*cost, node.to_string()))
+            .collect::<Vec<_>>();
+        assert_eq!(best, vec![(1, "b".to_string()), (2, "h".to_string())]);
+        assert_eq!(extractor.choices().find_best_n(nested, 5).len(), 3);
+    }
+}
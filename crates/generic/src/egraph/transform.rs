@@ -323,7 +323,7 @@ pub fn rebuild_network<CT: CellType, C: OperationCost<CT>>(
                 let gate = match instr.function.gate {
                     Gate::And => UntypedNetwork::And(inputs),
                     Gate::Xor => UntypedNetwork::Xor(inputs),
-                    Gate::Maj => UntypedNetwork::Maj(inputs),
+                    Gate::Maj => UntypedNetwork::Maj(instr.function.untied_inputs(&inputs)),
                     _ => unimplemented!(),
                 };
                 Signal::new(ntk.add(Node::Gate(gate)), instr.function.inverted)
@@ -348,10 +348,6 @@ pub fn rebuild_network<CT: CellType, C: OperationCost<CT>>(
         })
         .collect_vec();
     ntk.set_outputs(outputs);
-    eprintln!(
-        "rebuilt network with total cost {total_cost}, size {}",
-        ntk.size()
-    );
     (total_cost, ntk)
 }
 
@@ -384,7 +380,7 @@ impl<'a, CT: CellType, C: OperationCost<CT>> LpInversionCostFunction<'a, CT, C>
             let set = get_output_inverted(instr, arity);
             let cost = self
                 .cost
-                .cost(&Instruction::<CT> {
+                .operation_cost(&Instruction::<CT> {
                     typ: instr.clone(),
                     inputs: Vec::new(),
                     outputs: Vec::new(),
@@ -456,8 +452,14 @@ pub fn estimate_inversion_cost<CT: CellType>(meta: &ArchitectureMeta<CT>) -> f64
     let mut cost_n = 0;
     for src in &nodes {
         for dst in &nodes {
-            if let Some(cost) = copy_cost(&meta.copy_graph, *src, *dst, true, &FxHashSet::default())
-            {
+            if let Some(cost) = copy_cost(
+                &meta.copy_graph,
+                *src,
+                *dst,
+                true,
+                &FxHashSet::default(),
+                None,
+            ) {
                 cost_sum += cost.0;
                 cost_n += 1;
             }
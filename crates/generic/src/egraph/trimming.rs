@@ -5,11 +5,15 @@ use rustc_hash::{FxHashMap, FxHashSet};
 
 use crate::egraph::analysis::LimeAnalysis;
 
-pub fn trim_egraph<L: NetworkLanguage>(egraph: &mut EGraph<L, LimeAnalysis>, _outputs: &[Id]) {
-    trim_eclasses_commutative(egraph);
+/// Removes redundant nodes from the egraph and returns the number of removed nodes.
+pub fn trim_egraph<L: NetworkLanguage>(
+    egraph: &mut EGraph<L, LimeAnalysis>,
+    _outputs: &[Id],
+) -> usize {
+    trim_eclasses_commutative(egraph)
 }
 
-fn trim_eclasses_commutative<L: Language, N: Analysis<L>>(egraph: &mut EGraph<L, N>) {
+fn trim_eclasses_commutative<L: Language, N: Analysis<L>>(egraph: &mut EGraph<L, N>) -> usize {
     let mut removed = 0;
     let mut id_map = FxHashMap::default();
     for class in egraph.classes() {
@@ -44,5 +48,5 @@ fn trim_eclasses_commutative<L: Language, N: Analysis<L>>(egraph: &mut EGraph<L,
             }
         });
     }
-    eprintln!("removed {removed} nodes");
+    removed
 }
@@ -8,26 +8,45 @@ mod test;
 pub mod untyped_ntk;
 mod utils;
 pub mod validation;
+pub mod verbosity;
 
-use std::ffi::CString;
+use std::ffi::{CStr, CString};
 use std::os::raw::c_char;
-use std::{ffi::c_double, rc::Rc, time::Instant};
+use std::{
+    borrow::Cow,
+    ffi::{c_double, c_void},
+    fmt::{self, Display, Formatter},
+    io::{self, Write},
+    mem::{ManuallyDrop, MaybeUninit},
+    path::PathBuf,
+    rc::Rc,
+    time::Instant,
+};
 
 use derive_more::Deref;
 pub use eggmock;
-use eggmock::{Gate, Network, Node, Receiver, ReceiverFFI};
+use eggmock::{Gate, Id, Network, NetworkReceiver, Node, Receiver, ReceiverFFI, Signal};
 use itertools::Itertools;
 pub use lime_generic_def;
-use lime_generic_def::{Architecture, Cell, CellType};
+use lime_generic_def::{Architecture, Cell, CellPat, CellType};
 pub use lime_macros::define_generic_architecture;
+use rustc_hash::{FxHashMap, FxHashSet};
 
 use crate::{
-    compilation::{CandidateSelection, CompilationMode, CompilationParameters, compile},
-    copy::CopyGraph,
+    compilation::{
+        CandidateSelection, CompilationMode, CompilationParameters, CompilationResult,
+        CompileError, compile, compile_all_optimal, compile_traced, profile::Profiler,
+        rng::CompilerRng, trace::JsonTracer,
+    },
+    copy::{CopyGraph, copy_cost},
     cost::OperationCost,
-    egraph::{RewritingStatistics, RewritingStrategy, rewriting_receiver},
+    egraph::{
+        GateBiases, RewritingOptions, RewritingStatistics, RewritingStrategy, rewriting_receiver,
+    },
+    program::state::Program,
     untyped_ntk::UntypedNetwork,
     validation::rebuild_network,
+    verbosity::Verbosity,
 };
 
 #[derive(Deref)]
@@ -37,6 +56,70 @@ pub struct ArchitectureMeta<CT> {
     pub copy_graph: CopyGraph<CT>,
 }
 
+impl<CT: CellType> ArchitectureMeta<CT> {
+    /// Reports instructions writing to cell types that are neither read by any instruction nor can
+    /// be copied to a cell type that is. The results of such instructions can never be used.
+    pub fn check_instruction_reachability(&self) -> Vec<UnreadableOutput<CT>> {
+        let input_types = self
+            .instructions()
+            .iter()
+            .flat_map(|typ| typ.input.cell_types())
+            .filter(|typ| *typ != CT::CONSTANT)
+            .collect::<FxHashSet<_>>();
+        let is_readable = |output_type: CT| {
+            input_types.contains(&output_type)
+                || input_types.iter().any(|input_type| {
+                    [false, true].into_iter().any(|invert| {
+                        copy_cost(
+                            &self.copy_graph,
+                            CellPat::Type(output_type),
+                            CellPat::Type(*input_type),
+                            invert,
+                            &FxHashSet::default(),
+                            None,
+                        )
+                        .is_some()
+                    })
+                })
+        };
+        let mut warnings = Vec::new();
+        for instruction in self.instructions().iter() {
+            let output_types = instruction
+                .outputs
+                .cell_types()
+                .filter(|typ| *typ != CT::CONSTANT)
+                .unique();
+            for cell_type in output_types {
+                if !is_readable(cell_type) {
+                    warnings.push(UnreadableOutput {
+                        instruction: instruction.name.clone(),
+                        cell_type,
+                    });
+                }
+            }
+        }
+        warnings
+    }
+}
+
+/// See [`ArchitectureMeta::check_instruction_reachability`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnreadableOutput<CT> {
+    pub instruction: Cow<'static, str>,
+    pub cell_type: CT,
+}
+
+impl<CT: CellType> Display for UnreadableOutput<CT> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "instruction {} writes {} cells, which cannot be read by any instruction",
+            self.instruction,
+            self.cell_type.name()
+        )
+    }
+}
+
 #[derive(Debug)]
 #[repr(C)]
 pub struct CompilerStatistics {
@@ -49,19 +132,172 @@ pub struct CompilerStatistics {
     pub validation_success: bool,
 }
 
+/// Cost reported in [`CompilerStatistics`] if the received network is malformed.
+pub const INVALID_NETWORK_COST: c_double = -1.0;
+/// Cost reported in [`CompilerStatistics`] if the compiler failed to compile the network, see
+/// [`CompileError`].
+pub const COMPILATION_FAILED_COST: c_double = -2.0;
+
+impl CompilerStatistics {
+    fn invalid_network(rewrite: RewritingStatistics) -> Self {
+        Self::failed(rewrite, 0, INVALID_NETWORK_COST)
+    }
+
+    fn compilation_failed(rewrite: RewritingStatistics, ntk_size: u64, err: CompileError) -> Self {
+        eprintln!("compilation failed: {err}");
+        Self::failed(rewrite, ntk_size, COMPILATION_FAILED_COST)
+    }
+
+    fn failed(rewrite: RewritingStatistics, ntk_size: u64, cost: c_double) -> Self {
+        Self {
+            rewrite,
+            ntk_size,
+            t_compile: 0,
+            cost,
+            num_cells: 0,
+            num_instr: 0,
+            validation_success: false,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct CompilerResult {
     pub stats: CompilerStatistics,
     pub program: String,
+    /// see [`usage_summary_json`], `None` if nothing was compiled
+    pub usage: Option<String>,
+}
+
+/// Summarizes the cells used per cell type and the executed instructions per instruction type of
+/// the program as JSON, e.g. `{"cells":{"T":5,"DCC":2},"instructions":{"TRA":3}}`.
+pub fn usage_summary_json<CT: CellType>(program: &Program<CT>) -> String {
+    let cells = program
+        .cell_count_by_type()
+        .into_iter()
+        .map(|(typ, count)| (typ.name(), count))
+        .sorted()
+        .map(|(name, count)| format!("\"{name}\":{count}"))
+        .join(",");
+    let instructions = program
+        .instr_count_by_opcode()
+        .into_iter()
+        .sorted()
+        .map(|(name, count)| format!("\"{name}\":{count}"))
+        .join(",");
+    format!(r#"{{"cells":{{{cells}}},"instructions":{{{instructions}}}}}"#)
 }
 
 #[repr(C)]
 pub struct CompilerSettings {
     pub rewriting: RewritingStrategy,
     pub rewriting_size_factor: u64,
-    pub validator: ReceiverFFI<'static, bool>,
+    pub validator: FfiValidator,
     pub mode: CompilationMode,
     pub candidate_selector: CandidateSelection,
+    /// if non-zero, exhaustive compilation collects up to this many cost-optimal programs
+    pub tied_optimal_limit: u64,
+    /// during compiling extraction, eclasses with fewer nodes than this are assigned a structural
+    /// cost instead of compiling their subnetworks
+    pub extraction_compile_threshold: u64,
+    /// if set, the nodes of the exhaustive search tree are written to stderr as JSON lines (not
+    /// supported when collecting tied cost-optimal programs)
+    pub trace_search: bool,
+    /// a warning is printed if more than this fraction of the compiled operations are spills
+    pub spill_warning_fraction: c_double,
+    /// if set, constants read by multiple gates are copied into scratch cells before compiling
+    pub prematerialize_constants: bool,
+    /// if non-null, the network extracted after rewriting is written to this path in the format
+    /// read by [`parse_network`](untyped_ntk::parse_network)
+    pub dump_extracted_network: *const c_char,
+    /// seed of the generator used for all randomized decisions of the compiler
+    pub seed: u64,
+    /// level of diagnostic output printed to stderr, see [`Verbosity`]
+    pub verbosity: Verbosity,
+    /// multipliers of the compiled cost of gates of the respective function during compiling
+    /// extraction, see [`GateBiases`]
+    pub gate_biases: GateBiases,
+    /// if set, the time spent in the phases of the compilation is measured and printed with the
+    /// statistics
+    pub profile: bool,
+    /// cost added for every cell used by a program when comparing programs during the search, see
+    /// [`CompilationParameters::cell_reuse_bias`]
+    pub cell_reuse_bias: c_double,
+}
+
+/// Validator receiving the network rebuilt from the compiled program, see [`CompilerSettings`]. The
+/// C side may leave it zero-initialized, in which case validation fails instead of calling through
+/// a null pointer.
+#[repr(transparent)]
+pub struct FfiValidator(MaybeUninit<ReceiverFFI<'static, bool>>);
+
+/// Layout of a [`ReceiverFFI`] as declared by `eggmock::receiver_ffi`: the receiver's data pointer
+/// followed by the function pointers called on it.
+#[repr(C)]
+struct RawReceiverFFI {
+    _data: *mut c_void,
+    functions: [Option<extern "C" fn()>; RECEIVER_FFI_FUNCTIONS],
+}
+
+const RECEIVER_FFI_FUNCTIONS: usize = (size_of::<ReceiverFFI<'static, bool>>()
+    - size_of::<*mut c_void>())
+    / size_of::<extern "C" fn()>();
+
+const _: () = assert!(size_of::<RawReceiverFFI>() == size_of::<ReceiverFFI<'static, bool>>());
+
+impl FfiValidator {
+    pub fn new(receiver: ReceiverFFI<'static, bool>) -> Self {
+        Self(MaybeUninit::new(receiver))
+    }
+
+    /// Returns a validator as passed by a C caller not setting it.
+    pub fn null() -> Self {
+        Self(MaybeUninit::zeroed())
+    }
+
+    /// Returns whether any function pointer of the receiver is null. The data pointer is not
+    /// checked since receivers without state may leave it null.
+    pub fn is_null(&self) -> bool {
+        // SAFETY: the receiver is either fully initialized or zeroed, see `new` and `null`, and has
+        // the layout of `RawReceiverFFI`, whose function pointers are nullable
+        let raw = unsafe { &*self.0.as_ptr().cast::<RawReceiverFFI>() };
+        raw.functions.iter().any(Option::is_none)
+    }
+
+    /// Returns the receiver or `None` if it [is null](Self::is_null).
+    pub fn into_receiver(self) -> Option<ReceiverFFI<'static, bool>> {
+        if self.is_null() {
+            return None;
+        }
+        let validator = ManuallyDrop::new(self);
+        // SAFETY: non-null receivers are passed initialized
+        Some(unsafe { validator.0.assume_init_read() })
+    }
+}
+
+impl Drop for FfiValidator {
+    fn drop(&mut self) {
+        if !self.is_null() {
+            // SAFETY: non-null receivers are passed initialized
+            unsafe { self.0.assume_init_drop() }
+        }
+    }
+}
+
+impl From<ReceiverFFI<'static, bool>> for FfiValidator {
+    fn from(receiver: ReceiverFFI<'static, bool>) -> Self {
+        Self::new(receiver)
+    }
+}
+
+impl CompilerSettings {
+    fn dump_extracted_network_path(&self) -> Option<PathBuf> {
+        if self.dump_extracted_network.is_null() {
+            return None;
+        }
+        let path = unsafe { CStr::from_ptr(self.dump_extracted_network) };
+        Some(PathBuf::from(path.to_string_lossy().into_owned()))
+    }
 }
 
 pub fn generic_compiler_entrypoint<CT: CellType, C: OperationCost<CT>>(
@@ -74,46 +310,60 @@ pub fn generic_compiler_entrypoint<CT: CellType, C: OperationCost<CT>>(
         copy_graph: CopyGraph::build(&arch, &cost),
         arch,
     });
-    rewriting_receiver(
-        arch.clone(),
-        settings.rewriting,
-        settings.rewriting_size_factor as usize,
-        settings.candidate_selector,
-        settings.mode,
-        cost.clone(),
+    let receiver = rewriting_receiver(RewritingOptions {
+        arch: arch.clone(),
+        strategy: settings.rewriting,
+        output_strategies: FxHashMap::default(),
+        size_factor: settings.rewriting_size_factor as usize,
+        compile_threshold: settings.extraction_compile_threshold as usize,
+        gate_biases: settings.gate_biases,
+        candidate_selection: settings.candidate_selector,
+        compilation_mode: settings.mode,
+        cost: cost.clone(),
         disjunct_input_output,
-    )
+        extra_rules: Vec::new(),
+        verbosity: settings.verbosity,
+        dump_path: settings.dump_extracted_network_path(),
+    })
     .map(move |(ntk, rewriting_statistics)| {
         let input_cells = get_input_cells(&arch, &ntk);
         // add false node to match mockturtle network count if unchanged
         let ntk_size = ntk.size() as u64 + (!ntk.contains(&Node::False)) as u64;
         let t_compile = Instant::now();
-        let result = compile(CompilationParameters {
-            arch,
-            cost: cost.clone(),
-            input_cells: input_cells.clone(),
-            network: ntk,
-            mode: settings.mode,
-            candidate_selection: settings.candidate_selector,
-            disjunct_input_output,
-        })
-        .expect("compiler should succeed");
+        let result = compile_with_settings(
+            CompilationParameters {
+                arch,
+                cost: cost.clone(),
+                input_cells: input_cells.clone(),
+                network: ntk,
+                mode: settings.mode,
+                candidate_selection: settings.candidate_selector,
+                disjunct_input_output,
+                max_depth: None,
+                warm_start: None,
+                enabled_instructions: None,
+                cost_lower_bound: None,
+                spill_warning_fraction: Some(settings.spill_warning_fraction),
+                prematerialize_constants: settings.prematerialize_constants,
+                rng: CompilerRng::new(settings.seed),
+                profiler: settings.profile.then(Profiler::default),
+                cell_reuse_bias: settings.cell_reuse_bias,
+                signal_pins: FxHashMap::default(),
+                branch_and_bound: false,
+            },
+            &settings,
+        );
+        let result = match result {
+            Ok(result) => result,
+            Err(err) => {
+                return CompilerStatistics::compilation_failed(rewriting_statistics, ntk_size, err);
+            }
+        };
         let t_compile = (Instant::now() - t_compile).as_millis() as u64;
 
-        eprintln!("=== final program:");
-        eprintln!("{}", result.program);
+        report_result(&mut io::stderr().lock(), settings.verbosity, &result, &cost);
 
-        eprintln!("=== output cells:");
-        eprintln!("{}", result.outputs.iter().join("\n"));
-
-        let validation_success =
-            match rebuild_network(&result.program, &input_cells, &result.outputs) {
-                Ok(ntk) => ntk.send(settings.validator.with_input()),
-                Err(err) => {
-                    eprintln!("could not rebuild network: {err:?}");
-                    false
-                }
-            };
+        let validation_success = validate(settings.validator, &result, &input_cells);
 
         let num_cells = result.program.num_cells() as u64;
         let cost = cost.program_cost(&result.program);
@@ -128,6 +378,9 @@ pub fn generic_compiler_entrypoint<CT: CellType, C: OperationCost<CT>>(
             num_instr,
             validation_success,
         }
+    });
+    checked_receiver(receiver, || {
+        CompilerStatistics::invalid_network(RewritingStatistics::default())
     })
 }
 
@@ -141,46 +394,71 @@ pub fn generic_compiler_with_program<CT: CellType, C: OperationCost<CT>>(
         copy_graph: CopyGraph::build(&arch, &cost),
         arch,
     });
-
-    rewriting_receiver(
-        arch.clone(),
-        settings.rewriting,
-        settings.rewriting_size_factor as usize,
-        settings.candidate_selector,
-        settings.mode,
-        cost.clone(),
+    let receiver = rewriting_receiver(RewritingOptions {
+        arch: arch.clone(),
+        strategy: settings.rewriting,
+        output_strategies: FxHashMap::default(),
+        size_factor: settings.rewriting_size_factor as usize,
+        compile_threshold: settings.extraction_compile_threshold as usize,
+        gate_biases: settings.gate_biases,
+        candidate_selection: settings.candidate_selector,
+        compilation_mode: settings.mode,
+        cost: cost.clone(),
         disjunct_input_output,
-    )
+        extra_rules: Vec::new(),
+        verbosity: settings.verbosity,
+        dump_path: settings.dump_extracted_network_path(),
+    })
     .map(move |(ntk, rewriting_statistics)| {
         let input_cells = get_input_cells(&arch, &ntk);
         let ntk_size = ntk.size() as u64 + (!ntk.contains(&Node::False)) as u64;
 
         let t_compile = Instant::now();
-        let result = compile(CompilationParameters {
-            arch: arch.clone(),
-            cost: cost.clone(),
-            input_cells: input_cells.clone(),
-            network: ntk,
-            mode: settings.mode,
-            candidate_selection: settings.candidate_selector,
-            disjunct_input_output,
-        })
-        .expect("compiler should succeed");
+        let result = compile_with_settings(
+            CompilationParameters {
+                arch: arch.clone(),
+                cost: cost.clone(),
+                input_cells: input_cells.clone(),
+                network: ntk,
+                mode: settings.mode,
+                candidate_selection: settings.candidate_selector,
+                disjunct_input_output,
+                max_depth: None,
+                warm_start: None,
+                enabled_instructions: None,
+                cost_lower_bound: None,
+                spill_warning_fraction: Some(settings.spill_warning_fraction),
+                prematerialize_constants: settings.prematerialize_constants,
+                rng: CompilerRng::new(settings.seed),
+                profiler: settings.profile.then(Profiler::default),
+                cell_reuse_bias: settings.cell_reuse_bias,
+                signal_pins: FxHashMap::default(),
+                branch_and_bound: false,
+            },
+            &settings,
+        );
+        let result = match result {
+            Ok(result) => result,
+            Err(err) => {
+                return CompilerResult {
+                    stats: CompilerStatistics::compilation_failed(
+                        rewriting_statistics,
+                        ntk_size,
+                        err,
+                    ),
+                    program: String::new(),
+                    usage: None,
+                };
+            }
+        };
         let t_compile = (Instant::now() - t_compile).as_millis() as u64;
 
         let program_string = result.program.to_string();
+        let usage = usage_summary_json(&result.program);
 
-        eprintln!("=== final program:");
-        eprintln!("{}", program_string);
+        report_result(&mut io::stderr().lock(), settings.verbosity, &result, &cost);
 
-        let validation_success =
-            match rebuild_network(&result.program, &input_cells, &result.outputs) {
-                Ok(ntk) => ntk.send(settings.validator.with_input()),
-                Err(err) => {
-                    println!("could not rebuild network: {err:?}");
-                    false
-                }
-            };
+        let validation_success = validate(settings.validator, &result, &input_cells);
 
         let num_cells = result.program.num_cells() as u64;
         let cost_val = cost.program_cost(&result.program);
@@ -197,10 +475,216 @@ pub fn generic_compiler_with_program<CT: CellType, C: OperationCost<CT>>(
                 validation_success,
             },
             program: program_string,
+            usage: Some(usage),
+        }
+    });
+    checked_receiver(receiver, || CompilerResult {
+        stats: CompilerStatistics::invalid_network(RewritingStatistics::default()),
+        program: String::new(),
+        usage: None,
+    })
+}
+
+/// Compiles the given network without rewriting it first, e.g. for a network read from a file.
+/// The inputs are placed in the same cells as by [`generic_compiler_entrypoint`].
+pub fn compile_network<CT: CellType, C: OperationCost<CT>>(
+    arch: Architecture<CT>,
+    cost: C,
+    network: Network<UntypedNetwork>,
+    mode: CompilationMode,
+    candidate_selection: CandidateSelection,
+    disjunct_input_output: bool,
+) -> Result<CompilationResult<CT>, String> {
+    check_network(&network)?;
+    let input_cells = get_input_cells(&arch, &network);
+    let arch = Rc::new(ArchitectureMeta {
+        copy_graph: CopyGraph::build(&arch, &cost),
+        arch,
+    });
+    compile(CompilationParameters {
+        arch,
+        candidate_selection,
+        cost,
+        disjunct_input_output,
+        max_depth: None,
+        warm_start: None,
+        enabled_instructions: None,
+        cost_lower_bound: None,
+        spill_warning_fraction: None,
+        prematerialize_constants: false,
+        rng: CompilerRng::default(),
+        profiler: None,
+        cell_reuse_bias: 0.0,
+        signal_pins: FxHashMap::default(),
+        branch_and_bound: false,
+        input_cells,
+        mode,
+        network,
+    })
+    .map_err(|err| err.to_string())
+}
+
+/// Checks that the network received via FFI can be compiled at all, i.e. that it has inputs and
+/// outputs, that every signal refers to an existing node and that every gate has a valid arity.
+/// Majorities of networks have no tie-breaker, hence they require an odd arity.
+fn check_network(ntk: &Network<UntypedNetwork>) -> Result<(), String> {
+    if ntk.inputs().is_empty() {
+        return Err("network has no inputs".to_string());
+    }
+    if ntk.outputs().is_empty() {
+        return Err("network has no outputs".to_string());
+    }
+    let exists = |signal: &Signal| usize::from(signal.node_id()) < ntk.size();
+    if let Some(output) = ntk.outputs().iter().find(|output| !exists(output)) {
+        return Err(format!(
+            "output refers to missing node {:?}",
+            output.node_id()
+        ));
+    }
+    for id in (0..ntk.size()).map(Id::from_usize) {
+        let node = ntk.node(id);
+        if let Some(input) = node.inputs().iter().find(|input| !exists(input)) {
+            return Err(format!(
+                "node {id:?} refers to missing node {:?}",
+                input.node_id()
+            ));
+        }
+        let arity = node.inputs().len();
+        match node {
+            Node::Gate(UntypedNetwork::Maj(_)) if arity < 3 || arity.is_multiple_of(2) => {
+                return Err(format!("majority {id:?} has {arity} inputs"));
+            }
+            Node::Gate(UntypedNetwork::And(_) | UntypedNetwork::Xor(_)) if arity == 0 => {
+                return Err(format!("gate {id:?} has no inputs"));
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Wraps the `receiver` such that the network received via FFI is checked with [`check_network`]
+/// before it is passed on, e.g. before it is rewritten. Returns `invalid()` for invalid networks.
+fn checked_receiver<R: Receiver<Gate = UntypedNetwork>>(
+    receiver: R,
+    invalid: impl FnOnce() -> R::Result,
+) -> impl Receiver<Gate = UntypedNetwork, Result = R::Result> {
+    NetworkReceiver::<UntypedNetwork>::default().map(move |ntk| match check_network(&ntk) {
+        Ok(()) => ntk.send(receiver),
+        Err(err) => {
+            eprintln!("invalid network: {err}");
+            invalid()
         }
     })
 }
 
+/// Compiles the network and, if requested by the settings, prints all tied cost-optimal programs.
+/// Returns the optimal program using the fewest cells.
+fn compile_with_settings<CT: CellType, G: Gate, C: OperationCost<CT>>(
+    params: CompilationParameters<CT, G, C>,
+    settings: &CompilerSettings,
+) -> Result<CompilationResult<CT>, CompileError> {
+    let result = if settings.tied_optimal_limit == 0 {
+        if !settings.trace_search {
+            compile(params)
+        } else {
+            eprintln!("=== search trace:");
+            compile_traced(params, Some(&mut JsonTracer(io::stderr().lock())))
+        }
+    } else {
+        let results = compile_all_optimal(params, settings.tied_optimal_limit as usize);
+        let verbosity = settings.verbosity;
+        verbosity.log(
+            Verbosity::SUMMARY,
+            format_args!("=== {} tied-optimal programs", results.len()),
+        );
+        for (i, result) in results.iter().enumerate() {
+            verbosity.log(
+                Verbosity::PROGRAM,
+                format_args!("=== tied-optimal program {i}:\n{}", result.program),
+            );
+        }
+        results
+            .into_iter()
+            .next()
+            .ok_or(CompileError::NoProgramFound)
+    };
+    for warning in result.iter().flat_map(|result| &result.warnings) {
+        eprintln!("warning: {warning}");
+    }
+    result
+}
+
+/// Writes the diagnostics of a successful compilation: its statistics and profile at
+/// [`Verbosity::SUMMARY`], the program and the output cells at [`Verbosity::PROGRAM`] and the cells
+/// storing each signal at [`Verbosity::TRACE`].
+fn report_result<CT: CellType>(
+    out: &mut impl Write,
+    verbosity: Verbosity,
+    result: &CompilationResult<CT>,
+    cost: &impl OperationCost<CT>,
+) {
+    let program = &result.program;
+    verbosity.write(
+        out,
+        Verbosity::SUMMARY,
+        format_args!(
+            "=== statistics: cost {}, {} cells, {} instructions",
+            cost.program_cost(program),
+            program.num_cells(),
+            program.instructions().count()
+        ),
+    );
+    if let Some(profile) = &result.profile {
+        verbosity.write(
+            out,
+            Verbosity::SUMMARY,
+            format_args!("=== profile: {profile}"),
+        );
+    }
+    verbosity.write(
+        out,
+        Verbosity::PROGRAM,
+        format_args!("=== final program:\n{program}"),
+    );
+    verbosity.write(
+        out,
+        Verbosity::PROGRAM,
+        format_args!("=== output cells:\n{}", result.outputs.iter().join("\n")),
+    );
+    let placement = result
+        .signal_placement
+        .iter()
+        .sorted_by_key(|(signal, _)| (signal.node_id(), signal.is_inverted()))
+        .map(|(signal, cells)| format!("{signal:?}: {}", cells.iter().join(", ")))
+        .join("\n");
+    verbosity.write(
+        out,
+        Verbosity::TRACE,
+        format_args!("=== signal placement:\n{placement}"),
+    );
+}
+
+/// Rebuilds the network computed by the compiled program and passes it to the validator. Fails
+/// without calling the validator if it is null.
+fn validate<CT: CellType>(
+    validator: FfiValidator,
+    result: &CompilationResult<CT>,
+    input_cells: &[Cell<CT>],
+) -> bool {
+    let Some(validator) = validator.into_receiver() else {
+        eprintln!("warning: validator is null, skipping validation");
+        return false;
+    };
+    match rebuild_network(&result.program, input_cells, &result.outputs) {
+        Ok(ntk) => ntk.send(validator.with_input()),
+        Err(err) => {
+            eprintln!("could not rebuild network: {err:?}");
+            false
+        }
+    }
+}
+
 fn get_input_cells<CT: CellType, G: Gate>(
     arch: &Architecture<CT>,
     ntk: &Network<G>,
@@ -227,6 +711,9 @@ pub struct CompilerStatisticsFfi {
     pub num_instr: u64,
     pub validation_success: bool,
     pub program_str: *const c_char,
+    /// see [`usage_summary_json`], null if nothing was compiled, free with
+    /// [`gp_free_program_string`]
+    pub usage_str: *const c_char,
 }
 
 #[unsafe(no_mangle)]
@@ -242,6 +729,11 @@ pub fn map_result_to_ffi(
     r.map(|res| {
         let cstr = CString::new(res.program).expect("CString conversion failed");
         let ptr = cstr.into_raw();
+        let usage_ptr = res.usage.map_or(std::ptr::null_mut(), |usage| {
+            CString::new(usage)
+                .expect("CString conversion failed")
+                .into_raw()
+        });
         CompilerStatisticsFfi {
             rewrite: res.stats.rewrite,
             ntk_size: res.stats.ntk_size,
@@ -251,6 +743,7 @@ pub fn map_result_to_ffi(
             num_instr: res.stats.num_instr,
             validation_success: res.stats.validation_success,
             program_str: ptr,
+            usage_str: usage_ptr,
         }
     })
 }
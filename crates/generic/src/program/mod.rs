@@ -1,4 +1,5 @@
 pub mod collection;
+pub mod ssa;
 pub mod state;
 
 use std::rc::Rc;
@@ -14,6 +15,10 @@ use crate::{
     program::state::{Candidates, CellStates, Operation, Program, StateSavepoint, Uses},
 };
 
+/// Number of free cells of a type with an infinite amount of cells that are considered when
+/// searching for the most cheaply accessible free cell.
+const INFINITE_FREE_CELL_CANDIDATES: usize = 16;
+
 pub trait ProgramVersion {
     type CT: CellType;
     type G: Gate;
@@ -48,13 +53,22 @@ pub trait ProgramVersion {
         typ: Self::CT,
         not: &impl Set<Cell<Self::CT>>,
     ) -> Option<Cell<Self::CT>> {
-        if let Some(cell) = self
+        let cost = &self.parameters().cost;
+        let free_cells = self
             .state()
             .free_cells(typ)
             .iter()
             .map(|cell_idx| Cell::new(typ, cell_idx))
-            .find(|cell| !not.contains(cell))
-        {
+            .filter(|cell| !not.contains(cell));
+        // prefer cheaply accessible cells, the free cells of types with an infinite amount of cells
+        // are infinite as well, so only consider some of them
+        let free_cell = match typ.count() {
+            Some(_) => free_cells.min_by_key(|cell| (cost.cell_access_cost(*cell), cell.index())),
+            None => free_cells
+                .take(INFINITE_FREE_CELL_CANDIDATES)
+                .min_by_key(|cell| (cost.cell_access_cost(*cell), cell.index())),
+        };
+        if let Some(cell) = free_cell {
             return Some(cell);
         }
         let cell = typ.cell_iter().find(|cell| !not.contains(cell))?;
@@ -89,10 +103,9 @@ pub trait ProgramVersion {
     }
 
     fn is_last_use(&self, id: Id) -> bool {
-        let uses = self.uses().get(id);
-        let all_uses = self.parameters().network.node_output_ids(id).len()
-            + self.output_ids().contains(&id) as usize;
-        uses + 1 >= all_uses
+        self.uses()
+            .remaining(id, &self.parameters().network, self.output_ids())
+            <= 1
     }
 }
 
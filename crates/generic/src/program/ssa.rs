@@ -0,0 +1,175 @@
+use std::{
+    fmt::{self, Display, Formatter},
+    ops::BitXor,
+};
+
+use itertools::Itertools;
+use lime_generic_def::{Cell, CellType, Function};
+use rustc_hash::FxHashMap;
+
+use crate::program::state::Program;
+
+/// Value read by an [`SsaStatement`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SsaValue<CT> {
+    Constant(bool),
+    /// value held by the cell before the program is executed
+    Input(Cell<CT>),
+    /// result of the statement with the given index
+    Temp(usize),
+}
+
+/// Possibly inverted [`SsaValue`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SsaOperand<CT> {
+    pub value: SsaValue<CT>,
+    pub inverted: bool,
+}
+
+/// Assigns the result of applying `function` to `inputs` to the temporary `temp`. Only
+/// temporaries of preceding statements are referenced by `inputs`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SsaStatement<CT> {
+    pub temp: usize,
+    pub function: Function,
+    pub inputs: Vec<SsaOperand<CT>>,
+}
+
+impl<CT> SsaOperand<CT> {
+    fn constant(value: bool) -> Self {
+        Self {
+            value: SsaValue::Constant(value),
+            inverted: false,
+        }
+    }
+}
+
+impl<CT> BitXor<bool> for SsaOperand<CT> {
+    type Output = Self;
+
+    fn bitxor(self, inverted: bool) -> Self {
+        match self.value {
+            SsaValue::Constant(value) => Self::constant(value ^ inverted),
+            _ => Self {
+                inverted: self.inverted ^ inverted,
+                ..self
+            },
+        }
+    }
+}
+
+impl<CT: CellType> Program<CT> {
+    /// Reconstructs the dataflow of this program, abstracting away the cells. Every statement
+    /// corresponds to an instruction computing a gate, instructions computing a
+    /// [secondary function](lime_generic_def::InstructionType::secondary) result in a second
+    /// statement. Instructions only copying a value (i.e.
+    /// computing an identity) or computing a constant do not result in statements.
+    pub fn to_ssa(&self) -> Vec<SsaStatement<CT>> {
+        self.to_ssa_with_cells().0
+    }
+
+    /// Like [`Program::to_ssa`], but additionally returns the operands held by the cells written
+    /// by the program after its execution.
+    pub fn to_ssa_with_cells(
+        &self,
+    ) -> (Vec<SsaStatement<CT>>, FxHashMap<Cell<CT>, SsaOperand<CT>>) {
+        let mut statements = Vec::new();
+        let mut cells = FxHashMap::default();
+        cells.insert(CT::constant(false), SsaOperand::constant(false));
+        cells.insert(CT::constant(true), SsaOperand::constant(true));
+        for instruction in self.instructions() {
+            let (in_offset, input_cells, _) =
+                instruction.typ.input_range.slice(&instruction.inputs);
+            let inputs = input_cells
+                .iter()
+                .enumerate()
+                .map(|(in_idx, cell)| {
+                    let operand = cells.get(cell).copied().unwrap_or(SsaOperand {
+                        value: SsaValue::Input(*cell),
+                        inverted: false,
+                    });
+                    operand
+                        ^ instruction
+                            .typ
+                            .input_inverted
+                            .contains(&(in_offset + in_idx))
+                })
+                .collect_vec();
+
+            let mut apply = |function: Function| {
+                let mut evaluation = function.evaluate(inputs.len());
+                let mut unknown = Vec::new();
+                for (idx, input) in inputs.iter().enumerate() {
+                    match input.value {
+                        SsaValue::Constant(value) => evaluation.add(idx, value),
+                        _ => unknown.push((idx, *input)),
+                    }
+                }
+                // a single unknown input is not added, hence the evaluation determines whether
+                // the instruction computes its identity
+                if let [(_, input)] = unknown[..]
+                    && let Some(inverted) = evaluation.id_inverted()
+                {
+                    return input ^ inverted;
+                }
+                for (idx, _) in &unknown {
+                    evaluation.add_unknown(*idx);
+                }
+                if let Some(value) = evaluation.evaluate() {
+                    return SsaOperand::constant(value);
+                }
+                let temp = statements.len();
+                statements.push(SsaStatement {
+                    temp,
+                    function,
+                    inputs: inputs.clone(),
+                });
+                SsaOperand {
+                    value: SsaValue::Temp(temp),
+                    inverted: false,
+                }
+            };
+            let result = apply(instruction.typ.function);
+            let secondary = instruction.typ.secondary.map(apply);
+            let secondary_output = instruction.secondary_output();
+            for op in instruction.write_operands() {
+                let result = match secondary {
+                    Some(secondary) if Some(op) == secondary_output => secondary,
+                    _ => result,
+                };
+                // keep both constant cells consistent, they only differ in their inversion
+                let op = op.normalize_constant();
+                if op.cell == CT::constant(false) {
+                    cells.insert(CT::constant(true), result ^ !op.inverted);
+                }
+                cells.insert(op.cell, result ^ op.inverted);
+            }
+        }
+        (statements, cells)
+    }
+}
+
+impl<CT: CellType> Display for SsaOperand<CT> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if self.inverted {
+            write!(f, "!")?;
+        }
+        match self.value {
+            SsaValue::Constant(value) => write!(f, "{value}"),
+            SsaValue::Input(cell) => write!(f, "{cell}"),
+            SsaValue::Temp(temp) => write!(f, "t{temp}"),
+        }
+    }
+}
+
+impl<CT: CellType> Display for SsaStatement<CT> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "t{} = {}({})",
+            self.temp,
+            self.function,
+            self.inputs.iter().join(", ")
+        )
+    }
+}
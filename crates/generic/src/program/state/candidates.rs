@@ -1,11 +1,15 @@
-use std::collections::hash_map::Entry;
+use std::collections::{BTreeSet, hash_map::Entry};
 
 use derive_more::{Deref, DerefMut};
 use eggmock::Id;
-use rustc_hash::{FxHashMap, FxHashSet};
+use rustc_hash::FxHashMap;
 
+/// The nodes that can currently be computed.
+///
+/// Iterates the candidates in ascending order of their ids, independent of the order in which they
+/// were added or removed, so that candidate selection and thus compilation are reproducible.
 #[derive(Default, Debug, Deref, DerefMut)]
-pub struct Candidates(FxHashSet<Id>);
+pub struct Candidates(BTreeSet<Id>);
 
 impl Candidates {
     pub fn add(&mut self, candidate: Id) -> bool {
@@ -132,3 +136,38 @@ impl ChangeMap {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_iteration_order() {
+        let ids = [5, 1, 4, 2, 3].map(Id::from);
+        let mut candidates = Candidates::default();
+        for id in ids {
+            candidates.add(id);
+        }
+        let expected = [1, 2, 3, 4, 5].map(Id::from);
+        assert!(candidates.iter().copied().eq(expected));
+
+        // undone and replayed changes do not affect the order
+        let mut delta = CandidatesDelta::default();
+        {
+            let mut sp = candidates.savepoint();
+            sp.remove(Id::from(2));
+            sp.add(Id::from(0));
+            sp.append_to_delta(&mut delta);
+        }
+        assert!(candidates.iter().copied().eq(expected));
+        candidates.savepoint().replay(&delta);
+        assert!(candidates.iter().copied().eq(expected));
+        {
+            let mut sp = candidates.savepoint();
+            sp.replay(&delta);
+            sp.retain();
+        }
+        let expected = [0, 1, 3, 4, 5].map(Id::from);
+        assert!(candidates.iter().copied().eq(expected));
+    }
+}
@@ -157,9 +157,60 @@ impl<CT: CellType> CellStatesStore<CT> {
         self.free_cells.get(&typ).expect("unknown cell type")
     }
 
+    /// Returns the cells containing each stored signal.
+    pub fn placement(&self) -> FxHashMap<Signal, Vec<Cell<CT>>> {
+        self.signal_to_cells
+            .iter()
+            .filter(|(_, cells)| !cells.is_empty())
+            .map(|(signal, cells)| (*signal, cells.clone()))
+            .collect()
+    }
+
     pub fn savepoint(&mut self) -> CellStatesSavepoint<'_, CT> {
         CellStatesSavepoint::new(self)
     }
+
+    /// Panics if the cell -> signal mapping, the signal -> cells mapping and the free cells do not
+    /// agree with each other.
+    #[cfg(debug_assertions)]
+    pub fn assert_consistent(&self) {
+        for (cell, signal) in &self.cell_to_signal {
+            assert!(
+                self.cells_with(*signal).any(|other| other == *cell),
+                "{cell:?} stores {signal:?} but is not in the signal -> cells mapping"
+            );
+            assert!(
+                !self.free_cells(cell.typ()).contains(cell.index()),
+                "{cell:?} stores {signal:?} but is marked as free"
+            );
+        }
+        for (signal, cells) in &self.signal_to_cells {
+            for (i, cell) in cells.iter().enumerate() {
+                assert_eq!(
+                    self.cell(*cell),
+                    Some(*signal),
+                    "{cell:?} is in the signal -> cells mapping of {signal:?}"
+                );
+                assert!(
+                    !cells[..i].contains(cell),
+                    "{cell:?} is in the signal -> cells mapping of {signal:?} more than once"
+                );
+            }
+        }
+        for (&typ, free) in &self.free_cells {
+            let Some(count) = typ.count().filter(|_| typ != CT::CONSTANT) else {
+                continue;
+            };
+            for idx in 0..count {
+                let cell = Cell::new(typ, idx);
+                assert_eq!(
+                    free.contains(idx),
+                    !self.cell_to_signal.contains_key(&cell),
+                    "free cells of {typ:?} do not match the state of {cell:?}"
+                );
+            }
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -201,6 +252,35 @@ impl<'a, CT: CellType> CellStatesSavepoint<'a, CT> {
     pub fn retain(mut self) {
         self.previous.clear();
     }
+    pub fn placement(&self) -> FxHashMap<Signal, Vec<Cell<CT>>> {
+        self.store.placement()
+    }
+    #[cfg(debug_assertions)]
+    pub fn assert_consistent(&self) {
+        self.store.assert_consistent()
+    }
+    /// Returns the cells whose signal differs between the given store and the current state,
+    /// ordered by cell, together with the signal in `other` (before) and in the current state
+    /// (after). Useful to find where two compilations of the same network diverge.
+    pub fn diff(
+        &self,
+        other: &CellStatesStore<CT>,
+    ) -> Vec<(Cell<CT>, Option<Signal>, Option<Signal>)> {
+        let mut cells = Vec::from_iter(
+            other
+                .cell_to_signal
+                .keys()
+                .chain(self.store.cell_to_signal.keys())
+                .copied(),
+        );
+        cells.sort();
+        cells.dedup();
+        cells
+            .into_iter()
+            .map(|cell| (cell, other.cell(cell), self.store.cell(cell)))
+            .filter(|(_, before, after)| before != after)
+            .collect()
+    }
 }
 
 impl<'a, CT: CellType> CellStates<CT> for CellStatesSavepoint<'a, CT> {
@@ -236,3 +316,89 @@ impl<'a, CT: CellType> Drop for CellStatesSavepoint<'a, CT> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::definitions::{Ambit, AmbitCellType};
+
+    #[test]
+    fn test_diff() {
+        let arch = Ambit::new();
+        let cell = |i| Cell::new(AmbitCellType::D, i);
+        let signal = |id: usize| Signal::new(Id::from(id), false);
+
+        let mut before = CellStatesStore::new(&arch);
+        let mut after = CellStatesStore::new(&arch);
+        for store in [&mut before, &mut after] {
+            store.set(cell(0), signal(1));
+            store.set(cell(1), signal(2));
+            store.set(cell(2), signal(3));
+        }
+        let mut savepoint = after.savepoint();
+        assert!(savepoint.diff(&before).is_empty());
+
+        savepoint.set(cell(0), !signal(1));
+        savepoint.set(cell(1), None);
+        savepoint.set(cell(2), signal(3));
+        savepoint.set(cell(3), signal(4));
+        assert_eq!(
+            savepoint.diff(&before),
+            [
+                (cell(0), Some(signal(1)), Some(!signal(1))),
+                (cell(1), Some(signal(2)), None),
+                (cell(3), None, Some(signal(4))),
+            ]
+        );
+
+        // dropping the savepoint reverts its changes
+        drop(savepoint);
+        assert!(after.savepoint().diff(&before).is_empty());
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    fn test_assert_consistent() {
+        let arch = Ambit::new();
+        let mut store = CellStatesStore::new(&arch);
+        store.set(
+            Cell::new(AmbitCellType::D, 0),
+            Signal::new(Id::from(1), false),
+        );
+        store.set(
+            Cell::new(AmbitCellType::T, 0),
+            Signal::new(Id::from(2), true),
+        );
+        store.assert_consistent();
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "is marked as free")]
+    fn test_assert_consistent_free_cells() {
+        let arch = Ambit::new();
+        let cell = Cell::new(AmbitCellType::D, 0);
+        let mut store = CellStatesStore::new(&arch);
+        store.set(cell, Signal::new(Id::from(1), false));
+        store
+            .free_cells
+            .get_mut(&cell.typ())
+            .unwrap()
+            .add(cell.index());
+        store.assert_consistent();
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "is not in the signal -> cells mapping")]
+    fn test_assert_consistent_reverse_mapping() {
+        let arch = Ambit::new();
+        let mut store = CellStatesStore::new(&arch);
+        store.set(
+            Cell::new(AmbitCellType::D, 0),
+            Signal::new(Id::from(1), false),
+        );
+        store.signal_to_cells.clear();
+        store.assert_consistent();
+    }
+}
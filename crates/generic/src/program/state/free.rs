@@ -82,6 +82,19 @@ impl FreeCells {
         }
     }
 
+    /// Returns the number of free cells or `usize::MAX` if infinitely many cells are available.
+    pub fn count_free(&self) -> usize {
+        match &self.0 {
+            FreeCellsInner::RightOpen { .. } => usize::MAX,
+            FreeCellsInner::Set(set) => set.len(),
+        }
+    }
+
+    /// Returns whether no cell is free. Never the case if infinitely many cells are available.
+    pub fn is_exhausted(&self) -> bool {
+        self.count_free() == 0
+    }
+
     pub fn contains(&self, idx: CellIndex) -> bool {
         match &self.0 {
             FreeCellsInner::RightOpen { before, first } => *first <= idx || before.contains(&idx),
@@ -105,4 +118,24 @@ mod tests {
             matches!(&cells.0, FreeCellsInner::RightOpen { before, first } if before.iter().eq(&[0, 1]) && *first == 3)
         )
     }
+
+    #[test]
+    fn test_free_cells_count() {
+        let mut cells = FreeCells::new(Some(3));
+        assert_eq!(cells.count_free(), 3);
+        cells.remove(1);
+        assert_eq!(cells.count_free(), 2);
+        cells.remove(0);
+        cells.remove(2);
+        assert_eq!(cells.count_free(), 0);
+        assert!(cells.is_exhausted());
+        cells.add(2);
+        assert_eq!(cells.count_free(), 1);
+        assert!(!cells.is_exhausted());
+
+        let mut cells = FreeCells::new(None);
+        cells.remove(2);
+        assert_eq!(cells.count_free(), usize::MAX);
+        assert!(!cells.is_exhausted());
+    }
 }
@@ -2,16 +2,18 @@ mod candidates;
 mod cells;
 mod free;
 mod program;
+mod topology;
 mod uses;
 
 use derive_where::derive_where;
 use eggmock::{Gate, Id, Network, Node, Signal};
-use itertools::Itertools;
 use lime_generic_def::{Cell, CellType};
-use rustc_hash::FxHashSet;
+use rustc_hash::{FxHashMap, FxHashSet};
 
 use crate::{
-    compilation::CompilationParameters, cost::OperationCost, program::state::free::FreeCells,
+    compilation::CompilationParameters,
+    cost::OperationCost,
+    program::state::{free::FreeCells, topology::Topology},
 };
 
 pub use self::{candidates::*, cells::*, program::*, uses::*};
@@ -26,6 +28,8 @@ pub struct State<'a, CT: CellType, G> {
     network: &'a Network<G>,
     #[derive_where(skip)]
     output_ids: FxHashSet<Id>,
+    #[derive_where(skip)]
+    topology: Topology,
 }
 
 impl<'a, CT: CellType, G: Gate> State<'a, CT, G> {
@@ -34,6 +38,7 @@ impl<'a, CT: CellType, G: Gate> State<'a, CT, G> {
         let mut candidates = Candidates::default();
         let network = &params.network;
         let output_ids = network.outputs().iter().map(|sig| sig.node_id()).collect();
+        let input_cells = params.initial_input_cells();
         for &leaf_id in network.leaves() {
             let leaf = network.node(leaf_id);
             match leaf {
@@ -43,7 +48,7 @@ impl<'a, CT: CellType, G: Gate> State<'a, CT, G> {
                     }
                 }
                 Node::Input(i) => {
-                    cells.set(params.input_cells[*i as usize], Signal::new(leaf_id, false));
+                    cells.set(input_cells[*i as usize], Signal::new(leaf_id, false));
                 }
                 Node::Gate(_) => unreachable!("gate cannot be a leaf"),
             }
@@ -66,6 +71,7 @@ impl<'a, CT: CellType, G: Gate> State<'a, CT, G> {
             uses: Uses::new(params.network.leaves().iter().copied()),
             network,
             output_ids,
+            topology: Topology::new(network),
         }
     }
     pub fn savepoint(&mut self) -> StateSavepoint<'_, CT, G> {
@@ -76,6 +82,7 @@ impl<'a, CT: CellType, G: Gate> State<'a, CT, G> {
             uses: UsesSavepoint::new(&mut self.uses),
             network: self.network,
             output_ids: &self.output_ids,
+            topology: &self.topology,
         }
     }
     pub fn candidates(&self) -> &Candidates {
@@ -96,6 +103,8 @@ pub struct StateSavepoint<'a, CT: CellType, G> {
     network: &'a Network<G>,
     #[derive_where(skip)]
     output_ids: &'a FxHashSet<Id>,
+    #[derive_where(skip)]
+    topology: &'a Topology,
 }
 
 #[derive(Clone)]
@@ -133,6 +142,7 @@ impl<'a, CT: CellType, G: Gate> StateSavepoint<'a, CT, G> {
             candidates: self.candidates.savepoint(),
             network: self.network,
             output_ids: self.output_ids,
+            topology: self.topology,
         }
     }
 
@@ -156,6 +166,11 @@ impl<'a, CT: CellType, G: Gate> StateSavepoint<'a, CT, G> {
         self.program.program()
     }
 
+    /// Returns the cells containing each stored signal.
+    pub fn signal_placement(&self) -> FxHashMap<Signal, Vec<Cell<CT>>> {
+        self.cells.placement()
+    }
+
     pub fn append_to_delta(&self, delta: &mut StateDelta<CT>) {
         self.program.append_to_delta(&mut delta.program);
         self.cells.append_to_delta(&mut delta.cells);
@@ -168,14 +183,37 @@ impl<'a, CT: CellType, G: Gate> StateSavepoint<'a, CT, G> {
         self.cells.replay(&delta.cells);
         self.candidates.replay(&delta.candidates);
         self.uses.replay(delta.uses);
+        #[cfg(debug_assertions)]
+        self.assert_consistent();
     }
 
     pub fn retain(self) {
+        #[cfg(debug_assertions)]
+        self.assert_consistent();
         self.program.retain();
         self.cells.retain();
         self.candidates.retain();
         self.uses.retain();
     }
+
+    /// Panics if the cell states are inconsistent (see [`CellStatesStore::assert_consistent`]) or
+    /// if a candidate is already computed or reads a signal that is not stored in any cell.
+    #[cfg(debug_assertions)]
+    pub fn assert_consistent(&self) {
+        self.cells.assert_consistent();
+        for &id in self.candidates().iter() {
+            assert!(
+                !self.cells.contains_id(id),
+                "candidate {id:?} is already computed"
+            );
+            for input in self.network.node(id).inputs() {
+                assert!(
+                    self.cells.contains_id(input.node_id()),
+                    "input {input:?} of candidate {id:?} is not stored in any cell"
+                );
+            }
+        }
+    }
 }
 
 impl<'a, CT: CellType, G: Gate> CellStates<CT> for StateSavepoint<'a, CT, G> {
@@ -194,30 +232,18 @@ impl<'a, CT: CellType, G: Gate> CellStates<CT> for StateSavepoint<'a, CT, G> {
             && self.candidates.remove(signal.node_id())
         {
             // we did just compute a new candidate, check if that gave us new candidates
-            for fanout_id in self
-                .network
-                .node_outputs(signal.node_id())
-                .iter()
-                .map(Signal::node_id)
-            {
-                let fanout = self.network.node(fanout_id);
-                if fanout
-                    .inputs()
+            for &fanout_id in self.topology.fanouts(signal.node_id()) {
+                if self
+                    .topology
+                    .fanins(fanout_id)
                     .iter()
-                    .all(|fanin| self.cells.contains_id(fanin.node_id()))
+                    .all(|&fanin| self.cells.contains_id(fanin))
                 {
                     self.candidates.add(fanout_id);
                 }
             }
-            for id in self
-                .network
-                .node(signal.node_id())
-                .inputs()
-                .iter()
-                .map(|signal| signal.node_id())
-                .unique()
-            {
-                if self.uses.increment(id) >= self.network.node_output_ids(id).len()
+            for &id in self.topology.fanins(signal.node_id()) {
+                if self.uses.increment(id) >= self.topology.fanout_count(id)
                     && !self.output_ids.contains(&id)
                 {
                     self.cells.clear_all_by_id(id);
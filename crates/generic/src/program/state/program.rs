@@ -1,15 +1,26 @@
 use std::{
-    fmt::{Debug, Display, Formatter},
+    borrow::Cow,
+    fmt::{Debug, Display, Formatter, Write as _},
     slice,
 };
 
 use eggmock::Id;
-use lime_generic_def::{Cell, CellType, Instruction};
-use rustc_hash::FxHashSet;
+use itertools::Itertools;
+use lime_generic_def::{Architecture, Cell, CellType, Gate, Instruction};
+use ordered_float::OrderedFloat;
+use rustc_hash::{FxHashMap, FxHashSet};
+
+use crate::{
+    compilation::optimization::{coalesce_broadcasts, minimize_inversions},
+    copy::CopyGraph,
+    cost::{Cost, CostExt, OperationCost},
+};
 
 #[derive(Debug, Clone)]
 pub enum Operation<CT> {
-    Candidate(Instruction<CT>, Id),
+    /// Instruction computing a candidate and, into its
+    /// [secondary output](Instruction::secondary_output), possibly another one.
+    Candidate(Instruction<CT>, Id, Option<Id>),
     Other {
         instructions: Vec<Instruction<CT>>,
         comment: Option<String>,
@@ -27,14 +38,14 @@ pub enum Operation<CT> {
 impl<CT> Operation<CT> {
     pub fn instructions(&self) -> &[Instruction<CT>] {
         match self {
-            Self::Candidate(instr, _) => slice::from_ref(instr),
+            Self::Candidate(instr, ..) => slice::from_ref(instr),
             Self::Copy { instructions, .. } => instructions,
             Self::Other { instructions, .. } => instructions,
         }
     }
     pub fn instructions_mut(&mut self) -> &mut [Instruction<CT>] {
         match self {
-            Self::Candidate(instr, _) => slice::from_mut(instr),
+            Self::Candidate(instr, ..) => slice::from_mut(instr),
             Self::Copy { instructions, .. } => instructions,
             Self::Other { instructions, .. } => instructions,
         }
@@ -43,8 +54,16 @@ impl<CT> Operation<CT> {
     where
         CT: CellType,
     {
+        self.comment_with_namer(&|cell: Cell<CT>| cell.to_string())
+    }
+
+    /// Like [`Operation::comment`], but renders cells using the given namer.
+    fn comment_with_namer(&self, namer: &impl Fn(Cell<CT>) -> String) -> Option<String> {
         match self {
-            Self::Candidate(_, id) => Some(format!("compute candidate {id:?}")),
+            Self::Candidate(_, id, None) => Some(format!("compute candidate {id:?}")),
+            Self::Candidate(_, id, Some(secondary)) => {
+                Some(format!("compute candidates {id:?} and {secondary:?}"))
+            }
             Self::Copy {
                 from,
                 to,
@@ -54,25 +73,37 @@ impl<CT> Operation<CT> {
             } => {
                 let inv = if *inverted { "!" } else { "" };
                 let copy = if *spill { "spill" } else { "copy" };
-                Some(format!("{copy} {from} {inv}-> {to}"))
+                Some(format!("{copy} {} {inv}-> {}", namer(*from), namer(*to)))
             }
             Self::Other { comment, .. } => comment.clone(),
         }
     }
 }
 
-impl<CT: CellType> Display for Operation<CT> {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        if let Some(comment) = self.comment() {
+impl<CT: CellType> Operation<CT> {
+    /// Writes the operation like its [`Display`] implementation, but renders cells using the
+    /// given namer.
+    fn fmt_with_namer(
+        &self,
+        f: &mut Formatter<'_>,
+        namer: &impl Fn(Cell<CT>) -> String,
+    ) -> std::fmt::Result {
+        if let Some(comment) = self.comment_with_namer(namer) {
             writeln!(f, "// {comment}")?;
         }
         for instr in self.instructions() {
-            writeln!(f, "{instr}")?;
+            writeln!(f, "{}", instr.display_with_namer(namer))?;
         }
         Ok(())
     }
 }
 
+impl<CT: CellType> Display for Operation<CT> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        self.fmt_with_namer(f, &|cell: Cell<CT>| cell.to_string())
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Program<CT>(pub Vec<Operation<CT>>);
 
@@ -93,7 +124,98 @@ impl<CT> Program<CT> {
         self.0.iter().flat_map(Operation::instructions)
     }
 
+    /// Returns the number of instructions computing gates of the network, i.e. excluding copies
+    /// and spills.
+    pub fn compute_instruction_count(&self) -> usize {
+        self.0
+            .iter()
+            .filter(|op| matches!(op, Operation::Candidate(..)))
+            .count()
+    }
+
     pub fn num_cells(&self) -> usize
+    where
+        CT: CellType,
+    {
+        self.cells().len()
+    }
+
+    /// Returns the number of distinct cells used per cell type.
+    pub fn cell_count_by_type(&self) -> FxHashMap<CT, usize>
+    where
+        CT: CellType,
+    {
+        let mut counts = FxHashMap::default();
+        for cell in self.cells() {
+            *counts.entry(cell.typ()).or_default() += 1;
+        }
+        counts
+    }
+
+    /// Returns the number of executed instructions per instruction type.
+    pub fn instr_count_by_opcode(&self) -> FxHashMap<Cow<'static, str>, usize> {
+        let mut counts = FxHashMap::default();
+        for instr in self.instructions() {
+            *counts.entry(instr.typ.name.clone()).or_default() += 1;
+        }
+        counts
+    }
+
+    /// Applies the cost function to every instruction of the program and summarizes the results.
+    pub fn cost_breakdown(&self, cost: &impl OperationCost<CT>) -> CostBreakdown
+    where
+        CT: CellType,
+    {
+        let costs = self
+            .instructions()
+            .map(|instr| cost.instruction_cost(instr))
+            .collect_vec();
+        let total = costs.iter().copied().fold(Cost::ZERO, Cost::checked_add);
+        CostBreakdown {
+            total,
+            max_op: costs.iter().max().copied().unwrap_or_default(),
+            min_op: costs.iter().min().copied().unwrap_or_default(),
+            mean: if costs.is_empty() {
+                Cost::default()
+            } else {
+                total / OrderedFloat(costs.len() as f64)
+            },
+        }
+    }
+
+    /// Returns the cost of keeping cells occupied, i.e. the [`OperationCost::occupancy_cost`] of
+    /// every cell summed over the instructions its values are live for. A value is live from the
+    /// instruction writing it to the last instruction reading it, values read before being
+    /// written, e.g. of input cells, are live from the start of the program. Constant cells are
+    /// never occupied.
+    pub fn occupancy_cost(&self, cost: &impl OperationCost<CT>) -> Cost
+    where
+        CT: CellType,
+    {
+        let occupancy = |cell: Cell<CT>, (write, last_read): (usize, usize)| {
+            cost.occupancy_cost(cell.typ()) * OrderedFloat((last_read - write) as f64)
+        };
+        let occupies = |cell: &Cell<CT>| cell.typ() != CT::CONSTANT;
+        // instruction writing the current value of every cell and the last one reading it
+        let mut spans = FxHashMap::<Cell<CT>, (usize, usize)>::default();
+        let mut total = Cost::ZERO;
+        for (i, instr) in self.instructions().enumerate() {
+            for cell in instr.read_cells().filter(occupies) {
+                spans.entry(cell).or_insert((0, 0)).1 = i;
+            }
+            for cell in instr.write_cells().filter(occupies) {
+                if let Some(span) = spans.insert(cell, (i, i)) {
+                    total = total.checked_add(occupancy(cell, span));
+                }
+            }
+        }
+        spans
+            .into_iter()
+            .map(|(cell, span)| occupancy(cell, span))
+            .fold(total, Cost::checked_add)
+    }
+
+    pub(crate) fn cells(&self) -> FxHashSet<Cell<CT>>
     where
         CT: CellType,
     {
@@ -103,8 +225,188 @@ impl<CT> Program<CT> {
                 let output_cells = instr.outputs.iter().map(|op| op.cell);
                 input_cells.chain(output_cells)
             })
-            .collect::<FxHashSet<_>>()
-            .len()
+            .collect()
+    }
+
+    /// Checks that no instruction reads a cell that was not written before, except for the given
+    /// input cells and constants. Returns the first offending read otherwise.
+    pub fn check_def_use(&self, inputs: &[Cell<CT>]) -> Result<(), DefUseError<CT>>
+    where
+        CT: CellType,
+    {
+        let mut defined = FxHashSet::from_iter(inputs.iter().copied());
+        for (instruction, instr) in self.instructions().enumerate() {
+            // instructions computing a constant do not depend on the values of their inputs
+            let reads_inputs = !matches!(instr.typ.function.gate, Gate::Constant(_));
+            if let Some(cell) = instr
+                .read_cells()
+                .filter(|_| reads_inputs)
+                .find(|cell| cell.typ() != CT::CONSTANT && !defined.contains(cell))
+            {
+                return Err(DefUseError { instruction, cell });
+            }
+            defined.extend(instr.write_cells());
+        }
+        Ok(())
+    }
+
+    /// Returns for every instruction, indexed as yielded by [`Program::instructions`], the sorted
+    /// indices of the earlier instructions it depends on. An instruction depends on another one if
+    /// it reads a cell the other one writes last (read after write) or writes a cell the other one
+    /// reads or writes (write after read/write). Constant cells never introduce dependencies.
+    pub fn dependencies(&self) -> Vec<Vec<usize>>
+    where
+        CT: CellType,
+    {
+        self.dependency_edges()
+            .into_iter()
+            .map(|edges| {
+                edges
+                    .into_iter()
+                    .map(|edge| edge.instruction)
+                    .sorted_unstable()
+                    .dedup()
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Renders the data flow of the program as a graph in the DOT format. Every instruction is a
+    /// node and every edge connects the instruction writing a cell to an instruction reading the
+    /// written value, labeled with that cell. Reusing a cell for another value does not connect
+    /// the users of the different values.
+    pub fn to_dependency_dot(&self) -> String
+    where
+        CT: CellType,
+    {
+        let escape = |s: String| s.replace('\\', "\\\\").replace('"', "\\\"");
+        let mut dot = String::from("digraph program {\n");
+        for (idx, instr) in self.instructions().enumerate() {
+            let _ = writeln!(dot, "    i{idx} [label=\"{}\"];", escape(instr.to_string()));
+        }
+        for (idx, edges) in self.dependency_edges().into_iter().enumerate() {
+            let edges = edges
+                .into_iter()
+                .filter(|edge| edge.read_after_write)
+                .map(|edge| (edge.instruction, edge.cell))
+                .unique();
+            for (from, cell) in edges {
+                let _ = writeln!(
+                    dot,
+                    "    i{from} -> i{idx} [label=\"{}\"];",
+                    escape(cell.to_string())
+                );
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    fn dependency_edges(&self) -> Vec<Vec<Dependency<CT>>>
+    where
+        CT: CellType,
+    {
+        let mut last_write = FxHashMap::<Cell<CT>, usize>::default();
+        // instructions reading a cell since it was last written
+        let mut reads = FxHashMap::<Cell<CT>, Vec<usize>>::default();
+        self.instructions()
+            .enumerate()
+            .map(|(idx, instr)| {
+                let read_cells = instr
+                    .read_cells()
+                    .filter(|cell| cell.typ() != CT::CONSTANT)
+                    .collect_vec();
+                let write_cells = instr
+                    .write_cells()
+                    .filter(|cell| cell.typ() != CT::CONSTANT)
+                    .collect_vec();
+                let mut edges = Vec::new();
+                for &cell in &read_cells {
+                    edges.extend(last_write.get(&cell).map(|&instruction| Dependency {
+                        instruction,
+                        cell,
+                        read_after_write: true,
+                    }));
+                }
+                for &cell in &write_cells {
+                    let writer = last_write.get(&cell).into_iter();
+                    let readers = reads.get(&cell).into_iter().flatten();
+                    edges.extend(writer.chain(readers).map(|&instruction| Dependency {
+                        instruction,
+                        cell,
+                        read_after_write: false,
+                    }));
+                }
+                for cell in read_cells {
+                    reads.entry(cell).or_default().push(idx);
+                }
+                for cell in write_cells {
+                    last_write.insert(cell, idx);
+                    reads.remove(&cell);
+                }
+                edges
+            })
+            .collect()
+    }
+
+    /// Groups the instructions into cycles of at most `issue_width` instructions each, assuming
+    /// every instruction completes within a single cycle. Instructions of the same cycle are
+    /// independent of each other, see [`Program::dependencies`]. Every instruction is issued in
+    /// the earliest cycle after all of its dependencies that still has capacity left.
+    ///
+    /// Returns the indices of the instructions, as yielded by [`Program::instructions`], per cycle.
+    pub fn schedule(&self, issue_width: usize) -> Vec<Vec<usize>>
+    where
+        CT: CellType,
+    {
+        assert!(issue_width > 0, "issue width must be positive");
+        let mut cycles: Vec<Vec<usize>> = Vec::new();
+        let mut cycle_of = Vec::new();
+        for (idx, deps) in self.dependencies().into_iter().enumerate() {
+            let earliest = deps.iter().map(|&dep| cycle_of[dep] + 1).max().unwrap_or(0);
+            let cycle = (earliest..)
+                .find(|&cycle| {
+                    cycles
+                        .get(cycle)
+                        .is_none_or(|instrs| instrs.len() < issue_width)
+                })
+                .expect("cycles beyond the last one are empty");
+            if cycle == cycles.len() {
+                cycles.push(Vec::new());
+            }
+            cycles[cycle].push(idx);
+            cycle_of.push(cycle);
+        }
+        cycles
+    }
+
+    /// Returns a [`Display`]able version of this program that renders every cell, including the
+    /// constant cells, using `namer` instead of its [`Display`] implementation, e.g. to emit the
+    /// register aliases of a specific target.
+    pub fn display_with_namer<F>(&self, namer: F) -> NamedProgram<'_, CT, F>
+    where
+        F: Fn(Cell<CT>) -> String,
+    {
+        NamedProgram {
+            program: self,
+            namer,
+        }
+    }
+
+    /// See [`minimize_inversions`].
+    pub fn minimize_inversions(&mut self, graph: &CopyGraph<CT>)
+    where
+        CT: CellType,
+    {
+        minimize_inversions(self, graph)
+    }
+
+    /// See [`coalesce_broadcasts`].
+    pub fn coalesce_broadcasts(&mut self, arch: &Architecture<CT>)
+    where
+        CT: CellType,
+    {
+        coalesce_broadcasts(self, arch)
     }
 }
 
@@ -117,6 +419,64 @@ impl<CT: CellType> Display for Program<CT> {
     }
 }
 
+/// See [`Program::display_with_namer`].
+pub struct NamedProgram<'p, CT, F> {
+    program: &'p Program<CT>,
+    namer: F,
+}
+
+impl<CT, F> Display for NamedProgram<'_, CT, F>
+where
+    CT: CellType,
+    F: Fn(Cell<CT>) -> String,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for op in &self.program.0 {
+            op.fmt_with_namer(f, &self.namer)?;
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// An instruction another instruction has to be ordered after because both access `cell`, see
+/// [`Program::dependencies`].
+struct Dependency<CT> {
+    instruction: usize,
+    cell: Cell<CT>,
+    /// whether the dependent instruction reads the value written by `instruction`
+    read_after_write: bool,
+}
+
+/// Costs of the instructions of a [`Program`], see [`Program::cost_breakdown`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CostBreakdown {
+    pub total: Cost,
+    /// cost of the most expensive instruction
+    pub max_op: Cost,
+    /// cost of the cheapest instruction
+    pub min_op: Cost,
+    pub mean: Cost,
+}
+
+/// An instruction of a [`Program`] reading a cell that was not written before.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DefUseError<CT> {
+    /// Index of the offending instruction in [`Program::instructions`].
+    pub instruction: usize,
+    pub cell: Cell<CT>,
+}
+
+impl<CT: CellType> Display for DefUseError<CT> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "instruction {} reads undefined cell {}",
+            self.instruction, self.cell
+        )
+    }
+}
+
 #[derive(Debug)]
 pub struct ProgramSavepoint<'a, CT> {
     program: &'a mut Program<CT>,
@@ -0,0 +1,57 @@
+use eggmock::{Gate, Id, Network, Signal};
+use itertools::Itertools;
+use rustc_hash::FxHashMap;
+
+/// The fanins and fanouts of every node of a network, collected once when initializing a
+/// [`State`](super::State) so that computing a node does not query the network again.
+#[derive(Debug, Default)]
+pub struct Topology(FxHashMap<Id, NodeTopology>);
+
+#[derive(Debug, Default)]
+struct NodeTopology {
+    /// distinct nodes read by the node, in the order of its inputs
+    fanins: Vec<Id>,
+    /// nodes reading the node, in the order returned by [`Network::node_outputs`]
+    fanouts: Vec<Id>,
+    /// number of distinct nodes reading the node
+    fanout_count: usize,
+}
+
+impl Topology {
+    pub fn new<G: Gate>(network: &Network<G>) -> Self {
+        let nodes = (0..network.size())
+            .map(Id::from_usize)
+            .map(|id| {
+                let node = NodeTopology {
+                    fanins: network
+                        .node(id)
+                        .inputs()
+                        .iter()
+                        .map(Signal::node_id)
+                        .unique()
+                        .collect(),
+                    fanouts: network
+                        .node_outputs(id)
+                        .iter()
+                        .map(Signal::node_id)
+                        .collect(),
+                    fanout_count: network.node_output_ids(id).len(),
+                };
+                (id, node)
+            })
+            .collect();
+        Self(nodes)
+    }
+
+    pub fn fanins(&self, id: Id) -> &[Id] {
+        &self.0[&id].fanins
+    }
+
+    pub fn fanouts(&self, id: Id) -> &[Id] {
+        &self.0[&id].fanouts
+    }
+
+    pub fn fanout_count(&self, id: Id) -> usize {
+        self.0[&id].fanout_count
+    }
+}
@@ -1,4 +1,4 @@
-use eggmock::Id;
+use eggmock::{Gate, Id, Network};
 use rustc_hash::{FxHashMap, FxHashSet};
 
 #[derive(Debug)]
@@ -14,6 +14,17 @@ impl Uses {
         }
         self.0.get(&id).copied().unwrap_or(0)
     }
+    /// Returns the number of uses of the node that have not been consumed yet, i.e. the uses by
+    /// other nodes of the network plus one if the node is an output, minus [`Self::get`].
+    pub fn remaining<G: Gate>(
+        &self,
+        id: Id,
+        network: &Network<G>,
+        output_ids: &FxHashSet<Id>,
+    ) -> usize {
+        let all_uses = network.node_output_ids(id).len() + output_ids.contains(&id) as usize;
+        all_uses.saturating_sub(self.get(id))
+    }
 }
 
 #[derive(Debug)]
@@ -73,7 +84,10 @@ impl Drop for UsesSavepoint<'_> {
 
 #[cfg(test)]
 mod tests {
+    use eggmock::{Node, Signal};
+
     use super::*;
+    use crate::untyped_ntk::UntypedNetwork;
 
     #[test]
     pub fn test_uses() {
@@ -115,4 +129,34 @@ mod tests {
             assert_eq!(sp.increment(id0), 4);
         }
     }
+
+    #[test]
+    pub fn test_remaining() {
+        let mut ntk = Network::default();
+        let x = Signal::new(ntk.add(Node::Input(0)), false);
+        let y = Signal::new(ntk.add(Node::Input(1)), false);
+        let and = Signal::new(ntk.add(Node::Gate(UntypedNetwork::And(vec![x, y]))), false);
+        let xor = Signal::new(
+            ntk.add(Node::Gate(UntypedNetwork::Xor(vec![x, and]))),
+            false,
+        );
+        ntk.set_outputs(vec![xor, and]);
+        let output_ids = FxHashSet::from_iter([xor.node_id(), and.node_id()]);
+
+        let mut uses = Uses::new([Id::from_usize(0); 0]);
+        let mut sp = UsesSavepoint::new(&mut uses);
+        // x is read by both gates
+        assert_eq!(sp.uses().remaining(x.node_id(), &ntk, &output_ids), 2);
+        sp.increment(x.node_id());
+        assert_eq!(sp.uses().remaining(x.node_id(), &ntk, &output_ids), 1);
+        sp.increment(x.node_id());
+        assert_eq!(sp.uses().remaining(x.node_id(), &ntk, &output_ids), 0);
+        // the and gate is read by the xor gate and is an output
+        assert_eq!(sp.uses().remaining(and.node_id(), &ntk, &output_ids), 2);
+        sp.increment(and.node_id());
+        assert_eq!(sp.uses().remaining(and.node_id(), &ntk, &output_ids), 1);
+        sp.increment(and.node_id());
+        assert_eq!(sp.uses().remaining(and.node_id(), &ntk, &output_ids), 0);
+        assert_eq!(sp.uses().remaining(xor.node_id(), &ntk, &output_ids), 1);
+    }
 }
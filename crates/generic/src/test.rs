@@ -1,25 +1,137 @@
 #![cfg(test)]
 #![allow(dead_code)]
 
-use std::{borrow::Cow, rc::Rc};
+use std::{
+    borrow::Cow,
+    env,
+    ffi::CStr,
+    fs,
+    hash::{DefaultHasher, Hash, Hasher},
+    mem,
+    path::Path,
+    ptr,
+    rc::Rc,
+    str::FromStr,
+    time::{Duration, Instant},
+};
 
-use eggmock::{Id, Network, Node, Signal};
-use lime_generic_def::{Cell, Instruction, InstructionType, Operand};
-use rustc_hash::FxHashMap;
+use egg::{EGraph, Runner, rewrite};
+use eggmock::{
+    Gate, GateFunction, Id, Network, NetworkReceiver, Node, Receiver, ReceiverFFI, Signal,
+};
+use itertools::{EitherOrBoth, Itertools};
+use lime_generic_def::{
+    Architecture, Cell, CellPat, CellType, Instruction, InstructionCosts, InstructionType,
+    InstructionTypes, Operand,
+};
+use ordered_float::OrderedFloat;
+use rustc_hash::{FxHashMap, FxHashSet};
+use strum::IntoEnumIterator;
 
 use crate::{
-    ArchitectureMeta,
+    ArchitectureMeta, CompilerSettings, FfiValidator, INVALID_NETWORK_COST, RawReceiverFFI,
+    UnreadableOutput,
     compilation::{
-        CandidateSelection, CompilationMode, CompilationParameters, compile,
-        optimization::optimize_outputs,
+        CandidateSelection, CompilationMode, CompilationParameters, CompileError, StepFn,
+        candidate_selection::AllCandidates,
+        compile, compile_all_optimal, compile_both, compile_traced,
+        optimization::{
+            coalesce_broadcasts, minimize_inversions, optimize_outputs, remove_copy_round_trips,
+        },
+        profile::Profiler,
+        rng::CompilerRng,
+        step::DefaultStepFn,
+        trace::SearchNode,
+        warm_start::replay_warm_start,
+    },
+    compile_network,
+    copy::{
+        CopyGraph, PartialCopy, copy_cost, copy_cost_with_path, perform_copy,
+        placeholder::CellOrVar, repair_copy,
+    },
+    cost::{
+        AmbitCost, CachingCost, CellCountCost, Cost, CostExt, DeclaredCosts, EqualCosts,
+        OperationCost,
+    },
+    define_generic_architecture,
+    definitions::{Ambit, AmbitCellType, FELIX, FELIXCellType, IMPLY, PLiM},
+    egraph::{
+        GateBiases, LimeAnalysis, RewritingOptions, RewritingStrategy, extract_with_fallback,
+        rewriting_receiver, rewriting_rules,
+    },
+    generic_compiler_entrypoint, generic_compiler_with_program, gp_free_program_string,
+    map_result_to_ffi,
+    program::{
+        DummyProgramVersion, ProgramVersion,
+        collection::DeltaCollectionProgramVersion,
+        ssa::{SsaOperand, SsaValue},
+        state::{CellStates, DefUseError, Operation, Program, State},
     },
-    copy::CopyGraph,
-    cost::EqualCosts,
-    definitions::{Ambit, AmbitCellType, FELIX, IMPLY, PLiM},
-    program::state::{Operation, Program},
-    untyped_ntk::UntypedNetwork,
+    report_result,
+    untyped_ntk::{
+        ParseError, UntypedNetwork, UntypedNetworkLanguage, format_network, parse_network,
+    },
+    validation::{
+        EXHAUSTIVE_COMPARISON_INPUTS, NetworkStats, canonicalize, detect_cycle, evaluate,
+        explicit_constants, extract_cone, fanout_histogram, find_divergence, network_stats,
+        rebuild_network, to_blif,
+    },
+    verbosity::Verbosity,
 };
 
+/// Parameters compiling `network` greedily for `arch`, with every optional setting disabled. Tests
+/// override the settings they exercise.
+fn compilation_parameters<CT: CellType, G: Gate, C: OperationCost<CT>>(
+    arch: Rc<ArchitectureMeta<CT>>,
+    cost: C,
+    input_cells: Vec<Cell<CT>>,
+    network: Network<G>,
+) -> CompilationParameters<CT, G, C> {
+    CompilationParameters {
+        arch,
+        candidate_selection: CandidateSelection::All,
+        cost,
+        disjunct_input_output: false,
+        max_depth: None,
+        warm_start: None,
+        enabled_instructions: None,
+        cost_lower_bound: None,
+        spill_warning_fraction: None,
+        prematerialize_constants: false,
+        rng: CompilerRng::default(),
+        profiler: None,
+        cell_reuse_bias: 0.0,
+        signal_pins: FxHashMap::default(),
+        branch_and_bound: false,
+        input_cells,
+        mode: CompilationMode::Greedy,
+        network,
+    }
+}
+
+/// Options rewriting a network for the given architecture with the given strategy, extracting
+/// with the greedy compilation and equal costs.
+fn rewriting_options<CT: CellType>(
+    arch: Rc<ArchitectureMeta<CT>>,
+    strategy: RewritingStrategy,
+) -> RewritingOptions<CT, EqualCosts> {
+    RewritingOptions {
+        arch,
+        strategy,
+        output_strategies: FxHashMap::default(),
+        size_factor: 2,
+        compile_threshold: 0,
+        gate_biases: GateBiases::default(),
+        candidate_selection: CandidateSelection::All,
+        compilation_mode: CompilationMode::Greedy,
+        cost: EqualCosts,
+        disjunct_input_output: false,
+        extra_rules: Vec::new(),
+        verbosity: Verbosity::SILENT,
+        dump_path: None,
+    }
+}
+
 #[test]
 fn print_copy_graphs() {
     println!("Ambit: {:?}", CopyGraph::build(&Ambit::new(), &EqualCosts));
@@ -48,6 +160,7 @@ fn test_opt() {
                 typ: types["TRA"].clone(),
             },
             Id::from_usize(0),
+            None,
         ),
         Operation::Copy {
             computes_from_inverted: false,
@@ -95,6 +208,330 @@ fn test_opt() {
     println!("{program}");
 }
 
+#[test]
+fn test_minimize_inversions() {
+    let felix = FELIX::new();
+    let graph = CopyGraph::build(&felix, &EqualCosts);
+    let d = |idx| Cell::new(FELIXCellType::D, idx);
+    let copy = |from, to, inverted| {
+        let (_, _, edge) = graph
+            .all_optimal_edges_matching(CellPat::Cell(from), CellPat::Cell(to), inverted)
+            .next()
+            .expect("FELIX should be able to copy between D cells");
+        Operation::Copy {
+            from,
+            to,
+            inverted,
+            instructions: edge.instantiate(from, to).collect(),
+            spill: false,
+            computes_from_inverted: edge.computes_from_inverted,
+        }
+    };
+    let mut program = Program(vec![
+        copy(d(0), d(1), true),
+        copy(d(1), d(2), true),
+        // overrides d(1), hence the intermediate inverted value is never needed
+        copy(d(0), d(1), false),
+    ]);
+    println!("{program}");
+    minimize_inversions(&mut program, &graph);
+    println!("{program}");
+    assert_eq!(program.0.len(), 2);
+    assert!(matches!(
+        program.0[0],
+        Operation::Copy { from, to, inverted: false, .. } if from == d(0) && to == d(2)
+    ));
+}
+
+#[test]
+fn test_remove_copy_round_trips() {
+    let felix = FELIX::new();
+    let graph = CopyGraph::build(&felix, &EqualCosts);
+    let d = |idx| Cell::new(FELIXCellType::D, idx);
+    let copy = |from, to, inverted| {
+        let (_, _, edge) = graph
+            .all_optimal_edges_matching(CellPat::Cell(from), CellPat::Cell(to), inverted)
+            .next()
+            .expect("FELIX should be able to copy between D cells");
+        Operation::Copy {
+            from,
+            to,
+            inverted,
+            instructions: edge.instantiate(from, to).collect(),
+            spill: false,
+            computes_from_inverted: edge.computes_from_inverted,
+        }
+    };
+    let endpoints = |op: &Operation<FELIXCellType>| match *op {
+        Operation::Copy { from, to, .. } => Some((from, to)),
+        _ => None,
+    };
+
+    // d(1) is overridden afterwards, hence both copies are removed
+    let mut program = Program(vec![
+        copy(d(0), d(1), false),
+        copy(d(1), d(0), false),
+        copy(d(0), d(2), false),
+        copy(d(2), d(1), false),
+    ]);
+    remove_copy_round_trips(&mut program);
+    println!("{program}");
+    assert_eq!(program.0.len(), 2);
+    assert_eq!(endpoints(&program.0[0]), Some((d(0), d(2))));
+    assert_eq!(endpoints(&program.0[1]), Some((d(2), d(1))));
+
+    // d(1) may hold an output, hence only the copy back is removed
+    let mut program = Program(vec![copy(d(0), d(1), true), copy(d(1), d(0), true)]);
+    remove_copy_round_trips(&mut program);
+    assert_eq!(program.0.len(), 1);
+    assert_eq!(endpoints(&program.0[0]), Some((d(0), d(1))));
+
+    // a single inversion does not restore the value
+    let mut program = Program(vec![copy(d(0), d(1), true), copy(d(1), d(0), false)]);
+    remove_copy_round_trips(&mut program);
+    assert_eq!(program.0.len(), 2);
+}
+
+#[test]
+fn test_coalesce_broadcasts() {
+    let ambit = Ambit::new();
+    let types: FxHashMap<Cow<'static, str>, &InstructionType<AmbitCellType>> = ambit
+        .instructions()
+        .iter()
+        .map(|instr| (instr.name.clone(), instr))
+        .collect();
+    let d = |idx| Cell::new(AmbitCellType::D, idx);
+    let t = |idx| Cell::new(AmbitCellType::T, idx);
+    let copy = |from, to| Operation::Copy {
+        from,
+        to,
+        inverted: false,
+        instructions: vec![Instruction {
+            inputs: vec![from],
+            outputs: vec![Operand {
+                cell: to,
+                inverted: false,
+            }],
+            typ: types["RC"].clone(),
+        }],
+        spill: false,
+        computes_from_inverted: false,
+    };
+    let mut program = Program(vec![
+        copy(d(0), t(0)),
+        copy(d(0), t(1)),
+        copy(d(0), t(2)),
+        // different source, hence not part of the broadcast
+        copy(d(1), t(3)),
+    ]);
+    coalesce_broadcasts(&mut program, &ambit);
+    println!("{program}");
+    assert_eq!(program.0.len(), 2);
+    let [instruction] = program.0[0].instructions() else {
+        panic!("broadcast should be a single instruction");
+    };
+    assert_eq!(instruction.inputs, [d(0)]);
+    assert_eq!(
+        instruction.outputs.iter().map(|op| op.cell).collect_vec(),
+        [t(0), t(1), t(2)]
+    );
+    assert!(instruction.validate().is_ok());
+    assert!(matches!(program.0[1], Operation::Copy { from, .. } if from == d(1)));
+}
+
+#[test]
+fn test_display_with_namer() {
+    let ambit = Ambit::new();
+    let rc = ambit
+        .instructions()
+        .iter()
+        .find(|typ| typ.name == "RC")
+        .expect("ambit should have a row copy");
+    let d = Cell::new(AmbitCellType::D, 0);
+    let t = Cell::new(AmbitCellType::T, 0);
+    let copy = |from, to| Instruction {
+        inputs: vec![from],
+        outputs: vec![Operand {
+            cell: to,
+            inverted: false,
+        }],
+        typ: rc.clone(),
+    };
+    let program = Program(vec![
+        Operation::Copy {
+            from: d,
+            to: t,
+            inverted: false,
+            instructions: vec![copy(d, t)],
+            spill: false,
+            computes_from_inverted: false,
+        },
+        Operation::Other {
+            instructions: vec![copy(AmbitCellType::constant(true), t)],
+            comment: None,
+        },
+    ]);
+    let named = program
+        .display_with_namer(|cell| match cell.constant_value() {
+            Some(value) => format!("#{}", value as u8),
+            None if cell == t => "%r0".to_string(),
+            None => cell.to_string(),
+        })
+        .to_string();
+    println!("{named}");
+    assert!(named.contains("// copy D[0] -> %r0"));
+    assert!(named.contains("RC(D[0]) -> (%r0)"));
+    assert!(named.contains("RC(#1) -> (%r0)"));
+    assert!(!named.contains("T[0]"));
+    // naming cells by their display reproduces the plain display
+    assert_eq!(
+        program
+            .display_with_namer(|cell| cell.to_string())
+            .to_string(),
+        program.to_string()
+    );
+}
+
+#[test]
+fn test_schedule() {
+    let ambit = Ambit::new();
+    let types: FxHashMap<Cow<'static, str>, &InstructionType<AmbitCellType>> = ambit
+        .instructions()
+        .iter()
+        .map(|instr| (instr.name.clone(), instr))
+        .collect();
+    let d = |idx| Cell::new(AmbitCellType::D, idx);
+    let t = |idx| Cell::new(AmbitCellType::T, idx);
+    let copy = |from, to| Instruction {
+        inputs: vec![from],
+        outputs: vec![Operand {
+            cell: to,
+            inverted: false,
+        }],
+        typ: types["RC"].clone(),
+    };
+    let program = |instructions| {
+        Program(vec![Operation::Other {
+            instructions,
+            comment: None,
+        }])
+    };
+
+    let independent = program(vec![
+        copy(d(0), t(0)),
+        copy(d(1), t(1)),
+        copy(d(2), t(2)),
+        copy(d(3), t(3)),
+    ]);
+    assert!(independent.dependencies().iter().all(Vec::is_empty));
+    assert_eq!(independent.schedule(2), [vec![0, 1], vec![2, 3]]);
+    assert_eq!(independent.schedule(4), [vec![0, 1, 2, 3]]);
+
+    // read after write, write after write and write after read
+    let chain = program(vec![
+        copy(d(0), t(0)),
+        copy(t(0), d(1)),
+        copy(d(2), d(1)),
+        copy(d(3), d(2)),
+    ]);
+    assert_eq!(chain.dependencies(), [vec![], vec![0], vec![1], vec![2]]);
+    assert_eq!(chain.schedule(4), [vec![0], vec![1], vec![2], vec![3]]);
+}
+
+#[test]
+fn test_dependency_dot() {
+    let ambit = Ambit::new();
+    let types: FxHashMap<Cow<'static, str>, &InstructionType<AmbitCellType>> = ambit
+        .instructions()
+        .iter()
+        .map(|instr| (instr.name.clone(), instr))
+        .collect();
+    let d = |idx| Cell::new(AmbitCellType::D, idx);
+    let t = |idx| Cell::new(AmbitCellType::T, idx);
+    let copy = |from, to| Instruction {
+        inputs: vec![from],
+        outputs: vec![Operand {
+            cell: to,
+            inverted: false,
+        }],
+        typ: types["RC"].clone(),
+    };
+    // T[0] holds two independent values
+    let program = Program(vec![Operation::Other {
+        instructions: vec![
+            copy(d(0), t(0)),
+            copy(t(0), d(1)),
+            copy(d(2), t(0)),
+            copy(t(0), d(3)),
+        ],
+        comment: None,
+    }]);
+    let dot = program.to_dependency_dot();
+    println!("{dot}");
+    assert!(dot.starts_with("digraph program {"));
+    assert_eq!(
+        dot.lines()
+            .filter(|line| line.contains("[label=\"RC("))
+            .count(),
+        4
+    );
+    let edges = dot
+        .lines()
+        .filter(|line| line.contains(" -> i"))
+        .map(str::trim)
+        .collect_vec();
+    assert_eq!(
+        edges,
+        ["i0 -> i1 [label=\"T[0]\"];", "i2 -> i3 [label=\"T[0]\"];"]
+    );
+}
+
+#[test]
+fn test_check_def_use() {
+    let ambit = Ambit::new();
+    let types: FxHashMap<Cow<'static, str>, &InstructionType<AmbitCellType>> = ambit
+        .instructions()
+        .iter()
+        .map(|instr| (instr.name.clone(), instr))
+        .collect();
+    let d0 = Cell::new(AmbitCellType::D, 0);
+    let t = |idx| Cell::new(AmbitCellType::T, idx);
+    let copy = |to| Operation::Copy {
+        from: d0,
+        to,
+        inverted: false,
+        instructions: vec![Instruction {
+            inputs: vec![d0],
+            outputs: vec![Operand {
+                cell: to,
+                inverted: false,
+            }],
+            typ: types["RC"].clone(),
+        }],
+        spill: false,
+        computes_from_inverted: false,
+    };
+    let tra = Operation::Candidate(
+        Instruction {
+            inputs: vec![t(0), t(1), t(2)],
+            outputs: vec![],
+            typ: types["TRA"].clone(),
+        },
+        Id::from_usize(0),
+        None,
+    );
+    let program = Program(vec![copy(t(0)), copy(t(2)), tra.clone()]);
+    assert_eq!(
+        program.check_def_use(&[d0]),
+        Err(DefUseError {
+            instruction: 2,
+            cell: t(1),
+        })
+    );
+    let program = Program(vec![copy(t(0)), copy(t(1)), copy(t(2)), tra]);
+    assert_eq!(program.check_def_use(&[d0]), Ok(()));
+}
+
 fn mux1() -> Network<UntypedNetwork> {
     let mut ntk = Network::default();
     let i0 = Signal::new(ntk.add(Node::Input(0)), false);
@@ -145,6 +582,265 @@ fn mux2() -> Network<UntypedNetwork> {
     ntk
 }
 
+/// Network in which `n1` fans out to `n2` and `n3`, which reconverge in `n4`.
+fn reconvergent() -> Network<UntypedNetwork> {
+    let mut ntk = Network::default();
+    let i0 = Signal::new(ntk.add(Node::Input(0)), false);
+    let i1 = Signal::new(ntk.add(Node::Input(1)), false);
+    let i2 = Signal::new(ntk.add(Node::Input(2)), false);
+    let i3 = Signal::new(ntk.add(Node::Input(3)), false);
+    let maj = |ntk: &mut Network<UntypedNetwork>, inputs| {
+        Signal::new(ntk.add(Node::Gate(UntypedNetwork::Maj(inputs))), false)
+    };
+    let n1 = maj(&mut ntk, vec![i0, i1, i2]);
+    let n2 = maj(&mut ntk, vec![n1, i2, i3]);
+    let n3 = maj(&mut ntk, vec![n1, !i0, i3]);
+    let n4 = maj(&mut ntk, vec![n2, n3, i1]);
+    ntk.set_outputs(vec![n4]);
+    ntk
+}
+
+/// Network computing the conjunctions of all pairs of four inputs, which are then combined by a
+/// chain of conjunctions. The pairs are added before the chain, hence computing the nodes in the
+/// order of their ids keeps all pairs alive at once, while each link of the chain frees two cells.
+fn pairwise_ands() -> Network<UntypedNetwork> {
+    let mut ntk = Network::default();
+    let inputs = (0..4)
+        .map(|i| Signal::new(ntk.add(Node::Input(i)), false))
+        .collect_vec();
+    let and = |ntk: &mut Network<UntypedNetwork>, a, b| {
+        Signal::new(ntk.add(Node::Gate(UntypedNetwork::And(vec![a, b]))), false)
+    };
+    let pairs = inputs
+        .iter()
+        .tuple_combinations()
+        .map(|(a, b)| and(&mut ntk, *a, *b))
+        .collect_vec();
+    let output = pairs[1..]
+        .iter()
+        .fold(pairs[0], |chain, pair| and(&mut ntk, chain, *pair));
+    ntk.set_outputs(vec![output]);
+    ntk
+}
+
+#[test]
+fn test_network_stats() {
+    assert_eq!(
+        network_stats(&mux2()),
+        NetworkStats {
+            inputs: 3,
+            outputs: 1,
+            gates: 3,
+            depth: 3,
+            and_count: 0,
+            maj_count: 3,
+            xor_count: 0,
+            // n1 is read by n2 and n3
+            max_fanout: 2,
+        }
+    );
+}
+
+#[test]
+fn test_fanout_histogram() {
+    // the first input is read by every gate
+    let mut ntk = Network::default();
+    let inputs = (0..4)
+        .map(|i| Signal::new(ntk.add(Node::Input(i)), false))
+        .collect_vec();
+    let outputs = inputs[1..]
+        .iter()
+        .map(|&input| {
+            let gate = UntypedNetwork::And(vec![inputs[0], input]);
+            Signal::new(ntk.add(Node::Gate(gate)), false)
+        })
+        .collect();
+    ntk.set_outputs(outputs);
+    assert_eq!(
+        fanout_histogram(&ntk),
+        FxHashMap::from_iter([(3, 1), (1, 3), (0, ntk.size() - 4)])
+    );
+}
+
+#[test]
+fn test_extract_cone() {
+    let inputs = |n: usize| {
+        (0..1 << n).map(move |bits| Vec::from_iter((0..n).map(|i| bits & (1 << i) != 0)))
+    };
+
+    // mux2 only has a single output, so its cone is the whole network
+    let ntk = mux2();
+    let cone = extract_cone(&ntk, 0);
+    assert_eq!(network_stats(&cone), network_stats(&ntk));
+    for inputs in inputs(3) {
+        assert_eq!(evaluate(&cone, &inputs), evaluate(&ntk, &inputs));
+    }
+
+    let mut ntk = Network::default();
+    let x = Signal::new(ntk.add(Node::Input(0)), false);
+    let y = Signal::new(ntk.add(Node::Input(1)), false);
+    let z = Signal::new(ntk.add(Node::Input(2)), false);
+    let and = Signal::new(ntk.add(Node::Gate(UntypedNetwork::And(vec![x, y]))), false);
+    let xor = Signal::new(
+        ntk.add(Node::Gate(UntypedNetwork::Xor(vec![and, z]))),
+        false,
+    );
+    ntk.set_outputs(vec![!and, xor]);
+
+    // the xor is only required by the second output
+    let cone = extract_cone(&ntk, 0);
+    let stats = network_stats(&cone);
+    assert_eq!((stats.inputs, stats.outputs), (3, 1));
+    assert_eq!((stats.gates, stats.and_count, stats.xor_count), (1, 1, 0));
+    let cone = extract_cone(&ntk, 1);
+    let stats = network_stats(&cone);
+    assert_eq!((stats.gates, stats.and_count, stats.xor_count), (2, 1, 1));
+    for inputs in inputs(3) {
+        let outputs = evaluate(&ntk, &inputs);
+        assert_eq!(evaluate(&extract_cone(&ntk, 0), &inputs), [outputs[0]]);
+        assert_eq!(evaluate(&extract_cone(&ntk, 1), &inputs), [outputs[1]]);
+    }
+}
+
+#[test]
+fn test_canonicalize() {
+    let egraph_nodes = |ntk: Network<UntypedNetwork>| {
+        let (egraph, _) = ntk.send(EGraph::<UntypedNetworkLanguage, LimeAnalysis>::default());
+        egraph.total_number_of_nodes()
+    };
+
+    // mux2 with the inputs of every gate permuted
+    let permuted =
+        parse_network("(maj (maj (! i1) i2 (maj f i1 i0)) (! f) (maj i1 i0 f))").unwrap();
+    let canonical = canonicalize(&mux2());
+    assert_eq!(
+        format_network(&canonical),
+        format_network(&canonicalize(&permuted))
+    );
+    assert_eq!(
+        egraph_nodes(canonicalize(&mux2())),
+        egraph_nodes(canonicalize(&permuted))
+    );
+    for bits in 0..1 << 3 {
+        let inputs = Vec::from_iter((0..3).map(|i| bits & (1 << i) != 0));
+        assert_eq!(evaluate(&canonical, &inputs), evaluate(&mux2(), &inputs));
+    }
+
+    // and(x, y) and and(y, x) are merged
+    let ntk = parse_network("(xor (and i0 i1) (and i1 i0))").unwrap();
+    let canonical = canonicalize(&ntk);
+    assert_eq!(network_stats(&ntk).gates, 3);
+    assert_eq!(network_stats(&canonical).gates, 2);
+    for bits in 0..1 << 2 {
+        let inputs = Vec::from_iter((0..2).map(|i| bits & (1 << i) != 0));
+        assert_eq!(evaluate(&canonical, &inputs), evaluate(&ntk, &inputs));
+    }
+    assert!(egraph_nodes(canonical) < egraph_nodes(ntk));
+}
+
+#[test]
+fn test_detect_cycle() {
+    assert_eq!(detect_cycle(&mux2()), None);
+
+    // n1 = maj(i0, i1, n2), n2 = and(n1, i1), where n2 is referenced before it is added
+    let mut ntk = Network::default();
+    let i0 = Signal::new(ntk.add(Node::Input(0)), false);
+    let i1 = Signal::new(ntk.add(Node::Input(1)), false);
+    let n2_id = Id::from_usize(3);
+    let n1 = Signal::new(
+        ntk.add(Node::Gate(UntypedNetwork::Maj(vec![
+            i0,
+            i1,
+            Signal::new(n2_id, false),
+        ]))),
+        false,
+    );
+    let n2 = Signal::new(
+        ntk.add(Node::Gate(UntypedNetwork::And(vec![n1, !i1]))),
+        false,
+    );
+    assert_eq!(n2.node_id(), n2_id);
+    ntk.set_outputs(vec![n2]);
+
+    let cycle = detect_cycle(&ntk).expect("cycle should be detected");
+    assert_eq!(cycle.len(), 2);
+    assert!(cycle.contains(&n1.node_id()) && cycle.contains(&n2_id));
+
+    let ambit = Ambit::new();
+    let result = compile(compilation_parameters(
+        Rc::new(ArchitectureMeta {
+            copy_graph: CopyGraph::build(&ambit, &EqualCosts),
+            arch: ambit,
+        }),
+        EqualCosts,
+        vec![
+            Cell::new(AmbitCellType::D, 0),
+            Cell::new(AmbitCellType::D, 1),
+        ],
+        ntk,
+    ));
+    assert!(matches!(result, Err(CompileError::CyclicNetwork(_))));
+}
+
+#[test]
+fn test_to_blif() {
+    let blif = to_blif(&mux2(), "mux2");
+    let lines = blif.lines().collect_vec();
+    assert_eq!(
+        lines[..3],
+        [".model mux2", ".inputs i0 i1 i2", ".outputs o0"]
+    );
+    assert_eq!(lines.last(), Some(&".end"));
+    // one block per gate, the constant and the output
+    let blocks = lines
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| line.starts_with(".names"))
+        .collect_vec();
+    assert_eq!(blocks.len(), network_stats(&mux2()).gates + 2);
+
+    // every signal is defined before it is read, every row fits the number of inputs
+    let mut defined = FxHashSet::from_iter(["i0", "i1", "i2"]);
+    for (idx, line) in blocks {
+        let signals = line.split_whitespace().skip(1).collect_vec();
+        let (output, inputs) = signals.split_last().unwrap();
+        assert!(inputs.iter().all(|input| defined.contains(input)));
+        assert!(defined.insert(*output));
+        for row in lines[idx + 1..]
+            .iter()
+            .take_while(|line| !line.starts_with('.'))
+        {
+            let (pattern, value) = row.split_once(' ').unwrap();
+            assert_eq!(pattern.len(), inputs.len());
+            assert!(pattern.chars().all(|c| "01-".contains(c)));
+            assert_eq!(value, "1");
+        }
+    }
+    assert!(defined.contains("o0"));
+}
+
+#[test]
+fn test_parse_network() {
+    let parsed = parse_network("(maj (! f) (maj i0 i1 f) (maj (! i1) f i2))").unwrap();
+    let mux = mux1();
+    for assignment in 0..8u8 {
+        let inputs = (0..3)
+            .map(|i| assignment & (1 << i) != 0)
+            .collect::<Vec<_>>();
+        assert_eq!(evaluate(&parsed, &inputs), evaluate(&mux, &inputs));
+    }
+    assert_eq!(parse_network("i0 (! i1)").unwrap().outputs().len(), 2);
+    assert!(matches!(parse_network("(maj i0 i1"), Err(ParseError(_))));
+    assert!(matches!(parse_network("(nand i0 i1)"), Err(ParseError(_))));
+    assert!(matches!(parse_network("(! i0 i1)"), Err(ParseError(_))));
+    assert!(matches!(parse_network("(maj i0 i1)"), Err(ParseError(_))));
+    assert!(matches!(
+        parse_network("(maj i0 i1 i2 i3)"),
+        Err(ParseError(_))
+    ));
+    assert!(parse_network("(maj i0 i1 i2 i3 i4)").is_ok());
+}
+
 #[test]
 fn test_compile() {
     let ntk = mux2();
@@ -155,17 +851,2714 @@ fn test_compile() {
         arch,
     };
     let program = compile(CompilationParameters {
-        arch: Rc::new(arch),
-        candidate_selection: CandidateSelection::All,
-        cost: EqualCosts,
-        disjunct_input_output: false,
-        input_cells: vec![
-            Cell::new(AmbitCellType::D, 0),
-            Cell::new(AmbitCellType::D, 1),
-            Cell::new(AmbitCellType::D, 2),
-        ],
         mode: CompilationMode::Exhaustive,
-        network: ntk,
+        ..compilation_parameters(
+            Rc::new(arch),
+            EqualCosts,
+            vec![
+                Cell::new(AmbitCellType::D, 0),
+                Cell::new(AmbitCellType::D, 1),
+                Cell::new(AmbitCellType::D, 2),
+            ],
+            ntk,
+        )
     });
-    println!("{}", program.unwrap().program)
+    let program = program.unwrap().program;
+    println!("{program}");
+
+    // every gate of mux2 is computed exactly once
+    assert_eq!(
+        program.compute_instruction_count(),
+        network_stats(&mux2()).gates
+    );
+    assert!(program.compute_instruction_count() <= program.instructions().count());
+}
+
+#[test]
+fn test_compile_both() {
+    let arch = Ambit::new();
+    let arch = ArchitectureMeta {
+        copy_graph: CopyGraph::build(&arch, &EqualCosts),
+        arch,
+    };
+    let input_cells = vec![
+        Cell::new(AmbitCellType::D, 0),
+        Cell::new(AmbitCellType::D, 1),
+        Cell::new(AmbitCellType::D, 2),
+    ];
+    let (greedy, exhaustive) = compile_both(compilation_parameters(
+        Rc::new(arch),
+        EqualCosts,
+        input_cells.clone(),
+        mux2(),
+    ));
+    let greedy = greedy.expect("greedy compilation should succeed");
+    let exhaustive = exhaustive.expect("exhaustive compilation should succeed");
+    assert!(
+        EqualCosts.program_cost(&exhaustive.program) <= EqualCosts.program_cost(&greedy.program)
+    );
+    for result in [greedy, exhaustive] {
+        let rebuilt = rebuild_network(&result.program, &input_cells, &result.outputs)
+            .expect("network should be rebuildable");
+        for bits in 0..8 {
+            let inputs = Vec::from_iter((0..3).map(|i| bits & (1 << i) != 0));
+            assert_eq!(evaluate(&rebuilt, &inputs), evaluate(&mux2(), &inputs));
+        }
+    }
+}
+
+#[test]
+fn test_profile() {
+    let params = |profiler| {
+        let arch = Ambit::new();
+        let arch = ArchitectureMeta {
+            copy_graph: CopyGraph::build(&arch, &EqualCosts),
+            arch,
+        };
+        CompilationParameters {
+            profiler,
+            mode: CompilationMode::Exhaustive,
+            ..compilation_parameters(
+                Rc::new(arch),
+                EqualCosts,
+                vec![
+                    Cell::new(AmbitCellType::D, 0),
+                    Cell::new(AmbitCellType::D, 1),
+                    Cell::new(AmbitCellType::D, 2),
+                ],
+                mux2(),
+            )
+        }
+    };
+    let result = compile(params(None)).expect("compilation should succeed");
+    assert_eq!(result.profile, None);
+
+    let params = params(Some(Profiler::default()));
+    let t_compile = Instant::now();
+    let result = compile(params).expect("compilation should succeed");
+    let t_compile = t_compile.elapsed();
+    let profile = result.profile.expect("profiling was requested");
+    println!("{profile}");
+    assert!(profile.position_signals > Duration::ZERO);
+    assert!(profile.place_signals > Duration::ZERO);
+    assert!(profile.finalize > Duration::ZERO);
+    // only detecting cycles and wrapping the parameters are not covered by the profile
+    assert!(profile.total() <= t_compile);
+    assert!(t_compile - profile.total() < t_compile / 2);
+}
+
+#[test]
+fn test_to_ssa() {
+    let input_cells = vec![
+        Cell::new(AmbitCellType::D, 0),
+        Cell::new(AmbitCellType::D, 1),
+        Cell::new(AmbitCellType::D, 2),
+    ];
+    let arch = Ambit::new();
+    let arch = ArchitectureMeta {
+        copy_graph: CopyGraph::build(&arch, &EqualCosts),
+        arch,
+    };
+    let result = compile(CompilationParameters {
+        mode: CompilationMode::Exhaustive,
+        ..compilation_parameters(Rc::new(arch), EqualCosts, input_cells.clone(), mux2())
+    })
+    .expect("compilation should succeed");
+    let (statements, cells) = result.program.to_ssa_with_cells();
+    assert_eq!(statements, result.program.to_ssa());
+    assert_eq!(statements.len(), network_stats(&mux2()).gates);
+
+    for bits in 0..1 << input_cells.len() {
+        let inputs = Vec::from_iter((0..input_cells.len()).map(|i| bits & (1 << i) != 0));
+        let mut temps = Vec::new();
+        let value = |temps: &[bool], operand: SsaOperand<AmbitCellType>| {
+            let value = match operand.value {
+                SsaValue::Constant(value) => value,
+                SsaValue::Input(cell) => {
+                    inputs[input_cells.iter().position(|c| *c == cell).unwrap()]
+                }
+                SsaValue::Temp(temp) => temps[temp],
+            };
+            value ^ operand.inverted
+        };
+        for statement in &statements {
+            assert_eq!(statement.temp, temps.len());
+            let mut evaluation = statement.function.evaluate(statement.inputs.len());
+            for (idx, input) in statement.inputs.iter().enumerate() {
+                evaluation.add(idx, value(&temps, *input));
+            }
+            temps.push(evaluation.evaluate().unwrap());
+        }
+        let outputs = Vec::from_iter(result.outputs.iter().map(|output| {
+            let operand = cells.get(&output.cell).copied().unwrap_or(SsaOperand {
+                value: SsaValue::Input(output.cell),
+                inverted: false,
+            });
+            value(&temps, operand) ^ output.inverted
+        }));
+        assert_eq!(outputs, evaluate(&mux2(), &inputs));
+    }
+}
+
+define_generic_architecture! {
+    Narrow {
+        cells ([D], [S; 1]),
+        operands (
+            ANY = [(D)],
+            SCRATCH = [(S)],
+            PAIR = [(D, D)],
+            MIXED = [(D, S)],
+        ),
+        instructions (
+            // computes in one instruction, but writes two new cells
+            WIDE = (and(PAIR) -> (PAIR)),
+            // requires a copy into the scratch cell, but overrides its first input
+            NARROW = ([0] := and(MIXED)),
+            LOAD = (and(ANY) -> (SCRATCH)),
+        ),
+    }
+}
+
+#[test]
+fn test_cell_count_cost() {
+    fn compile_with<C: OperationCost<NarrowCellType>>(
+        cost: C,
+        mode: CompilationMode,
+    ) -> Program<NarrowCellType> {
+        let arch = Narrow::new();
+        let arch = ArchitectureMeta {
+            copy_graph: CopyGraph::build(&arch, &cost),
+            arch,
+        };
+        let mut ntk = Network::default();
+        let x = Signal::new(ntk.add(Node::Input(0)), false);
+        let y = Signal::new(ntk.add(Node::Input(1)), false);
+        let and = Signal::new(ntk.add(Node::Gate(UntypedNetwork::And(vec![x, y]))), false);
+        ntk.set_outputs(vec![and]);
+        compile(CompilationParameters {
+            mode,
+            ..compilation_parameters(
+                Rc::new(arch),
+                cost,
+                vec![
+                    Cell::new(NarrowCellType::D, 0),
+                    Cell::new(NarrowCellType::D, 1),
+                ],
+                ntk,
+            )
+        })
+        .expect("compilation should succeed")
+        .program
+    }
+    for mode in [CompilationMode::Greedy, CompilationMode::Exhaustive] {
+        let fastest = compile_with(EqualCosts, mode);
+        let smallest = compile_with(CellCountCost, mode);
+        // WIDE uses the inputs and two new cells, LOAD and NARROW a single scratch cell only
+        assert_eq!(fastest.num_cells(), 4, "{mode}");
+        assert_eq!(smallest.num_cells(), 3, "{mode}");
+        assert!(smallest.instructions().count() > fastest.instructions().count());
+        assert_eq!(
+            CellCountCost.program_cost(&smallest),
+            OrderedFloat(
+                smallest.num_cells() as f64 * CellCountCost::CELL_COST
+                    + smallest.instructions().count() as f64
+            )
+        );
+    }
+}
+
+#[test]
+fn test_cell_count_cost_with_context() {
+    let d = |idx| Cell::new(AmbitCellType::D, idx);
+    let rc = Ambit::new()
+        .instructions()
+        .iter()
+        .find(|typ| typ.name == "RC")
+        .unwrap()
+        .clone();
+    let copy = |from, to| {
+        Program(vec![Operation::Other {
+            instructions: vec![Instruction {
+                typ: rc.clone(),
+                inputs: vec![from],
+                outputs: vec![Operand {
+                    cell: to,
+                    inverted: false,
+                }],
+            }],
+            comment: None,
+        }])
+    };
+    let base = copy(d(0), d(1));
+    // reusing the cells of the base program is only charged for the instruction
+    assert_eq!(
+        CellCountCost.program_cost_with_context(&base, &copy(d(1), d(0))),
+        OrderedFloat(1.0)
+    );
+    assert_eq!(
+        CellCountCost.program_cost_with_context(&base, &copy(d(1), d(2))),
+        OrderedFloat(CellCountCost::CELL_COST + 1.0)
+    );
+}
+
+#[test]
+fn test_setting_names() {
+    fn assert_round_trip<E: IntoEnumIterator + ToString + FromStr + PartialEq>() {
+        for variant in E::iter() {
+            let name = variant.to_string();
+            assert!(
+                name.parse::<E>().is_ok_and(|parsed| parsed == variant),
+                "{name} does not round-trip"
+            );
+        }
+    }
+    assert_round_trip::<CompilationMode>();
+    assert_round_trip::<CandidateSelection>();
+    assert_round_trip::<RewritingStrategy>();
+    // names used by the benchmarks
+    assert_eq!(
+        CandidateSelection::MIGBasedCompiler.to_string(),
+        "plim_compiler"
+    );
+    assert_eq!(RewritingStrategy::GreedyEstimate.to_string(), "greedy");
+    assert_eq!(
+        RewritingStrategy::CompilingMemusage.to_string(),
+        "compiling_memusage"
+    );
+}
+
+#[test]
+fn test_signal_placement() {
+    let arch = Ambit::new();
+    let arch = Rc::new(ArchitectureMeta {
+        copy_graph: CopyGraph::build(&arch, &EqualCosts),
+        arch,
+    });
+    let input_cells = Vec::from_iter((0..3).map(|i| Cell::new(AmbitCellType::D, i)));
+    for mode in [CompilationMode::Greedy, CompilationMode::Exhaustive] {
+        let result = compile(CompilationParameters {
+            mode,
+            ..compilation_parameters(arch.clone(), EqualCosts, input_cells.clone(), mux2())
+        })
+        .expect("compilation should succeed");
+        for (signal, output) in mux2().outputs().iter().zip(&result.outputs) {
+            assert!(!output.inverted);
+            assert!(result.signal_placement[signal].contains(&output.cell));
+        }
+        if mode == CompilationMode::Exhaustive {
+            // the output is written by TRA directly instead of being copied out of the T cells
+            assert!(result.program.0.iter().any(
+                |op| matches!(op, Operation::Candidate(instr, ..) if !instr.outputs.is_empty())
+            ));
+        }
+
+        // every cell holds its signal at the end of the finalized program
+        let (statements, cells) = result.program.to_ssa_with_cells();
+        for bits in 0..1 << input_cells.len() {
+            let inputs = Vec::from_iter((0..input_cells.len()).map(|i| bits & (1 << i) != 0));
+            let value = |temps: &[bool], operand: SsaOperand<AmbitCellType>| {
+                let value = match operand.value {
+                    SsaValue::Constant(value) => value,
+                    SsaValue::Input(cell) => {
+                        inputs[input_cells.iter().position(|c| *c == cell).unwrap()]
+                    }
+                    SsaValue::Temp(temp) => temps[temp],
+                };
+                value ^ operand.inverted
+            };
+            let mut temps = Vec::new();
+            for statement in &statements {
+                let mut evaluation = statement.function.evaluate(statement.inputs.len());
+                for (idx, input) in statement.inputs.iter().enumerate() {
+                    evaluation.add(idx, value(&temps, *input));
+                }
+                temps.push(evaluation.evaluate().unwrap());
+            }
+            for (signal, placed) in &result.signal_placement {
+                let mut ntk = mux2();
+                ntk.set_outputs(vec![*signal]);
+                let expected = evaluate(&ntk, &inputs)[0];
+                for cell in placed {
+                    let operand = cells.get(cell).copied().unwrap_or(SsaOperand {
+                        value: SsaValue::Input(*cell),
+                        inverted: false,
+                    });
+                    assert_eq!(value(&temps, operand), expected, "{cell} holds {signal:?}");
+                }
+            }
+        }
+    }
+}
+
+/// Compiles the network with the given settings and asserts that the resulting program equals
+/// `golden` in the textual ISA format, ignoring comments and blank lines. Prints a line-by-line
+/// diff otherwise.
+fn assert_compiles_to<CT: CellType, C: OperationCost<CT>>(
+    network: Network<UntypedNetwork>,
+    arch: Architecture<CT>,
+    cost: C,
+    mode: CompilationMode,
+    golden: &str,
+) {
+    let program = compile_network(arch, cost, network, mode, CandidateSelection::All, false)
+        .expect("compilation should succeed")
+        .program
+        .to_string();
+    let instructions = |program: &str| {
+        program
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with("//"))
+            .map(str::to_string)
+            .collect_vec()
+    };
+    let (actual, expected) = (instructions(&program), instructions(golden));
+    if actual == expected {
+        return;
+    }
+    let diff = expected
+        .iter()
+        .zip_longest(&actual)
+        .map(|lines| match lines {
+            EitherOrBoth::Both(expected, actual) if expected == actual => format!("  {actual}"),
+            EitherOrBoth::Both(expected, actual) => format!("- {expected}\n+ {actual}"),
+            EitherOrBoth::Left(expected) => format!("- {expected}"),
+            EitherOrBoth::Right(actual) => format!("+ {actual}"),
+        })
+        .join("\n");
+    panic!("compiled program differs from the golden program (- golden, + compiled):\n{diff}");
+}
+
+/// Like [`assert_compiles_to`], but reads the golden program from `tests/golden/<name>.txt`. The
+/// file is (re)written instead if it does not exist yet or the `LIME_BLESS` environment variable is
+/// set, e.g. after an intended change of the compiler's output.
+fn assert_compiles_to_golden<CT: CellType, C: OperationCost<CT>>(
+    network: Network<UntypedNetwork>,
+    arch: Architecture<CT>,
+    cost: C,
+    mode: CompilationMode,
+    name: &str,
+) {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(format!("{name}.txt"));
+    match fs::read_to_string(&path) {
+        Ok(golden) if env::var_os("LIME_BLESS").is_none() => {
+            assert_compiles_to(network, arch, cost, mode, &golden)
+        }
+        _ => {
+            let result = compile_network(arch, cost, network, mode, CandidateSelection::All, false)
+                .expect("compilation should succeed");
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, result.program.to_string()).unwrap();
+            eprintln!("recorded golden program {}", path.display());
+        }
+    }
+}
+
+#[test]
+fn test_golden_mux2() {
+    assert_compiles_to_golden(
+        mux2(),
+        Ambit::new(),
+        EqualCosts,
+        CompilationMode::Greedy,
+        "mux2_ambit_greedy",
+    );
+}
+
+#[test]
+fn test_output_polarity() {
+    let network = || {
+        let mut ntk = Network::default();
+        let x = Signal::new(ntk.add(Node::Input(0)), false);
+        let y = Signal::new(ntk.add(Node::Input(1)), false);
+        let z = Signal::new(ntk.add(Node::Input(2)), false);
+        let maj = Signal::new(
+            ntk.add(Node::Gate(UntypedNetwork::Maj(vec![x, y, z]))),
+            false,
+        );
+        ntk.set_outputs(vec![!maj, maj, !x]);
+        ntk
+    };
+
+    let arch = Ambit::new();
+    let arch = Rc::new(ArchitectureMeta {
+        copy_graph: CopyGraph::build(&arch, &EqualCosts),
+        arch,
+    });
+    let input_cells = (0..3).map(|i| Cell::new(AmbitCellType::D, i)).collect_vec();
+    let result = compile(compilation_parameters(
+        arch,
+        EqualCosts,
+        input_cells.clone(),
+        network(),
+    ))
+    .expect("compilation should succeed");
+
+    let ntk = network();
+    let rebuilt = rebuild_network(&result.program, &input_cells, &result.outputs)
+        .expect("network should be rebuildable");
+    for bits in 0..8 {
+        let inputs = Vec::from_iter((0..3).map(|i| bits & (1 << i) != 0));
+        assert_eq!(evaluate(&rebuilt, &inputs), evaluate(&ntk, &inputs));
+    }
+    for (signal, output) in ntk.outputs().iter().zip(&result.outputs) {
+        let placed = &result.signal_placement[&(*signal ^ output.inverted)];
+        assert!(placed.contains(&output.cell));
+    }
+}
+
+#[test]
+fn test_constant_output() {
+    let network = |with_constant: bool| {
+        let mut ntk = Network::default();
+        let x = Signal::new(ntk.add(Node::Input(0)), false);
+        let y = Signal::new(ntk.add(Node::Input(1)), false);
+        let z = Signal::new(ntk.add(Node::Input(2)), false);
+        let maj = Signal::new(
+            ntk.add(Node::Gate(UntypedNetwork::Maj(vec![x, y, z]))),
+            false,
+        );
+        let mut outputs = vec![maj];
+        if with_constant {
+            outputs.push(!Signal::new(ntk.add(Node::False), false));
+        }
+        ntk.set_outputs(outputs);
+        ntk
+    };
+
+    let arch = Ambit::new();
+    let arch = Rc::new(ArchitectureMeta {
+        copy_graph: CopyGraph::build(&arch, &EqualCosts),
+        arch,
+    });
+    let input_cells = (0..3).map(|i| Cell::new(AmbitCellType::D, i)).collect_vec();
+    let compile_network = |with_constant| {
+        compile(compilation_parameters(
+            arch.clone(),
+            EqualCosts,
+            input_cells.clone(),
+            network(with_constant),
+        ))
+        .expect("compilation should succeed")
+    };
+    let result = compile_network(true);
+    assert_eq!(
+        result.outputs[1],
+        Operand {
+            cell: AmbitCellType::constant(true),
+            inverted: false,
+        }
+    );
+    // the constant output does not require any operation
+    let reference = compile_network(false);
+    assert_eq!(result.program.to_string(), reference.program.to_string());
+
+    let rebuilt = rebuild_network(&result.program, &input_cells, &result.outputs)
+        .expect("network should be rebuildable");
+    for bits in 0..8 {
+        let inputs = Vec::from_iter((0..3).map(|i| bits & (1 << i) != 0));
+        assert_eq!(
+            evaluate(&rebuilt, &inputs),
+            evaluate(&network(true), &inputs)
+        );
+    }
+}
+
+#[test]
+fn test_explicit_constants() {
+    let arch = Ambit::new();
+    let arch = Rc::new(ArchitectureMeta {
+        copy_graph: CopyGraph::build(&arch, &EqualCosts),
+        arch,
+    });
+    let input_cells = (0..3).map(|i| Cell::new(AmbitCellType::D, i)).collect_vec();
+    let result = compile(compilation_parameters(
+        arch,
+        EqualCosts,
+        input_cells.clone(),
+        mux2(),
+    ))
+    .expect("compilation should succeed");
+    let mut rebuilt = rebuild_network(&result.program, &input_cells, &result.outputs)
+        .expect("network should be rebuildable");
+    // additionally output both constants
+    let f = Signal::new(rebuilt.add(Node::False), false);
+    let mut outputs = rebuilt.outputs().to_vec();
+    outputs.extend([f, !f]);
+    rebuilt.set_outputs(outputs);
+
+    let explicit = explicit_constants(&rebuilt);
+    let signals = (0..explicit.size())
+        .filter_map(|id| match explicit.node(Id::from_usize(id)) {
+            Node::Gate(gate) => Some(gate.inputs().to_vec()),
+            _ => None,
+        })
+        .flatten()
+        .chain(explicit.outputs().iter().copied());
+    for signal in signals {
+        assert!(!(signal.is_inverted() && matches!(explicit.node(signal.node_id()), Node::False)));
+    }
+    for inputs in (0..3).map(|_| [false, true]).multi_cartesian_product() {
+        assert_eq!(evaluate(&explicit, &inputs), evaluate(&rebuilt, &inputs));
+        assert_eq!(evaluate(&explicit, &inputs)[1..], [false, true]);
+    }
+}
+
+#[test]
+fn test_signal_pins() {
+    let ntk = mux2();
+    let input = (0..ntk.size())
+        .map(Id::from_usize)
+        .find(|id| matches!(ntk.node(*id), Node::Input(0)))
+        .map(|id| Signal::new(id, false))
+        .unwrap();
+    let felix = FELIX::new();
+    let felix = Rc::new(ArchitectureMeta {
+        copy_graph: CopyGraph::build(&felix, &EqualCosts),
+        arch: felix,
+    });
+    let d = |idx| Cell::new(FELIXCellType::D, idx);
+    let compile_pinned = |signal_pins| {
+        compile(CompilationParameters {
+            signal_pins,
+            ..compilation_parameters(felix.clone(), EqualCosts, vec![d(0), d(1), d(2)], mux2())
+        })
+    };
+
+    let pin = d(5);
+    let result =
+        compile_pinned(FxHashMap::from_iter([(input, pin)])).expect("compilation should succeed");
+    let instructions = result.program.instructions().collect_vec();
+    let last_read = instructions
+        .iter()
+        .rposition(|instr| instr.read_cells().contains(&pin))
+        .expect("the pinned input should be read");
+    // the input stays in its pin until it is read for the last time
+    assert!(
+        instructions[..last_read]
+            .iter()
+            .all(|instr| !instr.write_cells().contains(&pin))
+    );
+    let rebuilt = rebuild_network(&result.program, &[pin, d(1), d(2)], &result.outputs)
+        .expect("network should be rebuildable");
+    for inputs in (0..3).map(|_| [false, true]).multi_cartesian_product() {
+        assert_eq!(evaluate(&rebuilt, &inputs), evaluate(&mux2(), &inputs));
+    }
+
+    // inputs can neither be inverted nor share a cell with another input
+    for pins in [[(!input, pin)], [(input, d(1))]] {
+        assert_eq!(
+            compile_pinned(FxHashMap::from_iter(pins)).err(),
+            Some(CompileError::InfeasiblePin(pins[0].0))
+        );
+    }
+    // constant cells can not be pinned to
+    let constant_pin = FxHashMap::from_iter([(input, FELIXCellType::constant(true))]);
+    assert_eq!(
+        compile_pinned(constant_pin).err(),
+        Some(CompileError::InfeasiblePin(input))
+    );
+}
+
+#[test]
+fn test_candidate_progression_high_fanout() {
+    // `hub` is read by every xor gate, which are all read by the output
+    let mut ntk = Network::default();
+    let inputs = (0..8)
+        .map(|i| Signal::new(ntk.add(Node::Input(i)), false))
+        .collect_vec();
+    let hub = ntk.add(Node::Gate(UntypedNetwork::And(vec![inputs[0], inputs[1]])));
+    let xors = inputs
+        .iter()
+        .map(|&input| {
+            let hub = Signal::new(hub, false);
+            Signal::new(
+                ntk.add(Node::Gate(UntypedNetwork::Xor(vec![hub, input]))),
+                false,
+            )
+        })
+        .collect_vec();
+    let output = ntk.add(Node::Gate(UntypedNetwork::And(xors)));
+    ntk.set_outputs(vec![Signal::new(output, false)]);
+
+    let arch = FELIX::new();
+    let params = compilation_parameters(
+        Rc::new(ArchitectureMeta {
+            copy_graph: CopyGraph::build(&arch, &EqualCosts),
+            arch,
+        }),
+        EqualCosts,
+        (0..8).map(|i| Cell::new(FELIXCellType::D, i)).collect(),
+        ntk,
+    );
+    let ntk = &params.network;
+    let mut state = State::initialize(&params);
+    let mut savepoint = state.savepoint();
+    let mut computed = FxHashSet::from_iter(ntk.leaves().iter().copied());
+    // the nodes whose inputs are all computed according to the network itself
+    let expected_candidates = |computed: &FxHashSet<Id>| {
+        (0..ntk.size())
+            .map(Id::from_usize)
+            .filter(|id| !computed.contains(id))
+            .filter(|id| {
+                let inputs = ntk.node(*id).inputs();
+                inputs
+                    .iter()
+                    .all(|input| computed.contains(&input.node_id()))
+            })
+            .collect_vec()
+    };
+    let mut next_cell = 8;
+    loop {
+        let candidates = savepoint.candidates().iter().copied().collect_vec();
+        assert_eq!(candidates, expected_candidates(&computed));
+        let Some(&candidate) = candidates.first() else {
+            break;
+        };
+        savepoint.set(
+            Cell::new(FELIXCellType::D, next_cell),
+            Signal::new(candidate, false),
+        );
+        next_cell += 1;
+        computed.insert(candidate);
+    }
+    assert_eq!(computed.len(), ntk.size());
+    // all readers of the hub are computed, hence it is no longer stored
+    assert_eq!(savepoint.cells_with_id(hub).count(), 0);
+    assert_eq!(savepoint.cells_with_id(output).count(), 1);
+}
+
+#[test]
+fn test_compile_max_depth() {
+    let arch = Ambit::new();
+    let arch = Rc::new(ArchitectureMeta {
+        copy_graph: CopyGraph::build(&arch, &EqualCosts),
+        arch,
+    });
+    let compile_with_depth = |max_depth| {
+        compile(CompilationParameters {
+            max_depth,
+            mode: CompilationMode::Exhaustive,
+            ..compilation_parameters(
+                arch.clone(),
+                EqualCosts,
+                vec![
+                    Cell::new(AmbitCellType::D, 0),
+                    Cell::new(AmbitCellType::D, 1),
+                    Cell::new(AmbitCellType::D, 2),
+                ],
+                mux2(),
+            )
+        })
+        .expect("compilation should succeed")
+    };
+    // branches deeper than one gate are completed greedily instead of being searched
+    let shallow = compile_with_depth(Some(1));
+    let full = compile_with_depth(None);
+    assert!(EqualCosts.program_cost(&shallow.program) >= EqualCosts.program_cost(&full.program));
+    assert!(
+        shallow
+            .warnings
+            .iter()
+            .any(|warning| warning.contains("maximum depth 1"))
+    );
+    assert!(full.warnings.is_empty());
+}
+
+#[test]
+fn test_compile_traced() {
+    let arch = Ambit::new();
+    let arch = ArchitectureMeta {
+        copy_graph: CopyGraph::build(&arch, &EqualCosts),
+        arch,
+    };
+    let params = Rc::new(CompilationParameters {
+        mode: CompilationMode::Exhaustive,
+        ..compilation_parameters(
+            Rc::new(arch),
+            EqualCosts,
+            vec![
+                Cell::new(AmbitCellType::D, 0),
+                Cell::new(AmbitCellType::D, 1),
+                Cell::new(AmbitCellType::D, 2),
+            ],
+            mux2(),
+        )
+    });
+    let mut nodes = Vec::<SearchNode>::new();
+    compile_traced(params.clone(), Some(&mut nodes)).expect("compilation should succeed");
+
+    let roots = nodes
+        .iter()
+        .filter(|node| node.parent.is_none())
+        .collect_vec();
+    assert_eq!(roots.len(), 1);
+    let root = roots[0];
+    assert_eq!(root.candidate, None);
+
+    // the first step branches into all possible ways of computing the only initial candidate
+    let mut state = State::initialize(&params);
+    let mut deltas = Vec::new();
+    DefaultStepFn(AllCandidates).step(
+        &params,
+        DeltaCollectionProgramVersion::new(state.savepoint(), &params, &mut deltas),
+    );
+    let children = nodes
+        .iter()
+        .filter(|node| node.parent == Some(root.id))
+        .collect_vec();
+    assert_eq!(children.len(), deltas.len());
+    assert!(children.iter().all(|node| node.candidate.is_some()));
+    assert!(children.iter().map(|node| node.candidate).all_equal());
+}
+
+#[test]
+fn test_compile_cost_lower_bound() {
+    let arch = Ambit::new();
+    let arch = Rc::new(ArchitectureMeta {
+        copy_graph: CopyGraph::build(&arch, &EqualCosts),
+        arch,
+    });
+    let compile_with_bound = |cost_lower_bound| {
+        let mut nodes = Vec::<SearchNode>::new();
+        let result = compile_traced(
+            CompilationParameters {
+                cost_lower_bound,
+                mode: CompilationMode::Exhaustive,
+                ..compilation_parameters(
+                    arch.clone(),
+                    EqualCosts,
+                    vec![
+                        Cell::new(AmbitCellType::D, 0),
+                        Cell::new(AmbitCellType::D, 1),
+                        Cell::new(AmbitCellType::D, 2),
+                    ],
+                    mux2(),
+                )
+            },
+            Some(&mut nodes),
+        )
+        .expect("compilation should succeed");
+        (EqualCosts.program_cost(&result.program), nodes.len())
+    };
+    let (optimal_cost, all_nodes) = compile_with_bound(None);
+    // the search stops after the first optimal program, hence fewer nodes are visited
+    let (cost, bounded_nodes) = compile_with_bound(Some(optimal_cost));
+    assert_eq!(cost, optimal_cost);
+    assert!(bounded_nodes < all_nodes);
+}
+
+/// Makes every FELIX gate instruction but `OR` expensive, so that branches using them can be
+/// pruned as soon as a program using `OR` only is found.
+#[derive(Clone)]
+struct ExpensiveGates;
+
+impl OperationCost<FELIXCellType> for ExpensiveGates {
+    fn cost<I: Into<CellOrVar<FELIXCellType>>>(
+        &self,
+        instruction: &Instruction<I, FELIXCellType>,
+    ) -> Cost {
+        if instruction.typ.id == FELIX::OR_INSTRUCTION_ID || instruction.inputs.len() == 1 {
+            OrderedFloat(1.0)
+        } else {
+            OrderedFloat(100.0)
+        }
+    }
+}
+
+#[test]
+fn test_compile_branch_and_bound() {
+    let arch = FELIX::new();
+    let arch = Rc::new(ArchitectureMeta {
+        copy_graph: CopyGraph::build(&arch, &ExpensiveGates),
+        arch,
+    });
+    let compile_pruned = |branch_and_bound| {
+        let mut nodes = Vec::<SearchNode>::new();
+        let result = compile_traced(
+            CompilationParameters {
+                branch_and_bound,
+                mode: CompilationMode::Exhaustive,
+                ..compilation_parameters(
+                    arch.clone(),
+                    ExpensiveGates,
+                    (0..3).map(|idx| Cell::new(FELIXCellType::D, idx)).collect(),
+                    mux2(),
+                )
+            },
+            Some(&mut nodes),
+        )
+        .expect("compilation should succeed");
+        (result.program.to_string(), nodes.len())
+    };
+    let (program, all_nodes) = compile_pruned(false);
+    let (pruned_program, pruned_nodes) = compile_pruned(true);
+    // the same optimal program is found while skipping the branches using expensive gates
+    assert_eq!(pruned_program, program);
+    assert!(pruned_nodes < all_nodes);
+}
+
+#[test]
+fn test_cell_reuse_bias() {
+    let arch = Ambit::new();
+    let arch = Rc::new(ArchitectureMeta {
+        copy_graph: CopyGraph::build(&arch, &EqualCosts),
+        arch,
+    });
+    let compile_with_bias = |cell_reuse_bias| {
+        compile(CompilationParameters {
+            cell_reuse_bias,
+            mode: CompilationMode::Exhaustive,
+            ..compilation_parameters(
+                arch.clone(),
+                EqualCosts,
+                vec![
+                    Cell::new(AmbitCellType::D, 0),
+                    Cell::new(AmbitCellType::D, 1),
+                    Cell::new(AmbitCellType::D, 2),
+                ],
+                mux2(),
+            )
+        })
+        .expect("compilation should succeed")
+        .program
+    };
+    let results = [0.0, 0.25, 0.5, 1.0, 2.0, 4.0, 8.0].map(compile_with_bias);
+    for (lower, higher) in results.iter().tuple_windows() {
+        assert!(higher.num_cells() <= lower.num_cells());
+        // fewer cells are only bought with more expensive programs
+        assert!(EqualCosts.program_cost(higher) >= EqualCosts.program_cost(lower));
+    }
+}
+
+#[test]
+fn test_compile_minimal_pressure() {
+    // every node is computed by a single instruction writing to a free cell, hence the number of
+    // cells used is the peak number of live values
+    let arch = Architecture::new(banks_instructions(&["AND_B"]));
+    let arch = Rc::new(ArchitectureMeta {
+        copy_graph: CopyGraph::build(&arch, &EqualCosts),
+        arch,
+    });
+    let compile_with_selection = |candidate_selection| {
+        compile(CompilationParameters {
+            candidate_selection,
+            ..compilation_parameters(
+                arch.clone(),
+                EqualCosts,
+                (0..4).map(|i| Cell::new(BanksCellType::B, i)).collect(),
+                pairwise_ands(),
+            )
+        })
+        .expect("compilation should succeed")
+    };
+    let all = compile_with_selection(CandidateSelection::All);
+    let minimal_pressure = compile_with_selection(CandidateSelection::MinimalPressure);
+    assert!(minimal_pressure.program.num_cells() < all.program.num_cells());
+}
+
+#[test]
+fn test_compile_all_optimal() {
+    // a single majority gate is symmetric in its inputs, hence there are multiple optimal programs
+    let mut ntk = Network::default();
+    let inputs = (0..3)
+        .map(|i| Signal::new(ntk.add(Node::Input(i)), false))
+        .collect();
+    let maj = Signal::new(ntk.add(Node::Gate(UntypedNetwork::Maj(inputs))), false);
+    ntk.set_outputs(vec![maj]);
+
+    let arch = Ambit::new();
+    let arch = ArchitectureMeta {
+        copy_graph: CopyGraph::build(&arch, &EqualCosts),
+        arch,
+    };
+    let results = compile_all_optimal(
+        CompilationParameters {
+            mode: CompilationMode::Exhaustive,
+            ..compilation_parameters(
+                Rc::new(arch),
+                EqualCosts,
+                vec![
+                    Cell::new(AmbitCellType::D, 0),
+                    Cell::new(AmbitCellType::D, 1),
+                    Cell::new(AmbitCellType::D, 2),
+                ],
+                ntk,
+            )
+        },
+        16,
+    );
+    assert!(results.len() > 1, "expected multiple tied-optimal programs");
+    let cost = EqualCosts.program_cost(&results[0].program);
+    for result in &results {
+        assert_eq!(EqualCosts.program_cost(&result.program), cost);
+    }
+    let programs = results
+        .iter()
+        .map(|result| result.program.to_string())
+        .collect::<std::collections::HashSet<_>>();
+    assert_eq!(programs.len(), results.len());
+}
+
+#[test]
+fn test_compile_invalid_networks() {
+    let mut without_outputs = Network::<UntypedNetwork>::default();
+    without_outputs.add(Node::Input(0));
+
+    let mut even_maj = Network::<UntypedNetwork>::default();
+    let i0 = Signal::new(even_maj.add(Node::Input(0)), false);
+    let i1 = Signal::new(even_maj.add(Node::Input(1)), false);
+    let maj = Signal::new(
+        even_maj.add(Node::Gate(UntypedNetwork::Maj(vec![i0, i1]))),
+        false,
+    );
+    even_maj.set_outputs(vec![maj]);
+
+    let mut dangling = Network::<UntypedNetwork>::default();
+    let i0 = Signal::new(dangling.add(Node::Input(0)), false);
+    let and = Signal::new(
+        dangling.add(Node::Gate(UntypedNetwork::And(vec![
+            i0,
+            Signal::new(Id::from_usize(5), false),
+        ]))),
+        false,
+    );
+    dangling.set_outputs(vec![and]);
+
+    for ntk in [without_outputs, even_maj, dangling] {
+        let settings = CompilerSettings {
+            rewriting: RewritingStrategy::None,
+            rewriting_size_factor: 0,
+            validator: ReceiverFFI::new(NetworkReceiver::<UntypedNetwork>::default().map(|_| true))
+                .into(),
+            mode: CompilationMode::Greedy,
+            candidate_selector: CandidateSelection::All,
+            tied_optimal_limit: 0,
+            extraction_compile_threshold: 0,
+            trace_search: false,
+            spill_warning_fraction: 1.0,
+            prematerialize_constants: false,
+            dump_extracted_network: ptr::null(),
+            seed: 0,
+            verbosity: Verbosity::SILENT,
+            gate_biases: GateBiases::default(),
+            profile: false,
+            cell_reuse_bias: 0.0,
+            signal_pins: FxHashMap::default(),
+            branch_and_bound: false,
+        };
+        let stats = ntk.send(generic_compiler_entrypoint(
+            Ambit::new(),
+            EqualCosts,
+            settings,
+            false,
+        ));
+        assert!(!stats.validation_success);
+        assert_eq!(stats.cost, INVALID_NETWORK_COST);
+    }
+}
+
+#[test]
+fn test_null_validator() {
+    let validator = FfiValidator::new(ReceiverFFI::new(
+        NetworkReceiver::<UntypedNetwork>::default().map(|_| true),
+    ));
+    assert!(!validator.is_null());
+    assert!(FfiValidator::null().is_null());
+    assert!(FfiValidator::null().into_receiver().is_none());
+
+    // receivers without state may pass a null data pointer
+    extern "C" fn never_called() {
+        unreachable!()
+    }
+    let mut stateless = FfiValidator::null();
+    let raw = stateless.0.as_mut_ptr().cast::<RawReceiverFFI>();
+    unsafe { (*raw).functions.fill(Some(never_called)) };
+    assert!(!stateless.is_null());
+    mem::forget(stateless);
+
+    let settings = CompilerSettings {
+        rewriting: RewritingStrategy::None,
+        rewriting_size_factor: 0,
+        validator: FfiValidator::null(),
+        mode: CompilationMode::Greedy,
+        candidate_selector: CandidateSelection::All,
+        tied_optimal_limit: 0,
+        extraction_compile_threshold: 0,
+        trace_search: false,
+        spill_warning_fraction: 1.0,
+        prematerialize_constants: false,
+        dump_extracted_network: ptr::null(),
+        seed: 0,
+        verbosity: Verbosity::SILENT,
+        gate_biases: GateBiases::default(),
+        profile: false,
+        cell_reuse_bias: 0.0,
+        signal_pins: FxHashMap::default(),
+        branch_and_bound: false,
+    };
+    // the program is compiled, but not validated
+    let stats = mux1().send(generic_compiler_entrypoint(
+        Ambit::new(),
+        EqualCosts,
+        settings,
+        false,
+    ));
+    assert!(!stats.validation_success);
+    assert_ne!(stats.cost, INVALID_NETWORK_COST);
+    assert!(stats.num_instr > 0);
+}
+
+/// Parses the counts of a flat JSON object with the given key as produced by
+/// [`crate::usage_summary_json`].
+fn parse_usage_counts(json: &str, key: &str) -> Vec<(String, usize)> {
+    let prefix = format!("\"{key}\":{{");
+    let start = json.find(&prefix).expect("key should be present") + prefix.len();
+    let end = start + json[start..].find('}').expect("object should be closed");
+    json[start..end]
+        .split(',')
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| {
+            let (name, count) = entry.split_once(':').expect("entry should be a pair");
+            (
+                name.trim_matches('"').to_string(),
+                count.parse().expect("count should be a number"),
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn test_usage_summary_ffi() {
+    let settings = CompilerSettings {
+        rewriting: RewritingStrategy::None,
+        rewriting_size_factor: 0,
+        validator: ReceiverFFI::new(NetworkReceiver::<UntypedNetwork>::default().map(|_| true))
+            .into(),
+        mode: CompilationMode::Greedy,
+        candidate_selector: CandidateSelection::All,
+        tied_optimal_limit: 0,
+        extraction_compile_threshold: 0,
+        trace_search: false,
+        spill_warning_fraction: 1.0,
+        prematerialize_constants: false,
+        dump_extracted_network: ptr::null(),
+        seed: 0,
+        verbosity: Verbosity::SILENT,
+        gate_biases: GateBiases::default(),
+        profile: false,
+        cell_reuse_bias: 0.0,
+        signal_pins: FxHashMap::default(),
+        branch_and_bound: false,
+    };
+    let stats = mux1().send(map_result_to_ffi(generic_compiler_with_program(
+        Ambit::new(),
+        EqualCosts,
+        settings,
+        false,
+    )));
+    assert!(!stats.usage_str.is_null());
+    let usage = unsafe { CStr::from_ptr(stats.usage_str) }
+        .to_str()
+        .expect("usage should be valid UTF-8")
+        .to_string();
+    gp_free_program_string(stats.usage_str.cast_mut());
+    gp_free_program_string(stats.program_str.cast_mut());
+    println!("{usage}");
+
+    let cells = parse_usage_counts(&usage, "cells");
+    let instructions = parse_usage_counts(&usage, "instructions");
+    // the inputs are placed in D cells
+    assert!(cells.iter().any(|(name, count)| name == "D" && *count >= 3));
+    assert_eq!(
+        cells.iter().map(|(_, count)| count).sum::<usize>() as u64,
+        stats.num_cells
+    );
+    assert_eq!(
+        instructions.iter().map(|(_, count)| count).sum::<usize>() as u64,
+        stats.num_instr
+    );
+}
+
+#[test]
+fn test_extraction_compile_threshold() {
+    let arch = Ambit::new();
+    let arch = Rc::new(ArchitectureMeta {
+        copy_graph: CopyGraph::build(&arch, &EqualCosts),
+        arch,
+    });
+    let extract = |compile_threshold| {
+        mux2().send(rewriting_receiver(RewritingOptions {
+            compile_threshold,
+            compilation_mode: CompilationMode::Exhaustive,
+            ..rewriting_options(arch.clone(), RewritingStrategy::Compiling)
+        }))
+    };
+    // a threshold of zero compiles every eclass, as before the threshold was introduced
+    let (full, full_stats) = extract(0);
+    let (cheap, cheap_stats) = extract(usize::MAX);
+    println!(
+        "full: {}ms, cheap: {}ms",
+        full_stats.t_extractor, cheap_stats.t_extractor
+    );
+    assert_eq!(full.outputs().len(), 1);
+    assert_eq!(cheap.outputs().len(), 1);
+    assert!(cheap_stats.t_extractor <= full_stats.t_extractor);
+    for stats in [full_stats, cheap_stats] {
+        assert!(stats.extraction_total_classes > 0);
+        assert!(stats.extraction_none_count < stats.extraction_total_classes);
+    }
+}
+
+#[test]
+fn test_gate_biases() {
+    let arch = FELIX::new();
+    let arch = Rc::new(ArchitectureMeta {
+        copy_graph: CopyGraph::build(&arch, &EqualCosts),
+        arch,
+    });
+    // xnor built from and gates, which the rewrites can convert to a negated xor gate
+    let xnor = || {
+        let mut ntk = Network::default();
+        let x = Signal::new(ntk.add(Node::Input(0)), false);
+        let y = Signal::new(ntk.add(Node::Input(1)), false);
+        let mut and =
+            |a, b| Signal::new(ntk.add(Node::Gate(UntypedNetwork::And(vec![a, b]))), false);
+        let (l, r) = (and(x, !y), and(!x, y));
+        let out = and(!l, !r);
+        ntk.set_outputs(vec![out]);
+        ntk
+    };
+    let extract = |gate_biases| {
+        let (ntk, _) = xnor().send(rewriting_receiver(RewritingOptions {
+            size_factor: 4,
+            gate_biases,
+            ..rewriting_options(arch.clone(), RewritingStrategy::Compiling)
+        }));
+        network_stats(&ntk)
+    };
+    let favor_xor = extract(GateBiases {
+        xor: 0.01,
+        ..Default::default()
+    });
+    assert!(favor_xor.xor_count > 0);
+    let avoid_xor = extract(GateBiases {
+        xor: 100.0,
+        ..Default::default()
+    });
+    assert_eq!(avoid_xor.xor_count, 0);
+}
+
+#[test]
+fn test_verbosity() {
+    let arch = Ambit::new();
+    let arch = Rc::new(ArchitectureMeta {
+        copy_graph: CopyGraph::build(&arch, &EqualCosts),
+        arch,
+    });
+    let result = compile(compilation_parameters(
+        arch,
+        EqualCosts,
+        (0..3).map(|i| Cell::new(AmbitCellType::D, i)).collect(),
+        mux2(),
+    ))
+    .expect("compilation should succeed");
+    let report = |level| {
+        let mut out = Vec::new();
+        report_result(&mut out, Verbosity(level), &result, &EqualCosts);
+        String::from_utf8(out).unwrap()
+    };
+
+    // each section only appears from its level on
+    let sections = [
+        (Verbosity::SUMMARY, "=== statistics"),
+        (Verbosity::PROGRAM, "=== final program"),
+        (Verbosity::PROGRAM, "=== output cells"),
+        (Verbosity::TRACE, "=== signal placement"),
+    ];
+    assert_eq!(report(0), "");
+    for level in 1..=3 {
+        let report = report(level);
+        for (threshold, section) in sections {
+            assert_eq!(
+                report.contains(section),
+                Verbosity(level).enabled(threshold),
+                "{section} at verbosity {level}"
+            );
+        }
+    }
+    assert!(report(2).contains(&result.program.to_string()));
+}
+
+#[test]
+fn test_dump_extracted_network() {
+    let arch = Ambit::new();
+    let arch = Rc::new(ArchitectureMeta {
+        copy_graph: CopyGraph::build(&arch, &EqualCosts),
+        arch,
+    });
+    let path = env::temp_dir().join(format!("lime-extracted-{}.txt", std::process::id()));
+    let (ntk, _) = mux2().send(rewriting_receiver(RewritingOptions {
+        dump_path: Some(path.clone()),
+        ..rewriting_options(arch, RewritingStrategy::GreedyEstimate)
+    }));
+    let dumped = fs::read_to_string(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+    assert_eq!(dumped, format_network(&ntk));
+    let reloaded = parse_network(&dumped).unwrap();
+    assert_eq!(reloaded.outputs().len(), ntk.outputs().len());
+    for inputs in (0..3).map(|_| [false, true]).multi_cartesian_product() {
+        assert_eq!(evaluate(&reloaded, &inputs), evaluate(&ntk, &inputs));
+    }
+}
+
+#[test]
+fn test_extra_rewrite_rules() {
+    let arch = Ambit::new();
+    let rules = rewriting_rules(
+        &arch,
+        vec![rewrite!("custom-maj-and"; "(maj ?x ?y f)" => "(and ?x ?y)")],
+    );
+    assert!(
+        rules
+            .iter()
+            .any(|rule| rule.name.as_str() == "custom-maj-and")
+    );
+
+    // n1 of mux2 is a majority with a constant false input
+    let (egraph, _) = mux2().send(EGraph::<UntypedNetworkLanguage, LimeAnalysis>::default());
+    let runner = Runner::default()
+        .with_egraph(egraph)
+        .with_iter_limit(1)
+        .run(&rules);
+    assert!(runner.iterations.iter().any(|iteration| {
+        iteration
+            .applied
+            .keys()
+            .any(|name| name.as_str() == "custom-maj-and")
+    }));
+}
+
+#[test]
+fn test_extraction_fallback() {
+    let arch = Ambit::new();
+    let arch = Rc::new(ArchitectureMeta {
+        copy_graph: CopyGraph::build(&arch, &EqualCosts),
+        arch,
+    });
+    let extract = |strategy| {
+        mux2().send(rewriting_receiver(rewriting_options(
+            arch.clone(),
+            strategy,
+        )))
+    };
+    // a failing LP solver is replaced by the greedy extraction
+    let (ntk, _) = extract_with_fallback(
+        || panic!("LP extraction failed"),
+        || extract(RewritingStrategy::GreedyEstimate),
+    );
+    for inputs in (0..3).map(|_| [false, true]).multi_cartesian_product() {
+        assert_eq!(evaluate(&ntk, &inputs), evaluate(&mux2(), &inputs));
+    }
+    let (ntk, _) = extract_with_fallback(
+        || extract(RewritingStrategy::None),
+        || panic!("fallback should not be used"),
+    );
+    assert_eq!(ntk.outputs().len(), 1);
+}
+
+#[test]
+fn test_output_strategies() {
+    let arch = FELIX::new();
+    let arch = Rc::new(ArchitectureMeta {
+        copy_graph: CopyGraph::build(&arch, &EqualCosts),
+        arch,
+    });
+    // a xor cone and a majority cone sharing their inputs
+    let network = || {
+        let mut ntk = Network::default();
+        let inputs = (0..3)
+            .map(|i| Signal::new(ntk.add(Node::Input(i)), false))
+            .collect_vec();
+        let xor = UntypedNetwork::Xor(vec![inputs[0], inputs[1]]);
+        let maj = UntypedNetwork::Maj(inputs);
+        let outputs = vec![
+            Signal::new(ntk.add(Node::Gate(xor)), false),
+            Signal::new(ntk.add(Node::Gate(maj)), true),
+        ];
+        ntk.set_outputs(outputs);
+        ntk
+    };
+
+    let (ntk, _) = network().send(rewriting_receiver(RewritingOptions {
+        output_strategies: FxHashMap::from_iter([
+            (0, RewritingStrategy::LP),
+            (1, RewritingStrategy::Compiling),
+        ]),
+        ..rewriting_options(arch, RewritingStrategy::GreedyEstimate)
+    }));
+    assert_eq!(ntk.outputs().len(), 2);
+    for inputs in (0..3).map(|_| [false, true]).multi_cartesian_product() {
+        assert_eq!(evaluate(&ntk, &inputs), evaluate(&network(), &inputs));
+    }
+}
+
+#[test]
+fn test_find_divergence() {
+    let arch = FELIX::new();
+    let arch = Rc::new(ArchitectureMeta {
+        copy_graph: CopyGraph::build(&arch, &EqualCosts),
+        arch,
+    });
+    let (extracted, _) = mux2().send(rewriting_receiver(rewriting_options(
+        arch,
+        RewritingStrategy::GreedyEstimate,
+    )));
+    assert_eq!(find_divergence(&mux2(), &extracted, 0), None);
+
+    // a broken extraction computing the inverted output
+    let mut broken = mux2();
+    let outputs = broken.outputs().iter().map(|output| !*output).collect_vec();
+    broken.set_outputs(outputs);
+    let inputs = find_divergence(&mux2(), &broken, 0).unwrap();
+    assert_ne!(evaluate(&mux2(), &inputs), evaluate(&broken, &inputs));
+
+    // networks with too many inputs are compared on samples
+    let wide = |inverted| {
+        let mut ntk = Network::default();
+        let inputs = (0..EXHAUSTIVE_COMPARISON_INPUTS + 1)
+            .map(|i| Signal::new(ntk.add(Node::Input(i as u32)), false))
+            .collect_vec();
+        let xor = ntk.add(Node::Gate(UntypedNetwork::Xor(inputs)));
+        ntk.set_outputs(vec![Signal::new(xor, inverted)]);
+        ntk
+    };
+    assert_eq!(find_divergence(&wide(false), &wide(false), 16), None);
+    assert!(find_divergence(&wide(false), &wide(true), 16).is_some());
+}
+
+#[test]
+fn test_min_depth_extraction() {
+    let arch = FELIX::new();
+    let arch = Rc::new(ArchitectureMeta {
+        copy_graph: CopyGraph::build(&arch, &EqualCosts),
+        arch,
+    });
+    // a chain of and gates, which can be balanced to a tree of depth 2
+    let chain = || {
+        let mut ntk = Network::default();
+        let inputs = (0..4)
+            .map(|i| Signal::new(ntk.add(Node::Input(i)), false))
+            .collect_vec();
+        let output = inputs[..3].iter().rev().fold(inputs[3], |acc, &input| {
+            Signal::new(
+                ntk.add(Node::Gate(UntypedNetwork::And(vec![input, acc]))),
+                false,
+            )
+        });
+        ntk.set_outputs(vec![output]);
+        ntk
+    };
+    let extract = |strategy| {
+        chain().send(rewriting_receiver(RewritingOptions {
+            size_factor: 4,
+            ..rewriting_options(arch.clone(), strategy)
+        }))
+    };
+    let (deep, deep_stats) = extract(RewritingStrategy::None);
+    assert_eq!(network_stats(&deep).depth, 3);
+    assert_eq!(deep_stats.extracted_depth, 3);
+    let (shallow, shallow_stats) = extract(RewritingStrategy::MinDepth);
+    assert_eq!(network_stats(&shallow).depth, 2);
+    assert_eq!(shallow_stats.extracted_depth, 2);
+    for inputs in (0..4).map(|_| [false, true]).multi_cartesian_product() {
+        assert_eq!(evaluate(&shallow, &inputs), evaluate(&chain(), &inputs));
+    }
+}
+
+#[test]
+fn test_compile_both_polarities() {
+    // x is needed in both polarities by the same operation
+    let mut ntk = Network::default();
+    let x = Signal::new(ntk.add(Node::Input(0)), false);
+    let y = Signal::new(ntk.add(Node::Input(1)), false);
+    let maj = Signal::new(
+        ntk.add(Node::Gate(UntypedNetwork::Maj(vec![x, !x, y]))),
+        false,
+    );
+    ntk.set_outputs(vec![maj]);
+
+    let arch = Ambit::new();
+    let arch = ArchitectureMeta {
+        copy_graph: CopyGraph::build(&arch, &EqualCosts),
+        arch,
+    };
+    let x_cell = Cell::new(AmbitCellType::D, 0);
+    let result = compile(compilation_parameters(
+        Rc::new(arch),
+        EqualCosts,
+        vec![x_cell, Cell::new(AmbitCellType::D, 1)],
+        ntk,
+    ))
+    .expect("compilation should succeed");
+    println!("{}", result.program);
+    // x is copied out of its input cell once, !x is derived from that copy
+    let copies_of_x = result
+        .program
+        .0
+        .iter()
+        .filter(|op| matches!(op, Operation::Copy { from, .. } if *from == x_cell))
+        .count();
+    assert_eq!(copies_of_x, 1);
+}
+
+#[test]
+fn test_compile_enabled_instructions() {
+    let arch = Ambit::new();
+    let compile_with = |enabled: Option<FxHashSet<u8>>| {
+        let arch = ArchitectureMeta {
+            copy_graph: CopyGraph::build_with_instructions(&arch, &EqualCosts, enabled.as_ref()),
+            arch: arch.clone(),
+        };
+        compile(CompilationParameters {
+            enabled_instructions: enabled,
+            ..compilation_parameters(
+                Rc::new(arch),
+                EqualCosts,
+                vec![
+                    Cell::new(AmbitCellType::D, 0),
+                    Cell::new(AmbitCellType::D, 1),
+                    Cell::new(AmbitCellType::D, 2),
+                ],
+                mux1(),
+            )
+        })
+    };
+    assert!(compile_with(None).is_ok());
+
+    // TRA is the only instruction computing a majority, hence nothing can be computed without it
+    let instructions = Ambit::instructions();
+    let without_tra = instructions
+        .iter()
+        .filter(|instr| instr.name != "TRA")
+        .map(|instr| instr.id)
+        .collect();
+    assert!(compile_with(Some(without_tra)).is_err());
+}
+
+define_generic_architecture! {
+    MixedIo {
+        cells ([D; 2]),
+        operands (
+            PAIR = [(D, D)],
+            ANY = [(D)],
+        ),
+        instructions (
+            AND = (and(PAIR) -> (ANY)),
+            AND_DISJOINT = (and(PAIR) -> (ANY)) disjoint_io,
+        ),
+    }
+}
+
+#[test]
+fn test_requires_disjoint_io() {
+    let arch = MixedIo::new();
+    let compile_with = |instruction: u8, disjunct_input_output: bool| {
+        let enabled = FxHashSet::from_iter([instruction]);
+        let arch = ArchitectureMeta {
+            copy_graph: CopyGraph::build_with_instructions(&arch, &EqualCosts, Some(&enabled)),
+            arch: arch.clone(),
+        };
+        let mut ntk = Network::default();
+        let x = Signal::new(ntk.add(Node::Input(0)), false);
+        let y = Signal::new(ntk.add(Node::Input(1)), false);
+        let and = Signal::new(ntk.add(Node::Gate(UntypedNetwork::And(vec![x, y]))), false);
+        ntk.set_outputs(vec![and]);
+        compile(CompilationParameters {
+            disjunct_input_output,
+            enabled_instructions: Some(enabled),
+            ..compilation_parameters(
+                Rc::new(arch),
+                EqualCosts,
+                vec![
+                    Cell::new(MixedIoCellType::D, 0),
+                    Cell::new(MixedIoCellType::D, 1),
+                ],
+                ntk,
+            )
+        })
+    };
+    // both cells hold the inputs, hence the output has to override one of them
+    let result = compile_with(MixedIo::AND_INSTRUCTION_ID, false).expect("AND may work in-place");
+    let instruction = result.program.instructions().next().unwrap();
+    assert!(instruction.inputs.contains(&instruction.outputs[0].cell));
+    assert!(compile_with(MixedIo::AND_DISJOINT_INSTRUCTION_ID, false).is_err());
+    // the global flag is relaxed if there is no other way to place the output, but only for
+    // instructions not requiring disjoint inputs and outputs
+    let result = compile_with(MixedIo::AND_INSTRUCTION_ID, true).expect("AND may be relaxed");
+    let instruction = result.program.instructions().next().unwrap();
+    assert!(instruction.inputs.contains(&instruction.outputs[0].cell));
+    assert!(compile_with(MixedIo::AND_DISJOINT_INSTRUCTION_ID, true).is_err());
+}
+
+define_generic_architecture! {
+    TieBreaking {
+        cells ([D]),
+        operands (
+            QUAD = [(D, D, D, D)],
+            ANY = [(D)],
+        ),
+        instructions (
+            MAJ = (maj(QUAD) -> (ANY)),
+            MAJ_TIE_BREAKING = (maj(QUAD) -> (ANY)) tie_breaker = 3,
+        ),
+    }
+}
+
+#[test]
+fn test_tie_breaking_majority() {
+    let instructions = TieBreaking::instructions();
+    let strict = &instructions[TieBreaking::MAJ_INSTRUCTION_ID as usize];
+    let tie_breaking = &instructions[TieBreaking::MAJ_TIE_BREAKING_INSTRUCTION_ID as usize];
+    assert_eq!(tie_breaking.function.tie_breaker, Some(3));
+    assert!(
+        tie_breaking
+            .describe()
+            .to_string()
+            .ends_with("; breaks ties by input 3")
+    );
+
+    let evaluate_typ = |typ: &InstructionType<TieBreakingCellType>, values: [bool; 4]| {
+        let mut evaluation = typ.function.evaluate(values.len());
+        // the position of the tie-breaker does not depend on the order of the values
+        for (idx, value) in values.into_iter().enumerate().rev() {
+            evaluation.add(idx, value);
+        }
+        evaluation.evaluate()
+    };
+    assert_eq!(evaluate_typ(strict, [true, true, false, false]), None);
+    for (values, result) in [
+        ([true, true, false, false], false),
+        ([false, true, false, true], true),
+        ([true, true, true, false], true),
+    ] {
+        assert_eq!(evaluate_typ(tie_breaking, values), Some(result));
+    }
+
+    // only the strict majority computes the majority gates of networks
+    assert_eq!(
+        instructions.gates().collect_vec(),
+        [lime_generic_def::Gate::Maj]
+    );
+    assert_eq!(instructions.ids_by_gate(GateFunction::Maj), &[strict.id]);
+}
+
+define_generic_architecture! {
+    FullAdder {
+        cells ([D]),
+        operands (
+            TRIPLE = [(D, D, D)],
+            PAIR = [(D, D)],
+        ),
+        instructions (
+            FA = (xor(TRIPLE) -> (PAIR)) secondary = maj,
+        ),
+    }
+}
+
+#[test]
+fn test_full_adder() {
+    let instructions = FullAdder::instructions();
+    let fa = &instructions[FullAdder::FA_INSTRUCTION_ID as usize];
+    assert!(
+        fa.describe()
+            .to_string()
+            .ends_with("; computes maj into output 1")
+    );
+    assert_eq!(instructions.ids_by_gate(GateFunction::Xor), &[fa.id]);
+    assert_eq!(instructions.ids_by_gate(GateFunction::Maj), &[fa.id]);
+
+    // sum and carry are separate gates reading the same inputs
+    let network = || {
+        let mut ntk = Network::default();
+        let inputs = Vec::from_iter((0..3).map(|i| Signal::new(ntk.add(Node::Input(i)), false)));
+        let sum = ntk.add(Node::Gate(UntypedNetwork::Xor(inputs.clone())));
+        let carry = ntk.add(Node::Gate(UntypedNetwork::Maj(inputs)));
+        ntk.set_outputs(vec![Signal::new(sum, false), Signal::new(carry, false)]);
+        ntk
+    };
+    let arch = FullAdder::new();
+    let arch = Rc::new(ArchitectureMeta {
+        copy_graph: CopyGraph::build(&arch, &EqualCosts),
+        arch,
+    });
+    let input_cells = Vec::from_iter((0..3).map(|i| Cell::new(FullAdderCellType::D, i)));
+    let result = compile(compilation_parameters(
+        arch,
+        EqualCosts,
+        input_cells.clone(),
+        network(),
+    ))
+    .expect("compilation should succeed");
+    println!("{}", result.program);
+
+    // a single operation computes both outputs
+    assert_eq!(result.program.compute_instruction_count(), 1);
+    assert_eq!(result.program.instructions().count(), 1);
+    let rebuilt = rebuild_network(&result.program, &input_cells, &result.outputs)
+        .expect("network should be rebuildable");
+    for bits in 0..8 {
+        let inputs = Vec::from_iter((0..3).map(|i| bits & (1 << i) != 0));
+        assert_eq!(evaluate(&rebuilt, &inputs), evaluate(&network(), &inputs));
+    }
+}
+
+#[test]
+fn test_felix_disjoint_io() {
+    let arch = FELIX::new();
+    let arch = Rc::new(ArchitectureMeta {
+        copy_graph: CopyGraph::build(&arch, &EqualCosts),
+        arch,
+    });
+    // FELIX can not override the inputs of an instruction, independent of the global flag
+    for disjunct_input_output in [false, true] {
+        let result = compile(CompilationParameters {
+            disjunct_input_output,
+            ..compilation_parameters(
+                arch.clone(),
+                EqualCosts,
+                (0..4).map(|idx| Cell::new(FELIXCellType::D, idx)).collect(),
+                reconvergent(),
+            )
+        })
+        .expect("compilation should succeed");
+        for instruction in result.program.instructions() {
+            assert!(
+                instruction
+                    .outputs
+                    .iter()
+                    .all(|output| !instruction.inputs.contains(&output.cell)),
+                "{instruction} overrides its inputs"
+            );
+        }
+    }
+}
+
+#[test]
+fn test_synthesizable_gates() {
+    // MixedIo only computes and, majority and xor are obtained by the conversion rewrites
+    assert_eq!(
+        MixedIo::new().synthesizable_gates(),
+        FxHashSet::from_iter([GateFunction::And, GateFunction::Maj, GateFunction::Xor])
+    );
+    // copies are no gates
+    assert!(Relay::new().synthesizable_gates().is_empty());
+}
+
+/// Charges for every output operand written by an instruction.
+#[derive(Clone)]
+struct PerOutputCost;
+
+impl OperationCost<AmbitCellType> for PerOutputCost {
+    fn cost<I: Into<CellOrVar<AmbitCellType>>>(
+        &self,
+        _instruction: &Instruction<I, AmbitCellType>,
+    ) -> Cost {
+        OrderedFloat(1.0)
+    }
+
+    fn output_cost(&self, _typ: &InstructionType<AmbitCellType>, outputs: usize) -> Cost {
+        OrderedFloat(outputs as f64)
+    }
+}
+
+#[test]
+fn test_output_cost() {
+    let ambit = Ambit::new();
+    let rc = ambit
+        .instructions()
+        .iter()
+        .find(|typ| typ.name == "RC")
+        .unwrap();
+    let t = |idx| Operand {
+        cell: Cell::new(AmbitCellType::T, idx),
+        inverted: false,
+    };
+    let copy = |outputs: Vec<_>| Instruction {
+        typ: rc.clone(),
+        inputs: vec![Cell::new(AmbitCellType::D, 0)],
+        outputs,
+    };
+    let single = copy(vec![t(0)]);
+    let double = copy(vec![t(0), t(3)]);
+    assert!(single.validate().is_ok() && double.validate().is_ok());
+    assert_eq!(PerOutputCost.instruction_cost(&single), OrderedFloat(2.0));
+    assert_eq!(PerOutputCost.instruction_cost(&double), OrderedFloat(3.0));
+    assert_eq!(
+        PerOutputCost.program_cost(&Program(vec![Operation::Other {
+            instructions: vec![single, double],
+            comment: None,
+        }])),
+        OrderedFloat(5.0)
+    );
+
+    // copies discovered for the copy graph are charged for their single output as well
+    let graph = CopyGraph::build(&ambit, &PerOutputCost);
+    let (_, _, edge) = graph
+        .all_optimal_edges_matching(
+            CellPat::Cell(Cell::new(AmbitCellType::D, 0)),
+            CellPat::Cell(Cell::new(AmbitCellType::T, 0)),
+            false,
+        )
+        .next()
+        .unwrap();
+    assert_eq!(edge.cost, OrderedFloat(2.0));
+}
+
+/// Charges extra for accessing the last row of Ambit's compute rows.
+#[derive(Clone)]
+struct FarRowCost;
+
+impl OperationCost<AmbitCellType> for FarRowCost {
+    fn cost<I: Into<CellOrVar<AmbitCellType>>>(
+        &self,
+        _instruction: &Instruction<I, AmbitCellType>,
+    ) -> Cost {
+        OrderedFloat(1.0)
+    }
+
+    fn cell_access_cost(&self, cell: Cell<AmbitCellType>) -> Cost {
+        if cell.typ() == AmbitCellType::T && cell.index() >= 3 {
+            OrderedFloat(100.0)
+        } else {
+            OrderedFloat(0.0)
+        }
+    }
+}
+
+#[test]
+fn test_compile_cell_access_cost() {
+    let arch = Ambit::new();
+    let arch = ArchitectureMeta {
+        copy_graph: CopyGraph::build(&arch, &FarRowCost),
+        arch,
+    };
+    let result = compile(CompilationParameters {
+        mode: CompilationMode::Exhaustive,
+        ..compilation_parameters(
+            Rc::new(arch),
+            FarRowCost,
+            vec![
+                Cell::new(AmbitCellType::D, 0),
+                Cell::new(AmbitCellType::D, 1),
+                Cell::new(AmbitCellType::D, 2),
+            ],
+            mux1(),
+        )
+    })
+    .expect("compilation should succeed");
+    println!("{}", result.program);
+    let far_row = Cell::new(AmbitCellType::T, 3);
+    assert!(result.program.instructions().all(|instr| {
+        instr
+            .read_cells()
+            .chain(instr.write_cells())
+            .all(|cell| cell != far_row)
+    }));
+    assert!(FarRowCost.program_cost(&result.program) < OrderedFloat(100.0));
+}
+
+/// Charges for every instruction a compute row of Ambit holds a value.
+#[derive(Clone)]
+struct ComputeRowOccupancyCost;
+
+impl OperationCost<AmbitCellType> for ComputeRowOccupancyCost {
+    fn cost<I: Into<CellOrVar<AmbitCellType>>>(
+        &self,
+        _instruction: &Instruction<I, AmbitCellType>,
+    ) -> Cost {
+        OrderedFloat(1.0)
+    }
+
+    fn occupancy_cost(&self, typ: AmbitCellType) -> Cost {
+        if typ == AmbitCellType::T {
+            OrderedFloat(1.0)
+        } else {
+            OrderedFloat(0.0)
+        }
+    }
+}
+
+#[test]
+fn test_occupancy_cost() {
+    let ambit = Ambit::new();
+    let rc = ambit
+        .instructions()
+        .iter()
+        .find(|typ| typ.name == "RC")
+        .unwrap();
+    let d = |idx| Cell::new(AmbitCellType::D, idx);
+    let t = |idx| Cell::new(AmbitCellType::T, idx);
+    let copy = |from, to| {
+        rc.build(
+            vec![from],
+            vec![Operand {
+                cell: to,
+                inverted: false,
+            }],
+        )
+        .unwrap()
+    };
+    let program = |instructions| {
+        Program(vec![Operation::Other {
+            instructions,
+            comment: None,
+        }])
+    };
+    // T[0] holds its value while T[1] is written and read
+    let long = program(vec![
+        copy(d(0), t(0)),
+        copy(d(1), t(1)),
+        copy(t(1), d(2)),
+        copy(t(0), d(3)),
+    ]);
+    // T[0] is read right after being written
+    let brief = program(vec![
+        copy(d(0), t(0)),
+        copy(t(0), d(3)),
+        copy(d(1), t(1)),
+        copy(t(1), d(2)),
+    ]);
+    assert_eq!(
+        long.occupancy_cost(&ComputeRowOccupancyCost),
+        OrderedFloat(4.0)
+    );
+    assert_eq!(
+        brief.occupancy_cost(&ComputeRowOccupancyCost),
+        OrderedFloat(2.0)
+    );
+    // both programs are equally expensive otherwise
+    assert_eq!(
+        ComputeRowOccupancyCost.program_cost(&long),
+        ComputeRowOccupancyCost.program_cost(&brief)
+    );
+    assert_eq!(long.occupancy_cost(&EqualCosts), OrderedFloat(0.0));
+}
+
+/// Charges extra for FELIX' XOR instruction.
+#[derive(Clone)]
+struct XorCost;
+
+impl OperationCost<FELIXCellType> for XorCost {
+    fn cost<I: Into<CellOrVar<FELIXCellType>>>(
+        &self,
+        instruction: &Instruction<I, FELIXCellType>,
+    ) -> Cost {
+        if instruction.typ.name == "XOR" {
+            OrderedFloat(1.5)
+        } else {
+            OrderedFloat(1.0)
+        }
+    }
+}
+
+#[test]
+fn test_cost_breakdown() {
+    let mut ntk = Network::default();
+    let x = Signal::new(ntk.add(Node::Input(0)), false);
+    let y = Signal::new(ntk.add(Node::Input(1)), false);
+    let xor = Signal::new(ntk.add(Node::Gate(UntypedNetwork::Xor(vec![x, y]))), false);
+    ntk.set_outputs(vec![xor, !x]);
+
+    let arch = FELIX::new();
+    let arch = ArchitectureMeta {
+        copy_graph: CopyGraph::build(&arch, &XorCost),
+        arch,
+    };
+    let result = compile(CompilationParameters {
+        disjunct_input_output: true,
+        ..compilation_parameters(
+            Rc::new(arch),
+            XorCost,
+            vec![
+                Cell::new(FELIXCellType::D, 0),
+                Cell::new(FELIXCellType::D, 1),
+            ],
+            ntk,
+        )
+    })
+    .expect("compilation should succeed");
+    println!("{}", result.program);
+
+    let breakdown = result.program.cost_breakdown(&XorCost);
+    assert_eq!(breakdown.total, XorCost.program_cost(&result.program));
+    assert_eq!(breakdown.max_op, OrderedFloat(1.5));
+    assert_eq!(breakdown.min_op, OrderedFloat(1.0));
+    let num_instr = result.program.instructions().count() as f64;
+    assert_eq!(breakdown.mean, breakdown.total / OrderedFloat(num_instr));
+}
+
+#[test]
+fn test_cost_arithmetic() {
+    assert_eq!(Cost::ZERO.checked_add(OrderedFloat(1.5)), OrderedFloat(1.5));
+    let infinite = Cost::INFINITY.checked_add(OrderedFloat(1.0));
+    assert_eq!(infinite, Cost::INFINITY);
+    assert_eq!(infinite.checked_add(Cost::INFINITY), Cost::INFINITY);
+    assert!(OrderedFloat(f64::MAX) < infinite);
+    assert_eq!(
+        [Cost::INFINITY, OrderedFloat(2.0), Cost::ZERO].iter().min(),
+        Some(&Cost::ZERO)
+    );
+}
+
+#[test]
+#[cfg(debug_assertions)]
+#[should_panic(expected = "NaN")]
+fn test_cost_nan() {
+    Cost::ZERO.checked_add(OrderedFloat(f64::NAN));
+}
+
+#[test]
+fn test_declared_costs() {
+    assert_eq!(FELIX::instruction_cost(FELIX::XOR_INSTRUCTION_ID), 1.5);
+    assert_eq!(FELIX::instruction_cost(FELIX::MIN_INSTRUCTION_ID), 1.0);
+
+    let arch = FELIX::new();
+    let cost = DeclaredCosts::<FELIX>::default();
+    for typ in arch.instructions().iter() {
+        let instruction = Instruction::<FELIXCellType> {
+            typ: typ.clone(),
+            inputs: vec![],
+            outputs: vec![],
+        };
+        assert_eq!(cost.cost(&instruction), XorCost.cost(&instruction));
+    }
+}
+
+#[test]
+fn test_ambit_cost() {
+    let arch = Ambit::new();
+    let rc = arch.instructions().by_id(Ambit::RC_INSTRUCTION_ID);
+    let d = |idx| Cell::new(AmbitCellType::D, idx);
+    let mut instruction = Instruction {
+        typ: rc.clone(),
+        inputs: vec![d(0)],
+        outputs: vec![],
+    };
+    assert_eq!(
+        AmbitCost.operation_cost(&instruction),
+        OrderedFloat(2.0 / 3.0)
+    );
+    instruction.outputs.push(Operand {
+        cell: d(1),
+        inverted: false,
+    });
+    instruction.outputs.push(Operand {
+        cell: d(2),
+        inverted: false,
+    });
+    assert_eq!(AmbitCost.operation_cost(&instruction), OrderedFloat(1.0));
+}
+
+/// Costs depending on the operands of an instruction, counting how often it was queried.
+#[derive(Clone, Default)]
+struct OperandCost(Rc<std::cell::Cell<usize>>);
+
+impl OperationCost<FELIXCellType> for OperandCost {
+    fn cost<I: Into<CellOrVar<FELIXCellType>>>(
+        &self,
+        instruction: &Instruction<I, FELIXCellType>,
+    ) -> Cost {
+        OrderedFloat(instruction.typ.id as f64)
+    }
+
+    fn instruction_cost(&self, instruction: &Instruction<FELIXCellType>) -> Cost {
+        self.0.set(self.0.get() + 1);
+        let indices: u32 = instruction.inputs.iter().map(|cell| cell.index()).sum();
+        let inverted = instruction.outputs.iter().filter(|op| op.inverted).count();
+        self.cost(instruction) + OrderedFloat(indices as f64 / 2.0 + inverted as f64)
+    }
+}
+
+#[test]
+fn test_caching_cost() {
+    let arch = FELIX::new();
+    let calls = OperandCost::default();
+    let cost = CachingCost::new(calls.clone());
+    let d = |idx| Cell::new(FELIXCellType::D, idx);
+    let mut distinct = 0;
+    for typ in arch.instructions().iter() {
+        for inputs in [vec![d(0), d(1)], vec![d(1), d(2)]] {
+            for inverted in [false, true] {
+                let instruction = Instruction::<FELIXCellType> {
+                    typ: typ.clone(),
+                    inputs: inputs.clone(),
+                    outputs: vec![Operand {
+                        cell: d(3),
+                        inverted,
+                    }],
+                };
+                distinct += 1;
+                let expected = OperandCost::default().instruction_cost(&instruction);
+                for _ in 0..3 {
+                    assert_eq!(cost.instruction_cost(&instruction), expected);
+                    assert_eq!(cost.clone().instruction_cost(&instruction), expected);
+                }
+            }
+        }
+    }
+    assert_eq!(calls.0.get(), distinct);
+}
+
+define_generic_architecture! {
+    Relay {
+        cells ([S; 2], [D]),
+        operands (
+            D_ANY = [(D)],
+            S0 = [(S[0])],
+            S1 = [(S[1])],
+        ),
+        instructions (
+            LOAD = (and(D_ANY) -> (S0)),
+            MOVE = (and(S0) -> (S1)),
+            STORE = (and(S1) -> (D_ANY)),
+            BYPASS = (and(S0) -> (D_ANY)),
+        ),
+    }
+}
+
+/// Makes the direct path from `S[0]` to `D` more expensive than the detour via `S[1]`.
+#[derive(Clone)]
+struct BypassCost;
+
+impl OperationCost<RelayCellType> for BypassCost {
+    fn cost<I: Into<CellOrVar<RelayCellType>>>(
+        &self,
+        instruction: &Instruction<I, RelayCellType>,
+    ) -> Cost {
+        if instruction.typ.name == "BYPASS" {
+            OrderedFloat(10.0)
+        } else {
+            OrderedFloat(1.0)
+        }
+    }
+}
+
+#[test]
+fn test_copy_graph_all_paths() {
+    let arch = Relay::new();
+    let graph = CopyGraph::build(&arch, &EqualCosts);
+    let from = CellPat::Cell(Cell::new(RelayCellType::D, 0));
+    let to = CellPat::Type(RelayCellType::D);
+    let path_names = |max_len| {
+        graph
+            .all_paths(from, to, false, max_len)
+            .into_iter()
+            .map(|path| {
+                path.into_iter()
+                    .flat_map(|(_, edge)| edge.template.iter().map(|instr| instr.typ.name.clone()))
+                    .collect_vec()
+            })
+            .sorted()
+            .collect_vec()
+    };
+    assert_eq!(
+        path_names(3),
+        [vec!["LOAD", "BYPASS"], vec!["LOAD", "MOVE", "STORE"]]
+    );
+    assert_eq!(path_names(2), [vec!["LOAD", "BYPASS"]]);
+    assert!(graph.all_paths(from, to, true, 3).is_empty());
+}
+
+#[test]
+fn test_copy_cost_max_hops() {
+    let arch = Relay::new();
+    let graph = CopyGraph::build(&arch, &BypassCost);
+    let from = Cell::new(RelayCellType::D, 0);
+    let to = CellPat::Type(RelayCellType::D);
+    let forbidden = FxHashSet::from_iter([from]);
+    let cost = |max_hops| copy_cost(&graph, from, to, false, &forbidden, max_hops);
+
+    // the cheapest path is LOAD, MOVE, STORE, the only shorter one is LOAD, BYPASS
+    let optimal = cost(None).expect("D[0] should be copyable");
+    assert_eq!(cost(Some(3)), Some(optimal));
+    let limited = cost(Some(2)).expect("LOAD, BYPASS should be within the limit");
+    assert!(limited > optimal);
+    assert_eq!(cost(Some(1)), None);
+}
+
+define_generic_architecture! {
+    FarConstants {
+        cells ([S; 2], [D]),
+        operands (
+            CONSTANT = [(bool)],
+            S_ANY = [(S)],
+            D_ANY = [(D)],
+            S_PAIR = [(S[0], S[1])],
+        ),
+        instructions (
+            AND = ([0..] := and(S_PAIR)),
+            LOAD_CONSTANT = (and(CONSTANT) -> (S_ANY)),
+            LOAD = (and(D_ANY) -> (S_ANY)),
+            STORE = (and(S_ANY) -> (D_ANY)),
+        ),
+    }
+}
+
+/// Makes reading the constant cells expensive.
+#[derive(Clone)]
+struct FarConstantCost;
+
+impl OperationCost<FarConstantsCellType> for FarConstantCost {
+    fn cost<I: Into<CellOrVar<FarConstantsCellType>>>(
+        &self,
+        instruction: &Instruction<I, FarConstantsCellType>,
+    ) -> Cost {
+        if instruction.typ.id == FarConstants::LOAD_CONSTANT_INSTRUCTION_ID {
+            OrderedFloat(10.0)
+        } else {
+            OrderedFloat(1.0)
+        }
+    }
+}
+
+#[test]
+fn test_prematerialize_constants() {
+    // every gate reads the constant true, which is consumed by the AND instruction
+    let network = || {
+        let mut ntk = Network::default();
+        let f = Signal::new(ntk.add(Node::False), false);
+        let outputs = (0..3)
+            .map(|i| {
+                let input = Signal::new(ntk.add(Node::Input(i)), false);
+                let gate = UntypedNetwork::And(vec![input, !f]);
+                Signal::new(ntk.add(Node::Gate(gate)), false)
+            })
+            .collect();
+        ntk.set_outputs(outputs);
+        ntk
+    };
+
+    let arch = FarConstants::new();
+    let arch = Rc::new(ArchitectureMeta {
+        copy_graph: CopyGraph::build(&arch, &FarConstantCost),
+        arch,
+    });
+    let compile_with = |prematerialize_constants| {
+        compile(CompilationParameters {
+            prematerialize_constants,
+            ..compilation_parameters(
+                arch.clone(),
+                FarConstantCost,
+                (0..3)
+                    .map(|i| Cell::new(FarConstantsCellType::D, i))
+                    .collect(),
+                network(),
+            )
+        })
+        .expect("compilation should succeed")
+        .program
+    };
+    let direct = compile_with(false);
+    let materialized = compile_with(true);
+    assert!(matches!(
+        materialized.0.first(),
+        Some(Operation::Copy { from, .. }) if from.typ() == FarConstantsCellType::CONSTANT
+    ));
+    assert!(FarConstantCost.program_cost(&materialized) < FarConstantCost.program_cost(&direct));
+}
+
+#[test]
+fn test_repair_copy() {
+    let arch = Relay::new();
+    let arch = ArchitectureMeta {
+        copy_graph: CopyGraph::build(&arch, &BypassCost),
+        arch,
+    };
+    let mut ntk = Network::<UntypedNetwork>::default();
+    let input = Signal::new(ntk.add(Node::Input(0)), false);
+    ntk.set_outputs(vec![input]);
+    let d0 = Cell::new(RelayCellType::D, 0);
+    let params = Rc::new(compilation_parameters(
+        Rc::new(arch),
+        BypassCost,
+        vec![d0],
+        ntk,
+    ));
+    let mut state = State::initialize(&params);
+    let mut savepoint = state.savepoint();
+    let mut version = DummyProgramVersion::new(&mut savepoint, &params);
+
+    // the cheapest path D[0] -> S[0] -> S[1] -> D becomes blocked once S[1] is in use
+    let to = CellPat::Type(RelayCellType::D);
+    let (_, path) = copy_cost_with_path(
+        &params.arch.copy_graph,
+        d0,
+        to,
+        false,
+        &FxHashSet::from_iter([d0]),
+        None,
+    )
+    .expect("D[0] should be copyable");
+    let forbidden = FxHashSet::from_iter([d0, Cell::new(RelayCellType::S, 1)]);
+    let partial = perform_copy(path, &mut version, d0, to, &forbidden)
+        .expect_err("S[1] should block the copy");
+    assert_eq!(partial.reached, Cell::new(RelayCellType::S, 0));
+
+    let cell = repair_copy(partial, &mut version, to, &forbidden)
+        .expect("the copy should continue from S[0]");
+    assert_eq!(cell.typ(), RelayCellType::D);
+    assert_ne!(cell, d0);
+    assert_eq!(version.state().cell(cell), Some(input));
+    // LOAD is reused from the blocked path, only BYPASS is added
+    let ops = &version.program().0;
+    assert_eq!(ops.len(), 2);
+    assert!(matches!(
+        ops[1],
+        Operation::Copy { from, to, .. } if from == partial.reached && to == cell
+    ));
+
+    // a copy blocked by a whole cell type can not be rerouted and fails without changes
+    let blocked = PartialCopy {
+        reached: d0,
+        blocked: CellPat::Type(RelayCellType::S),
+        invert: false,
+    };
+    let result = repair_copy(blocked, &mut version, to, &forbidden);
+    assert!(matches!(result, Err(PartialCopy { reached, .. }) if reached == d0));
+    assert_eq!(version.program().0.len(), 2);
+}
+
+define_generic_architecture! {
+    Banks {
+        cells ([A], [B]),
+        operands (
+            A_ANY = [(A)],
+            B_ANY = [(B)],
+            A_TRIPLE = [(A, A, A)],
+            B_PAIR = [(B, B)],
+        ),
+        instructions (
+            MAJ_A = (maj(A_TRIPLE) -> (A_ANY)),
+            COPY_A = (and(A_ANY) -> (A_ANY)),
+            AND_B = (and(B_PAIR) -> (B_ANY)),
+            COPY_B = (and(B_ANY) -> (B_ANY)),
+            A_TO_B = (and(A_ANY) -> (B_ANY)),
+            B_TO_A = (and(B_ANY) -> (A_ANY)),
+        ),
+    }
+}
+
+fn banks_instructions(names: &[&str]) -> InstructionTypes<BanksCellType> {
+    InstructionTypes::new(
+        Banks::instructions()
+            .iter()
+            .filter(|typ| names.contains(&&*typ.name))
+            .enumerate()
+            .map(|(id, typ)| InstructionType {
+                id: id as u8,
+                ..typ.clone()
+            })
+            .collect(),
+    )
+}
+
+#[test]
+fn test_compose() {
+    let arch = Architecture::new(banks_instructions(&["MAJ_A", "COPY_A"])).compose(
+        Architecture::new(banks_instructions(&["AND_B", "COPY_B"])),
+        banks_instructions(&["A_TO_B", "B_TO_A"]),
+    );
+    assert_eq!(arch.instructions().len(), 6);
+    for (id, typ) in arch.instructions().iter().enumerate() {
+        assert_eq!(typ.id as usize, id);
+    }
+
+    // the inputs are stored in bank B, but only bank A can compute a majority
+    let mut ntk = Network::default();
+    let inputs = (0..3)
+        .map(|i| Signal::new(ntk.add(Node::Input(i)), false))
+        .collect();
+    let maj = Signal::new(ntk.add(Node::Gate(UntypedNetwork::Maj(inputs))), false);
+    ntk.set_outputs(vec![maj]);
+
+    let arch = ArchitectureMeta {
+        copy_graph: CopyGraph::build(&arch, &EqualCosts),
+        arch,
+    };
+    let result = compile(compilation_parameters(
+        Rc::new(arch),
+        EqualCosts,
+        (0..3).map(|i| Cell::new(BanksCellType::B, i)).collect(),
+        ntk,
+    ))
+    .expect("compilation should succeed");
+    println!("{}", result.program);
+    assert!(
+        result
+            .program
+            .instructions()
+            .any(|instr| instr.typ.name == "B_TO_A")
+    );
+}
+
+#[test]
+fn test_architecture_hash() {
+    fn hash(arch: &Architecture<AmbitCellType>) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        arch.hash(&mut hasher);
+        hasher.finish()
+    }
+    assert!(Ambit::new() == Ambit::new());
+    assert_eq!(hash(&Ambit::new()), hash(&Ambit::new()));
+
+    // same instruction names, but the first instruction computes the inverted function
+    let mut instructions = Ambit::new().instructions().iter().cloned().collect_vec();
+    instructions[0].function.inverted ^= true;
+    let modified = Architecture::new(InstructionTypes::new(instructions));
+    assert!(modified != Ambit::new());
+    assert_ne!(hash(&modified), hash(&Ambit::new()));
+}
+
+#[test]
+fn test_all_cells() {
+    let ambit = Ambit::new();
+    let cells = ambit.all_cells().collect_vec();
+    // two constants, four T and two DCC cells, the D cells are unbounded
+    assert_eq!(cells.len(), 8);
+    assert_eq!(cells.iter().unique().count(), cells.len());
+    assert!(cells.iter().all(|cell| cell.typ() != AmbitCellType::D));
+    assert_eq!(
+        cells
+            .iter()
+            .filter(|cell| cell.typ() == AmbitCellType::T)
+            .count(),
+        4
+    );
+}
+
+define_generic_architecture! {
+    Sink {
+        cells ([D], [X]),
+        operands (
+            D_ANY = [(D)],
+            X_ANY = [(X)],
+        ),
+        instructions (
+            NOT = (!and(D_ANY) -> (D_ANY)),
+            // X cells are never read, hence the result of SINK is lost
+            SINK = (and(D_ANY) -> (X_ANY)),
+        ),
+    }
+}
+
+#[test]
+fn test_display_architecture() {
+    let ambit = Ambit::new();
+    let displayed = ambit.to_string();
+    println!("{displayed}");
+    for instruction in ambit.instructions().iter() {
+        assert!(displayed.contains(&format!("{}: ", instruction.name)));
+    }
+    assert!(displayed.contains("[T; 4]"));
+    assert!(displayed.contains("[DCC; 2]"));
+    assert!(displayed.contains("[D]"));
+    // TRA overrides its inputs, RC_INV inverts them
+    assert!(displayed.contains("TRA: maj((T[0], T[1], T[2]) | "));
+    assert!(displayed.contains("; overrides inputs all"));
+    assert!(displayed.contains("RC_INV: and((DCC)) -> "));
+}
+
+#[test]
+fn test_instructions_by_gate() {
+    let instructions = Ambit::instructions();
+    let tra = instructions
+        .iter()
+        .find(|instr| instr.name == "TRA")
+        .expect("Ambit should have a TRA instruction");
+    assert_eq!(instructions.ids_by_gate(GateFunction::Maj), &[tra.id]);
+    assert!(instructions.ids_by_gate(GateFunction::Xor).is_empty());
+}
+
+#[test]
+fn test_check_instruction_reachability() {
+    let arch = Sink::new();
+    let arch = ArchitectureMeta {
+        copy_graph: CopyGraph::build(&arch, &EqualCosts),
+        arch,
+    };
+    assert_eq!(
+        arch.check_instruction_reachability(),
+        vec![UnreadableOutput {
+            instruction: "SINK".into(),
+            cell_type: SinkCellType::X,
+        }]
+    );
+
+    let arch = Ambit::new();
+    let arch = ArchitectureMeta {
+        copy_graph: CopyGraph::build(&arch, &EqualCosts),
+        arch,
+    };
+    assert!(arch.check_instruction_reachability().is_empty());
+}
+
+define_generic_architecture! {
+    Stranded {
+        cells ([D], [U]),
+        operands (
+            D_ANY = [(D)],
+            D_PAIR = [(D, D)],
+            U_PAIR = [(U, U)],
+        ),
+        instructions (
+            // U cells are never written, hence the operands of AND_U can never be placed
+            AND_U = (and(U_PAIR) -> (D_ANY)),
+            AND_D = (and(D_PAIR) -> (D_ANY)),
+            NOT = (!and(D_ANY) -> (D_ANY)),
+        ),
+    }
+}
+
+#[test]
+fn test_compile_unplaceable_instruction() {
+    let mut ntk = Network::default();
+    let x = Signal::new(ntk.add(Node::Input(0)), false);
+    let y = Signal::new(ntk.add(Node::Input(1)), false);
+    let and = Signal::new(ntk.add(Node::Gate(UntypedNetwork::And(vec![x, y]))), false);
+    ntk.set_outputs(vec![and]);
+
+    let arch = Stranded::new();
+    let arch = ArchitectureMeta {
+        copy_graph: CopyGraph::build(&arch, &EqualCosts),
+        arch,
+    };
+    let result = compile(CompilationParameters {
+        mode: CompilationMode::Exhaustive,
+        ..compilation_parameters(
+            Rc::new(arch),
+            EqualCosts,
+            vec![
+                Cell::new(StrandedCellType::D, 0),
+                Cell::new(StrandedCellType::D, 1),
+            ],
+            ntk,
+        )
+    })
+    .expect("compilation should succeed");
+    println!("{}", result.program);
+    assert!(
+        result
+            .program
+            .instructions()
+            .all(|instr| instr.typ.name != "AND_U")
+    );
+    assert!(
+        result
+            .program
+            .instructions()
+            .any(|instr| instr.typ.name == "AND_D")
+    );
+}
+
+/// Computes `(x & y) & (y & z)`, optionally inverting `x` and `z` or the second operand of the
+/// last gate.
+fn and_tree(invert_inputs: bool, invert_last: bool) -> Network<UntypedNetwork> {
+    let mut ntk = Network::default();
+    let x = Signal::new(ntk.add(Node::Input(0)), invert_inputs);
+    let y = Signal::new(ntk.add(Node::Input(1)), false);
+    let z = Signal::new(ntk.add(Node::Input(2)), invert_inputs);
+    let n1 = Signal::new(ntk.add(Node::Gate(UntypedNetwork::And(vec![x, y]))), false);
+    let n2 = Signal::new(ntk.add(Node::Gate(UntypedNetwork::And(vec![y, z]))), false);
+    let n3 = Signal::new(
+        ntk.add(Node::Gate(UntypedNetwork::And(vec![n1, n2 ^ invert_last]))),
+        false,
+    );
+    ntk.set_outputs(vec![n3]);
+    ntk
+}
+
+define_generic_architecture! {
+    Cramped {
+        cells ([R; 2], [D]),
+        operands (
+            D_ANY = [(D)],
+            R_ANY = [(R)],
+            R_PAIR = [(R[0], R[1])],
+        ),
+        instructions (
+            LOAD = (and(D_ANY) -> (R_ANY)),
+            STORE = (and(R_ANY) -> (D_ANY)),
+            AND = (and(R_PAIR) -> (R_ANY)),
+        ),
+    }
+}
+
+#[test]
+fn test_spill_warning() {
+    let arch = Cramped::new();
+    let arch = Rc::new(ArchitectureMeta {
+        copy_graph: CopyGraph::build(&arch, &EqualCosts),
+        arch,
+    });
+    let compile_with_fraction = |spill_warning_fraction| {
+        compile(CompilationParameters {
+            spill_warning_fraction,
+            ..compilation_parameters(
+                arch.clone(),
+                EqualCosts,
+                (0..3).map(|i| Cell::new(CrampedCellType::D, i)).collect(),
+                // both operands of the last gate have to be kept while the other one is computed
+                // in the only two compute cells
+                and_tree(false, false),
+            )
+        })
+        .expect("compilation should succeed")
+    };
+    let result = compile_with_fraction(Some(0.0));
+    assert!(
+        result
+            .program
+            .0
+            .iter()
+            .any(|op| matches!(op, Operation::Copy { spill: true, .. }))
+    );
+    assert_eq!(result.warnings.len(), 1);
+    assert!(compile_with_fraction(Some(1.0)).warnings.is_empty());
+    assert!(compile_with_fraction(None).warnings.is_empty());
+}
+
+define_generic_architecture! {
+    SpillBank {
+        cells ([R; 2], [S; 4], [D]),
+        operands (
+            D_ANY = [(D)],
+            R_ANY = [(R)],
+            S_ANY = [(S)],
+            R_PAIR = [(R[0], R[1])],
+        ),
+        instructions (
+            LOAD = (and(D_ANY) -> (R_ANY)),
+            STORE = (and(R_ANY) -> (D_ANY, S_ANY)),
+            RESTORE = (and(S_ANY) -> (R_ANY)),
+            AND = (and(R_PAIR) -> (R_ANY)),
+        ),
+        spill (R = S[2..4]),
+    }
+}
+
+#[test]
+fn test_spill_region() {
+    assert_eq!(
+        SpillBankCellType::R.spill_region(),
+        Some((SpillBankCellType::S, 2..4))
+    );
+    assert_eq!(SpillBankCellType::D.spill_region(), None);
+
+    let arch = SpillBank::new();
+    let result = compile(compilation_parameters(
+        Rc::new(ArchitectureMeta {
+            copy_graph: CopyGraph::build(&arch, &EqualCosts),
+            arch,
+        }),
+        EqualCosts,
+        (0..3).map(|i| Cell::new(SpillBankCellType::D, i)).collect(),
+        and_tree(false, false),
+    ))
+    .expect("compilation should succeed");
+    println!("{}", result.program);
+    let spill_targets = result
+        .program
+        .0
+        .iter()
+        .filter_map(|op| match op {
+            Operation::Copy {
+                to, spill: true, ..
+            } => Some(*to),
+            _ => None,
+        })
+        .collect_vec();
+    assert!(!spill_targets.is_empty());
+    assert!(
+        spill_targets
+            .iter()
+            .all(|cell| cell.typ() == SpillBankCellType::S && (2..4).contains(&cell.index()))
+    );
+}
+
+#[test]
+fn test_warm_start() {
+    let arch = Stranded::new();
+    let arch = Rc::new(ArchitectureMeta {
+        copy_graph: CopyGraph::build(&arch, &EqualCosts),
+        arch,
+    });
+    let params = |network, warm_start| {
+        Rc::new(CompilationParameters {
+            warm_start,
+            ..compilation_parameters(
+                arch.clone(),
+                EqualCosts,
+                (0..3).map(|i| Cell::new(StrandedCellType::D, i)).collect(),
+                network,
+            )
+        })
+    };
+    let previous = compile(params(and_tree(false, false), None))
+        .expect("compilation should succeed")
+        .program;
+    println!("{previous}");
+
+    // only the last gate changed, hence the first two gates are reused
+    let perturbed = params(and_tree(false, true), Some(previous.clone()));
+    {
+        let mut state = State::initialize(&perturbed);
+        let mut savepoint = state.savepoint();
+        assert_eq!(replay_warm_start(&mut savepoint, &perturbed), 2);
+        assert_eq!(savepoint.candidates().len(), 1);
+    }
+    let result = compile(perturbed).expect("compilation should succeed");
+    println!("{}", result.program);
+    for (op, previous_op) in result.program.0.iter().zip(&previous.0).take(2) {
+        assert_eq!(op.to_string(), previous_op.to_string());
+    }
+
+    // both gates computed first changed, hence nothing is reused
+    let mismatched = params(and_tree(true, false), Some(previous));
+    let mut state = State::initialize(&mismatched);
+    let mut savepoint = state.savepoint();
+    assert_eq!(replay_warm_start(&mut savepoint, &mismatched), 0);
+    assert!(savepoint.program().0.is_empty());
+}
+
+#[test]
+fn test_seeded_rng() {
+    let ambit = Ambit::new();
+    let arch = Rc::new(ArchitectureMeta {
+        copy_graph: CopyGraph::build(&ambit, &EqualCosts),
+        arch: ambit,
+    });
+    // independent gates that can be computed in any order
+    let source = "(maj i0 i1 i2) (maj i3 i4 i5) (maj i6 i7 i8) (maj i9 i10 i11)";
+    let compile_with_seed = |seed| {
+        let network = parse_network(source).unwrap();
+        compile(CompilationParameters {
+            candidate_selection: CandidateSelection::Random,
+            rng: CompilerRng::new(seed),
+            ..compilation_parameters(
+                arch.clone(),
+                EqualCosts,
+                crate::get_input_cells(&arch, &network),
+                network,
+            )
+        })
+        .unwrap()
+        .program
+        .to_string()
+    };
+    let program = compile_with_seed(42);
+    assert_eq!(program, compile_with_seed(42));
+    assert!((0..16).any(|seed| compile_with_seed(seed) != program));
 }
@@ -1,13 +1,16 @@
-use std::iter::once;
+use std::{
+    fmt::{self, Display, Formatter},
+    iter::{Peekable, once},
+};
 
 use eggmock::{
-    FFIGate, GateFunction, ReceiveFrom, Receiver, Signal, define_network,
+    FFIGate, GateFunction, Network, Node, ReceiveFrom, Receiver, Signal, define_network,
     egg::{Analysis, ENodeOrVar, Id, Pattern, RecExpr, Rewrite, Var, rewrite},
 };
 use either::Either;
 use itertools::Itertools;
-use lime_generic_def::{Architecture, CellType};
-use rustc_hash::FxHashSet;
+use lime_generic_def::{Architecture, CellType, GATE_CONVERSIONS};
+use rustc_hash::{FxHashMap, FxHashSet};
 use tracing::warn;
 
 define_network! {
@@ -39,6 +42,142 @@ impl ReceiveFrom<FFIGate> for UntypedNetwork {
     }
 }
 
+/// Error returned by [`parse_network`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError(pub String);
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Parses a network from S-expressions using the operators of [`UntypedNetworkLanguage`], e.g.
+/// `(maj i0 i1 (! f))`. `iN` denotes the `N`-th input, `f` denotes false and `!` inverts its
+/// argument. Every top-level expression becomes an output of the network. Majorities have to have
+/// an odd number of at least three arguments.
+pub fn parse_network(s: &str) -> Result<Network<UntypedNetwork>, ParseError> {
+    let s = s.replace('(', " ( ").replace(')', " ) ");
+    let mut tokens = s.split_whitespace().peekable();
+    let mut parser = NetworkParser::default();
+    let mut outputs = Vec::new();
+    while tokens.peek().is_some() {
+        outputs.push(parser.parse_expr(&mut tokens)?);
+    }
+    if outputs.is_empty() {
+        return Err(ParseError("expected at least one expression".into()));
+    }
+    parser.ntk.set_outputs(outputs);
+    Ok(parser.ntk)
+}
+
+/// Formats the network in the format read by [`parse_network`], i.e. one S-expression per output.
+/// Nodes shared by multiple expressions are repeated in each of them.
+pub fn format_network(ntk: &Network<UntypedNetwork>) -> String {
+    fn format_signal(ntk: &Network<UntypedNetwork>, signal: Signal, out: &mut String) {
+        if signal.is_inverted() {
+            out.push_str("(! ");
+        }
+        let node = ntk.node(signal.node_id());
+        match node {
+            Node::False => out.push('f'),
+            Node::Input(idx) => out.push_str(&format!("i{idx}")),
+            Node::Gate(gate) => {
+                let op = match gate {
+                    UntypedNetwork::And(_) => "and",
+                    UntypedNetwork::Xor(_) => "xor",
+                    UntypedNetwork::Maj(_) => "maj",
+                };
+                out.push('(');
+                out.push_str(op);
+                for input in node.inputs() {
+                    out.push(' ');
+                    format_signal(ntk, *input, out);
+                }
+                out.push(')');
+            }
+        }
+        if signal.is_inverted() {
+            out.push(')');
+        }
+    }
+    let mut out = String::new();
+    for output in ntk.outputs() {
+        format_signal(ntk, *output, &mut out);
+        out.push('\n');
+    }
+    out
+}
+
+#[derive(Default)]
+struct NetworkParser {
+    ntk: Network<UntypedNetwork>,
+    inputs: FxHashMap<u32, Signal>,
+    false_signal: Option<Signal>,
+}
+
+impl NetworkParser {
+    fn parse_expr<'s>(
+        &mut self,
+        tokens: &mut Peekable<impl Iterator<Item = &'s str>>,
+    ) -> Result<Signal, ParseError> {
+        match tokens.next() {
+            None => Err(ParseError("unexpected end of input".into())),
+            Some(")") => Err(ParseError("unexpected `)`".into())),
+            Some("(") => {
+                let op = tokens
+                    .next()
+                    .ok_or_else(|| ParseError("expected operator after `(`".into()))?;
+                let mut args = Vec::new();
+                loop {
+                    match tokens.peek() {
+                        None => return Err(ParseError(format!("unclosed `({op}`"))),
+                        Some(&")") => break,
+                        Some(_) => args.push(self.parse_expr(tokens)?),
+                    }
+                }
+                tokens.next();
+                if op == "!" {
+                    let [arg] = args.as_slice() else {
+                        return Err(ParseError("`!` expects exactly one argument".into()));
+                    };
+                    return Ok(!*arg);
+                }
+                if args.is_empty() {
+                    return Err(ParseError(format!("`{op}` expects arguments")));
+                }
+                // a majority is only defined for an odd number of inputs
+                if op == "maj" && (args.len() < 3 || args.len().is_multiple_of(2)) {
+                    return Err(ParseError(format!(
+                        "`maj` expects an odd number of at least 3 arguments, got {}",
+                        args.len()
+                    )));
+                }
+                let gate = match op {
+                    "and" => UntypedNetwork::And(args),
+                    "xor" => UntypedNetwork::Xor(args),
+                    "maj" => UntypedNetwork::Maj(args),
+                    _ => return Err(ParseError(format!("unknown operator `{op}`"))),
+                };
+                Ok(Signal::new(self.ntk.add(Node::Gate(gate)), false))
+            }
+            Some("f") => Ok(*self
+                .false_signal
+                .get_or_insert_with(|| Signal::new(self.ntk.add(Node::False), false))),
+            Some(symbol) => {
+                let idx = symbol
+                    .strip_prefix('i')
+                    .and_then(|idx| idx.parse().ok())
+                    .ok_or_else(|| ParseError(format!("unknown symbol `{symbol}`")))?;
+                Ok(*self
+                    .inputs
+                    .entry(idx)
+                    .or_insert_with(|| Signal::new(self.ntk.add(Node::Input(idx)), false)))
+            }
+        }
+    }
+}
+
 pub fn create_rewrites<N: Analysis<UntypedNetworkLanguage>, CT: CellType>(
     architecture: &Architecture<CT>,
 ) -> Vec<Rewrite<UntypedNetworkLanguage, N>> {
@@ -118,24 +257,15 @@ pub fn create_rewrites<N: Analysis<UntypedNetworkLanguage>, CT: CellType>(
         rewrites.extend(
             rewrite!("and-xor-dist"; "(and ?x (xor ?y ?z))" <=> "(xor (and ?x ?y) (and ?x ?z))"),
         );
-        rewrites.extend(rewrite!("and-xor-conv"; "
-                (and
-                    (! (and ?x (! ?y)))
-                    (! (and (! ?x) ?y))
-                )" <=> "(! (xor ?x ?y))"));
     }
 
-    if gates.contains(&And) && gates.contains(&Maj) {
-        rewrites.extend(rewrite!("maj-and-conv1"; "(maj ?a ?b f)" <=> "(and ?a ?b)"));
-        rewrites.extend(rewrite!("maj-and-conv2"; "(! (maj ?x ?y ?z))" <=> r#"
-            (and
-                (and
-                    (! (and ?x ?y))
-                    (! (and ?y ?z))
-                )
-                (! (and ?x ?z))
-            )
-        "#));
+    // conversions between gates are also required if only one of them is computed by an
+    // instruction, otherwise networks containing the other gate could not be compiled
+    let synthesizable = architecture.synthesizable_gates();
+    for (gate, via) in GATE_CONVERSIONS {
+        if via.iter().all(|via| synthesizable.contains(via)) {
+            rewrites.extend(conversion_rewrites(*gate));
+        }
     }
 
     // folding of "base" gates
@@ -146,6 +276,30 @@ pub fn create_rewrites<N: Analysis<UntypedNetworkLanguage>, CT: CellType>(
     rewrites
 }
 
+/// Returns the rewrites expressing `gate` by the gate functions it is converted into according to
+/// [`GATE_CONVERSIONS`].
+fn conversion_rewrites<N: Analysis<UntypedNetworkLanguage>>(
+    gate: GateFunction,
+) -> Vec<Rewrite<UntypedNetworkLanguage, N>> {
+    match gate {
+        GateFunction::And => rewrite!("maj-and-conv1"; "(maj ?a ?b f)" <=> "(and ?a ?b)"),
+        GateFunction::Maj => rewrite!("maj-and-conv2"; "(! (maj ?x ?y ?z))" <=> r#"
+            (and
+                (and
+                    (! (and ?x ?y))
+                    (! (and ?y ?z))
+                )
+                (! (and ?x ?z))
+            )
+        "#),
+        GateFunction::Xor => rewrite!("and-xor-conv"; "
+                (and
+                    (! (and ?x (! ?y)))
+                    (! (and (! ?x) ?y))
+                )" <=> "(! (xor ?x ?y))"),
+    }
+}
+
 fn add_maj_folds<N: Analysis<UntypedNetworkLanguage>, CT>(
     arch: &Architecture<CT>,
     rewrites: &mut Vec<Rewrite<UntypedNetworkLanguage, N>>,
@@ -181,7 +335,9 @@ fn add_maj_folds<N: Analysis<UntypedNetworkLanguage>, CT>(
         .instructions()
         .iter()
         .filter(|op| {
+            // majorities breaking ties do not compute the majority gates of networks
             op.function.gate.gate_function() == Some(GateFunction::Maj)
+                && op.function.tie_breaker.is_none()
                 && op.arity().is_some_and(|arity| arity > 3)
         })
         .flat_map(|op| op.arity())
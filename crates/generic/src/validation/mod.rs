@@ -1,13 +1,515 @@
-use eggmock::{Network, Node, Signal};
-use lime_generic_def::{Cell, CellType, Gate, set::Set};
-use rustc_hash::FxHashMap;
+use std::{
+    fmt::Write,
+    hash::{Hash, Hasher},
+};
+
+use eggmock::{GateFunction, Id, Network, Node, Signal};
+use itertools::Itertools;
+use lime_generic_def::{Cell, CellType, Function, Gate, Operand, set::Set};
+use rand::{Rng, SeedableRng, rngs::SmallRng};
+use rustc_hash::{FxHashMap, FxHasher};
 
 use crate::{program::state::Program, untyped_ntk::UntypedNetwork};
 
+/// Structural statistics of a network, see [`network_stats`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct NetworkStats {
+    pub inputs: usize,
+    pub outputs: usize,
+    /// number of gates reachable from the outputs
+    pub gates: usize,
+    /// number of gates on the longest path from an input to an output
+    pub depth: usize,
+    pub and_count: usize,
+    pub maj_count: usize,
+    pub xor_count: usize,
+    /// largest number of gates reading the same node
+    pub max_fanout: usize,
+}
+
+pub fn network_stats(ntk: &Network<UntypedNetwork>) -> NetworkStats {
+    let mut stats = NetworkStats {
+        inputs: ntk.inputs().len(),
+        outputs: ntk.outputs().len(),
+        ..Default::default()
+    };
+    // depth of every visited node, determined in post-order to avoid deep recursion
+    let mut depths = FxHashMap::<Id, usize>::default();
+    let mut stack = Vec::from_iter(ntk.outputs().iter().map(|output| (output.node_id(), false)));
+    while let Some((id, expanded)) = stack.pop() {
+        if depths.contains_key(&id) {
+            continue;
+        }
+        let (inputs, count) = match ntk.node(id) {
+            Node::Gate(UntypedNetwork::And(inputs)) => (inputs, &mut stats.and_count),
+            Node::Gate(UntypedNetwork::Maj(inputs)) => (inputs, &mut stats.maj_count),
+            Node::Gate(UntypedNetwork::Xor(inputs)) => (inputs, &mut stats.xor_count),
+            Node::False | Node::Input(_) => {
+                depths.insert(id, 0);
+                continue;
+            }
+        };
+        if !expanded {
+            stack.push((id, true));
+            stack.extend(inputs.iter().map(|input| (input.node_id(), false)));
+            continue;
+        }
+        let depth = inputs
+            .iter()
+            .map(|input| depths[&input.node_id()])
+            .max()
+            .unwrap_or(0);
+        depths.insert(id, depth + 1);
+        *count += 1;
+        stats.gates += 1;
+    }
+    stats.depth = ntk
+        .outputs()
+        .iter()
+        .map(|output| depths[&output.node_id()])
+        .max()
+        .unwrap_or(0);
+    stats.max_fanout = depths
+        .keys()
+        .map(|id| ntk.node_output_ids(*id).len())
+        .max()
+        .unwrap_or(0);
+    stats
+}
+
+/// Returns how many nodes of the network have a given fanout, i.e. are read by that many gates.
+/// Nodes read by no gate, e.g. unused inputs or nodes only read as outputs, have a fanout of `0`.
+pub fn fanout_histogram(ntk: &Network<UntypedNetwork>) -> FxHashMap<usize, usize> {
+    let mut histogram = FxHashMap::default();
+    for id in (0..ntk.size()).map(Id::from_usize) {
+        *histogram.entry(ntk.node_outputs(id).len()).or_default() += 1;
+    }
+    histogram
+}
+
+/// Evaluates the outputs of the network for the given values of its inputs.
+pub fn evaluate(ntk: &Network<UntypedNetwork>, inputs: &[bool]) -> Vec<bool> {
+    // value of every visited node, determined in post-order to avoid deep recursion
+    let mut values = FxHashMap::<Id, bool>::default();
+    let mut stack = Vec::from_iter(ntk.outputs().iter().map(|output| (output.node_id(), false)));
+    while let Some((id, expanded)) = stack.pop() {
+        if values.contains_key(&id) {
+            continue;
+        }
+        let (gate_inputs, function) = match ntk.node(id) {
+            Node::Gate(UntypedNetwork::And(inputs)) => (inputs, GateFunction::And),
+            Node::Gate(UntypedNetwork::Maj(inputs)) => (inputs, GateFunction::Maj),
+            Node::Gate(UntypedNetwork::Xor(inputs)) => (inputs, GateFunction::Xor),
+            Node::False => {
+                values.insert(id, false);
+                continue;
+            }
+            Node::Input(i) => {
+                values.insert(id, inputs[*i as usize]);
+                continue;
+            }
+        };
+        if !expanded {
+            stack.push((id, true));
+            stack.extend(gate_inputs.iter().map(|input| (input.node_id(), false)));
+            continue;
+        }
+        let mut input_values = gate_inputs
+            .iter()
+            .map(|input| values[&input.node_id()] ^ input.is_inverted());
+        let value = match function {
+            GateFunction::And => input_values.all(|v| v),
+            GateFunction::Xor => input_values.fold(false, |acc, v| acc ^ v),
+            GateFunction::Maj => input_values.filter(|v| *v).count() * 2 > gate_inputs.len(),
+        };
+        values.insert(id, value);
+    }
+    ntk.outputs()
+        .iter()
+        .map(|output| values[&output.node_id()] ^ output.is_inverted())
+        .collect()
+}
+
+/// Networks with at most this many inputs are compared on all input assignments by
+/// [`find_divergence`].
+pub const EXHAUSTIVE_COMPARISON_INPUTS: usize = 12;
+
+/// Compares the outputs of two networks over the same inputs and returns an assignment of the
+/// inputs for which they differ, or `None` if none was found. Networks with at most
+/// [`EXHAUSTIVE_COMPARISON_INPUTS`] inputs are compared exhaustively, larger networks on `samples`
+/// pseudo-random assignments, hence `None` does not prove their equivalence.
+pub fn find_divergence(
+    a: &Network<UntypedNetwork>,
+    b: &Network<UntypedNetwork>,
+    samples: usize,
+) -> Option<Vec<bool>> {
+    let num_inputs = a.inputs().len().max(b.inputs().len());
+    let diverges = |inputs: &[bool]| evaluate(a, inputs) != evaluate(b, inputs);
+    if num_inputs <= EXHAUSTIVE_COMPARISON_INPUTS {
+        (0..1u64 << num_inputs)
+            .map(|bits| Vec::from_iter((0..num_inputs).map(|i| (bits >> i) & 1 == 1)))
+            .find(|inputs| diverges(inputs))
+    } else {
+        let mut rng = SmallRng::seed_from_u64(0);
+        (0..samples)
+            .map(|_| Vec::from_iter((0..num_inputs).map(|_| rng.random::<bool>())))
+            .find(|inputs| diverges(inputs))
+    }
+}
+
+/// Returns the ids of the nodes forming a combinational cycle, if the network contains one. Every
+/// node of the returned cycle is an input of its successor and the last node is an input of the
+/// first one.
+pub fn detect_cycle<G: eggmock::Gate>(ntk: &Network<G>) -> Option<Vec<Id>> {
+    // whether a visited node is on the current path, i.e. whether its inputs are being visited
+    let mut active = FxHashMap::<Id, bool>::default();
+    for root in (0..ntk.size()).map(Id::from_usize) {
+        if active.contains_key(&root) {
+            continue;
+        }
+        // path of active nodes, each with the index of its next input to visit
+        let mut path = vec![(root, 0)];
+        active.insert(root, true);
+        while let Some((id, next)) = path.last_mut() {
+            let Some(input) = ntk.node(*id).inputs().get(*next) else {
+                active.insert(*id, false);
+                path.pop();
+                continue;
+            };
+            *next += 1;
+            let input = input.node_id();
+            match active.get(&input) {
+                None => {
+                    active.insert(input, true);
+                    path.push((input, 0));
+                }
+                Some(true) => {
+                    let start = path.iter().position(|(id, _)| *id == input).unwrap();
+                    return Some(path[start..].iter().rev().map(|(id, _)| *id).collect());
+                }
+                Some(false) => {}
+            }
+        }
+    }
+    None
+}
+
+/// Returns a network computing only the output with the given index, i.e. containing only the
+/// nodes reachable from it. All inputs of the network are kept, so that input indices do not
+/// change.
+pub fn extract_cone(ntk: &Network<UntypedNetwork>, output_idx: usize) -> Network<UntypedNetwork> {
+    let mut cone = Network::default();
+    let input_ids =
+        Vec::from_iter((0..ntk.inputs().len()).map(|i| cone.add(Node::Input(i as u32))));
+    let output = ntk.outputs()[output_idx];
+    // ids of the copied nodes, determined in post-order to avoid deep recursion
+    let mut ids = FxHashMap::<Id, Id>::default();
+    let mut stack = vec![(output.node_id(), false)];
+    while let Some((id, expanded)) = stack.pop() {
+        if ids.contains_key(&id) {
+            continue;
+        }
+        let (gate_inputs, gate): (_, fn(Vec<Signal>) -> UntypedNetwork) = match ntk.node(id) {
+            Node::Gate(UntypedNetwork::And(inputs)) => (inputs, UntypedNetwork::And),
+            Node::Gate(UntypedNetwork::Maj(inputs)) => (inputs, UntypedNetwork::Maj),
+            Node::Gate(UntypedNetwork::Xor(inputs)) => (inputs, UntypedNetwork::Xor),
+            Node::False => {
+                ids.insert(id, cone.add(Node::False));
+                continue;
+            }
+            Node::Input(i) => {
+                ids.insert(id, input_ids[*i as usize]);
+                continue;
+            }
+        };
+        if !expanded {
+            stack.push((id, true));
+            stack.extend(gate_inputs.iter().map(|input| (input.node_id(), false)));
+            continue;
+        }
+        let gate_inputs = gate_inputs
+            .iter()
+            .map(|input| Signal::new(ids[&input.node_id()], input.is_inverted()))
+            .collect();
+        ids.insert(id, cone.add(Node::Gate(gate(gate_inputs))));
+    }
+    cone.set_outputs(vec![Signal::new(
+        ids[&output.node_id()],
+        output.is_inverted(),
+    )]);
+    cone
+}
+
+/// Combines networks over the same inputs into a single network computing the outputs of all of
+/// them in order. Structurally identical gates of the networks are merged, see [`canonicalize`].
+pub fn merge_networks(networks: &[Network<UntypedNetwork>]) -> Network<UntypedNetwork> {
+    let num_inputs = networks
+        .iter()
+        .flat_map(|ntk| (0..ntk.size()).map(|id| ntk.node(Id::from_usize(id))))
+        .filter_map(|node| match node {
+            Node::Input(i) => Some(*i as usize + 1),
+            _ => None,
+        })
+        .chain(networks.iter().map(|ntk| ntk.inputs().len()))
+        .max()
+        .unwrap_or(0);
+    let mut merged = Network::default();
+    let input_ids = Vec::from_iter((0..num_inputs).map(|i| merged.add(Node::Input(i as u32))));
+    let mut false_id = None;
+    let mut outputs = Vec::new();
+    for ntk in networks {
+        // ids of the copied nodes, determined in post-order to avoid deep recursion
+        let mut ids = FxHashMap::<Id, Id>::default();
+        let mut stack =
+            Vec::from_iter(ntk.outputs().iter().map(|output| (output.node_id(), false)));
+        while let Some((id, expanded)) = stack.pop() {
+            if ids.contains_key(&id) {
+                continue;
+            }
+            let (gate_inputs, gate): (_, fn(Vec<Signal>) -> UntypedNetwork) = match ntk.node(id) {
+                Node::Gate(UntypedNetwork::And(inputs)) => (inputs, UntypedNetwork::And),
+                Node::Gate(UntypedNetwork::Maj(inputs)) => (inputs, UntypedNetwork::Maj),
+                Node::Gate(UntypedNetwork::Xor(inputs)) => (inputs, UntypedNetwork::Xor),
+                Node::False => {
+                    let copied = *false_id.get_or_insert_with(|| merged.add(Node::False));
+                    ids.insert(id, copied);
+                    continue;
+                }
+                Node::Input(i) => {
+                    ids.insert(id, input_ids[*i as usize]);
+                    continue;
+                }
+            };
+            if !expanded {
+                stack.push((id, true));
+                stack.extend(gate_inputs.iter().map(|input| (input.node_id(), false)));
+                continue;
+            }
+            let gate_inputs = gate_inputs
+                .iter()
+                .map(|input| Signal::new(ids[&input.node_id()], input.is_inverted()))
+                .collect();
+            ids.insert(id, merged.add(Node::Gate(gate(gate_inputs))));
+        }
+        outputs.extend(
+            ntk.outputs()
+                .iter()
+                .map(|output| Signal::new(ids[&output.node_id()], output.is_inverted())),
+        );
+    }
+    merged.set_outputs(outputs);
+    canonicalize(&merged)
+}
+
+/// Returns an equivalent network in which the inputs of every gate are sorted into a canonical
+/// order and structurally identical gates are merged. All gates of [`UntypedNetwork`] are
+/// commutative, hence networks differing only in the order of gate inputs canonicalize to the same
+/// network, which keeps the initial egraph built from it small. Only nodes reachable from the
+/// outputs are kept, all inputs are kept so that input indices do not change.
+pub fn canonicalize(ntk: &Network<UntypedNetwork>) -> Network<UntypedNetwork> {
+    fn gate_of(node: &Node<UntypedNetwork>) -> Option<(GateFunction, &[Signal])> {
+        match node {
+            Node::Gate(UntypedNetwork::And(inputs)) => Some((GateFunction::And, inputs)),
+            Node::Gate(UntypedNetwork::Maj(inputs)) => Some((GateFunction::Maj, inputs)),
+            Node::Gate(UntypedNetwork::Xor(inputs)) => Some((GateFunction::Xor, inputs)),
+            Node::False | Node::Input(_) => None,
+        }
+    }
+
+    // structural hashes of the reachable nodes, independent of the order of gate inputs and of the
+    // node ids, determined in post-order to avoid deep recursion
+    let mut hashes = FxHashMap::<Id, u64>::default();
+    let mut stack = Vec::from_iter(ntk.outputs().iter().map(|output| (output.node_id(), false)));
+    while let Some((id, expanded)) = stack.pop() {
+        if hashes.contains_key(&id) {
+            continue;
+        }
+        let mut hasher = FxHasher::default();
+        match gate_of(ntk.node(id)) {
+            None => {
+                match ntk.node(id) {
+                    Node::Input(i) => Some(*i),
+                    _ => None,
+                }
+                .hash(&mut hasher);
+            }
+            Some((_, inputs)) if !expanded => {
+                stack.push((id, true));
+                stack.extend(inputs.iter().map(|input| (input.node_id(), false)));
+                continue;
+            }
+            Some((function, inputs)) => {
+                function.hash(&mut hasher);
+                inputs
+                    .iter()
+                    .map(|input| (hashes[&input.node_id()], input.is_inverted()))
+                    .sorted()
+                    .for_each(|key| key.hash(&mut hasher));
+            }
+        }
+        hashes.insert(id, hasher.finish());
+    }
+    let sort_key = |signal: &Signal| (hashes[&signal.node_id()], signal.is_inverted());
+
+    // copy the nodes, visiting the inputs of every gate in canonical order so that the ids of the
+    // copied nodes do not depend on the original order either
+    let mut canonical = Network::default();
+    let input_ids =
+        Vec::from_iter((0..ntk.inputs().len()).map(|i| canonical.add(Node::Input(i as u32))));
+    let mut false_id = None;
+    let mut gates = FxHashMap::<(GateFunction, Vec<Signal>), Id>::default();
+    let mut ids = FxHashMap::<Id, Id>::default();
+    let mut stack = Vec::from_iter(
+        ntk.outputs()
+            .iter()
+            .rev()
+            .map(|output| (output.node_id(), false)),
+    );
+    while let Some((id, expanded)) = stack.pop() {
+        if ids.contains_key(&id) {
+            continue;
+        }
+        let Some((function, inputs)) = gate_of(ntk.node(id)) else {
+            let copied = match ntk.node(id) {
+                Node::Input(i) => input_ids[*i as usize],
+                _ => *false_id.get_or_insert_with(|| canonical.add(Node::False)),
+            };
+            ids.insert(id, copied);
+            continue;
+        };
+        let mut inputs = inputs.to_vec();
+        inputs.sort_by_key(sort_key);
+        if !expanded {
+            stack.push((id, true));
+            stack.extend(inputs.iter().rev().map(|input| (input.node_id(), false)));
+            continue;
+        }
+        let inputs = inputs
+            .iter()
+            .map(|input| Signal::new(ids[&input.node_id()], input.is_inverted()))
+            .collect_vec();
+        let copied = *gates
+            .entry((function, inputs))
+            .or_insert_with_key(|(_, inputs)| {
+                let inputs = inputs.clone();
+                canonical.add(Node::Gate(match function {
+                    GateFunction::And => UntypedNetwork::And(inputs),
+                    GateFunction::Maj => UntypedNetwork::Maj(inputs),
+                    GateFunction::Xor => UntypedNetwork::Xor(inputs),
+                }))
+            });
+        ids.insert(id, copied);
+    }
+    canonical.set_outputs(
+        ntk.outputs()
+            .iter()
+            .map(|output| Signal::new(ids[&output.node_id()], output.is_inverted()))
+            .collect(),
+    );
+    canonical
+}
+
+/// Exports the network as a BLIF model, e.g. to verify it using ABC. The inputs are named `i0`,
+/// `i1`, ..., the outputs `o0`, `o1`, ... Every gate reachable from the outputs is described by a
+/// `.names` block whose truth table includes the inversions of its inputs.
+pub fn to_blif(ntk: &Network<UntypedNetwork>, model: &str) -> String {
+    let mut blif = format!(".model {model}\n");
+    let inputs = (0..ntk.inputs().len()).map(|i| format!(" i{i}")).join("");
+    let outputs = (0..ntk.outputs().len()).map(|i| format!(" o{i}")).join("");
+    writeln!(blif, ".inputs{inputs}\n.outputs{outputs}").unwrap();
+
+    // names of the visited nodes, determined in post-order to avoid deep recursion
+    let mut names = FxHashMap::<Id, String>::default();
+    let mut stack = Vec::from_iter(ntk.outputs().iter().map(|output| (output.node_id(), false)));
+    while let Some((id, expanded)) = stack.pop() {
+        if names.contains_key(&id) {
+            continue;
+        }
+        let gate = match ntk.node(id) {
+            Node::Gate(gate) => gate,
+            Node::False => {
+                // a `.names` block without rows is constant false
+                writeln!(blif, ".names false").unwrap();
+                names.insert(id, "false".to_string());
+                continue;
+            }
+            Node::Input(i) => {
+                names.insert(id, format!("i{i}"));
+                continue;
+            }
+        };
+        let inputs = ntk.node(id).inputs();
+        if !expanded {
+            stack.push((id, true));
+            stack.extend(inputs.iter().map(|input| (input.node_id(), false)));
+            continue;
+        }
+        let name = format!("n{}", names.len());
+        let input_names = inputs
+            .iter()
+            .map(|input| &names[&input.node_id()])
+            .join(" ");
+        writeln!(blif, ".names {input_names} {name}").unwrap();
+        // column entry requiring the `i`-th input of the gate to have the given value
+        let literal = |i: usize, value: bool| {
+            if value ^ inputs[i].is_inverted() {
+                '1'
+            } else {
+                '0'
+            }
+        };
+        let rows = match gate {
+            UntypedNetwork::And(_) => vec![String::from_iter(
+                (0..inputs.len()).map(|i| literal(i, true)),
+            )],
+            // every majority of true inputs, the remaining inputs do not matter
+            UntypedNetwork::Maj(_) => (0..inputs.len())
+                .combinations(inputs.len() / 2 + 1)
+                .map(|majority| {
+                    String::from_iter((0..inputs.len()).map(|i| {
+                        if majority.contains(&i) {
+                            literal(i, true)
+                        } else {
+                            '-'
+                        }
+                    }))
+                })
+                .collect(),
+            UntypedNetwork::Xor(_) => (0..1usize << inputs.len())
+                .filter(|values| values.count_ones() % 2 == 1)
+                .map(|values| {
+                    String::from_iter((0..inputs.len()).map(|i| literal(i, values & (1 << i) != 0)))
+                })
+                .collect(),
+        };
+        for row in rows {
+            writeln!(blif, "{row} 1").unwrap();
+        }
+        names.insert(id, name);
+    }
+    for (i, output) in ntk.outputs().iter().enumerate() {
+        let literal = if output.is_inverted() { '0' } else { '1' };
+        writeln!(
+            blif,
+            ".names {} o{i}\n{literal} 1",
+            names[&output.node_id()]
+        )
+        .unwrap();
+    }
+    blif.push_str(".end\n");
+    blif
+}
+
+/// Rebuilds the network computed by the program from the given input cells to the given output
+/// operands. Constants are represented by a single `Node::False`, the constant `true` is its
+/// inverted signal. Use [`explicit_constants`] to obtain a network with a distinct node for `true`.
+/// Majorities breaking ties become majorities reading their tie-breaker twice, such that every
+/// majority of the network has an odd arity. Instructions with a secondary function add a node for
+/// each of their functions.
 pub fn rebuild_network<CT: CellType>(
     program: &Program<CT>,
     inputs: &[Cell<CT>],
-    outputs: &[Cell<CT>],
+    outputs: &[Operand<CT>],
 ) -> Result<Network<UntypedNetwork>, String> {
     let mut cells = FxHashMap::default();
     let mut ntk = Network::default();
@@ -22,49 +524,128 @@ pub fn rebuild_network<CT: CellType>(
         instruction
             .validate()
             .map_err(|()| format!("invalid instruction {instruction}"))?;
-        let inputs = instruction.typ.input_range.slice(&instruction.inputs).1;
-        let mut evaluation = instruction.typ.function.evaluate(inputs.len());
-        let mut inputs = Vec::new();
-        let (in_offset, input_cells, _) = instruction.typ.input_range.slice(&instruction.inputs);
-        for (in_idx, input) in input_cells.iter().enumerate() {
-            let sig = cells.get(input);
-            let Some(sig) = sig else {
-                evaluation.add_unknown();
-                continue;
-            };
-            let sig = *sig
-                ^ instruction
-                    .typ
-                    .input_inverted
-                    .contains(&(in_offset + in_idx));
-            inputs.push(sig);
-            let node = ntk.node(sig.node_id());
-            if matches!(node, Node::False) {
-                evaluation.add(sig.is_inverted());
-            } else {
-                evaluation.add_unknown();
+        let apply = |ntk: &mut Network<UntypedNetwork>, function: Function| {
+            let inputs = instruction.typ.input_range.slice(&instruction.inputs).1;
+            let mut evaluation = function.evaluate(inputs.len());
+            let mut inputs = Vec::new();
+            let (in_offset, input_cells, _) =
+                instruction.typ.input_range.slice(&instruction.inputs);
+            for (in_idx, input) in input_cells.iter().enumerate() {
+                let sig = cells.get(input);
+                let Some(sig) = sig else {
+                    evaluation.add_unknown(in_idx);
+                    continue;
+                };
+                let sig = *sig
+                    ^ instruction
+                        .typ
+                        .input_inverted
+                        .contains(&(in_offset + in_idx));
+                inputs.push(sig);
+                let node = ntk.node(sig.node_id());
+                if matches!(node, Node::False) {
+                    evaluation.add(in_idx, sig.is_inverted());
+                } else {
+                    evaluation.add_unknown(in_idx);
+                }
             }
-        }
-        let signal = if let Some(value) = evaluation.evaluate() {
-            f ^ value
-        } else {
-            let node = match instruction.typ.function.gate {
+            if let Some(value) = evaluation.evaluate() {
+                return f ^ value;
+            }
+            let node = match function.gate {
                 Gate::And => UntypedNetwork::And(inputs),
-                Gate::Maj => UntypedNetwork::Maj(inputs),
+                Gate::Maj => UntypedNetwork::Maj(function.untied_inputs(&inputs)),
                 Gate::Xor => UntypedNetwork::Xor(inputs),
                 // evaluation would have a result
                 Gate::Constant(_) => unreachable!(),
             };
-            Signal::new(ntk.add(Node::Gate(node)), instruction.typ.function.inverted)
+            Signal::new(ntk.add(Node::Gate(node)), function.inverted)
         };
+        let signal = apply(&mut ntk, instruction.typ.function);
+        let secondary = instruction
+            .typ
+            .secondary
+            .map(|function| apply(&mut ntk, function));
+        let secondary_output = instruction.secondary_output();
         for op in instruction.write_operands() {
+            let signal = match secondary {
+                Some(secondary) if Some(op) == secondary_output => secondary,
+                _ => signal,
+            };
+            // keep both constant cells consistent, they only differ in their inversion
+            let op = op.normalize_constant();
+            if op.cell == CT::constant(false) {
+                cells.insert(CT::constant(true), !signal ^ op.inverted);
+            }
             cells.insert(op.cell, signal ^ op.inverted);
         }
     }
     let mut output_signals = Vec::new();
     for output in outputs {
-        output_signals.push(*cells.get(output).expect("output cell should be set"));
+        let signal = cells.get(&output.cell).expect("output cell should be set");
+        output_signals.push(*signal ^ output.inverted);
     }
     ntk.set_outputs(output_signals);
     Ok(ntk)
 }
+
+/// Returns an equivalent network without inverted signals of constants, i.e. with distinct nodes
+/// for both constants, for consumers not handling the inverted `Node::False` used as the constant
+/// `true` by [`rebuild_network`]. As there is no node type for `true`, it is represented by an
+/// `And` gate without inputs. All inputs are kept, so that input indices do not change.
+pub fn explicit_constants(ntk: &Network<UntypedNetwork>) -> Network<UntypedNetwork> {
+    let mut explicit = Network::default();
+    let input_ids =
+        Vec::from_iter((0..ntk.inputs().len()).map(|i| explicit.add(Node::Input(i as u32))));
+    // ids of the nodes representing `false` and `true`, added on first use
+    let mut constant_ids = [None, None];
+    // ids of the copied gates, determined in post-order to avoid deep recursion
+    let mut ids = FxHashMap::<Id, Id>::default();
+    let mut copy_signal = |explicit: &mut Network<_>, ids: &FxHashMap<Id, Id>, signal: Signal| {
+        let id = match ntk.node(signal.node_id()) {
+            Node::False => {
+                let value = signal.is_inverted();
+                let id = *constant_ids[value as usize].get_or_insert_with(|| {
+                    explicit.add(if value {
+                        Node::Gate(UntypedNetwork::And(Vec::new()))
+                    } else {
+                        Node::False
+                    })
+                });
+                return Signal::new(id, false);
+            }
+            Node::Input(i) => input_ids[*i as usize],
+            Node::Gate(_) => ids[&signal.node_id()],
+        };
+        Signal::new(id, signal.is_inverted())
+    };
+    let mut stack = Vec::from_iter(ntk.outputs().iter().map(|output| (output.node_id(), false)));
+    while let Some((id, expanded)) = stack.pop() {
+        if ids.contains_key(&id) {
+            continue;
+        }
+        let (gate_inputs, gate): (_, fn(Vec<Signal>) -> UntypedNetwork) = match ntk.node(id) {
+            Node::Gate(UntypedNetwork::And(inputs)) => (inputs, UntypedNetwork::And),
+            Node::Gate(UntypedNetwork::Maj(inputs)) => (inputs, UntypedNetwork::Maj),
+            Node::Gate(UntypedNetwork::Xor(inputs)) => (inputs, UntypedNetwork::Xor),
+            Node::False | Node::Input(_) => continue,
+        };
+        if !expanded {
+            stack.push((id, true));
+            stack.extend(gate_inputs.iter().map(|input| (input.node_id(), false)));
+            continue;
+        }
+        let gate_inputs = gate_inputs
+            .iter()
+            .map(|input| copy_signal(&mut explicit, &ids, *input))
+            .collect();
+        ids.insert(id, explicit.add(Node::Gate(gate(gate_inputs))));
+    }
+    let outputs = ntk
+        .outputs()
+        .iter()
+        .map(|output| copy_signal(&mut explicit, &ids, *output))
+        .collect();
+    explicit.set_outputs(outputs);
+    explicit
+}
@@ -0,0 +1,39 @@
+use std::{
+    fmt,
+    io::{self, Write},
+};
+
+/// Level of diagnostic output of the compiler. Every message is assigned one of the levels below
+/// and only printed if the configured verbosity is at least that level. Warnings and errors are
+/// printed independent of the verbosity.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+#[repr(transparent)]
+pub struct Verbosity(pub u8);
+
+impl Verbosity {
+    /// No output on success.
+    pub const SILENT: Self = Self(0);
+    /// Timings and statistics of the individual phases.
+    pub const SUMMARY: Self = Self(1);
+    /// Additionally the compiled programs.
+    pub const PROGRAM: Self = Self(2);
+    /// Additionally the progress of the individual phases.
+    pub const TRACE: Self = Self(3);
+
+    pub fn enabled(self, level: Self) -> bool {
+        self >= level
+    }
+
+    /// Prints the message to stderr if messages of the given level are enabled.
+    pub fn log(self, level: Self, message: fmt::Arguments) {
+        self.write(&mut io::stderr().lock(), level, message);
+    }
+
+    /// Writes the message as a line to `out` if messages of the given level are enabled.
+    pub fn write(self, out: &mut impl Write, level: Self, message: fmt::Arguments) {
+        if self.enabled(level) {
+            // diagnostics are best effort, failing to print them must not abort the compilation
+            let _ = writeln!(out, "{message}");
+        }
+    }
+}
@@ -0,0 +1,51 @@
+use std::{env, fs, path::PathBuf, process::Command};
+
+fn write_network(name: &str, network: &str) -> PathBuf {
+    let path = env::temp_dir().join(format!("lime-cli-{}-{name}.txt", std::process::id()));
+    fs::write(&path, network).expect("network file should be writable");
+    path
+}
+
+fn lime(args: &[&str]) -> (bool, String, String) {
+    let output = Command::new(env!("CARGO_BIN_EXE_lime"))
+        .args(args)
+        .output()
+        .expect("lime binary should be executable");
+    (
+        output.status.success(),
+        String::from_utf8_lossy(&output.stdout).into_owned(),
+        String::from_utf8_lossy(&output.stderr).into_owned(),
+    )
+}
+
+#[test]
+fn compiles_network_file() {
+    let path = write_network("maj", "(maj i0 i1 i2) (! i0)");
+    let (success, stdout, stderr) = lime(&[
+        "--arch",
+        "ambit",
+        "--input",
+        path.to_str().unwrap(),
+        "--mode",
+        "greedy",
+    ]);
+    fs::remove_file(&path).ok();
+    assert!(success, "lime failed:\n{stderr}");
+    assert!(stdout.contains("=== program:"));
+    assert!(stdout.contains("=== outputs:"));
+    assert!(stdout.contains("cost: "));
+    assert!(stdout.contains("instructions: "));
+}
+
+#[test]
+fn fails_on_invalid_input() {
+    let path = write_network("invalid", "(maj i0 i1");
+    let (success, _, stderr) = lime(&["--arch", "ambit", "--input", path.to_str().unwrap()]);
+    fs::remove_file(&path).ok();
+    assert!(!success);
+    assert!(stderr.contains("cannot parse"));
+
+    let (success, _, stderr) = lime(&["--arch", "unknown", "--input", "missing.txt"]);
+    assert!(!success);
+    assert!(stderr.contains("cannot read"));
+}
@@ -3,12 +3,19 @@ use derive_syn_parse::Parse;
 use lime_generic_def::InputIndices;
 use proc_macro2::Span;
 use syn::{
-    Error, Ident, LitBool, LitInt, Result, Token, Visibility, bracketed,
+    Error, Expr, Ident, LitBool, LitInt, Result, Token, Visibility, bracketed,
     parse::{Parse, ParseStream},
     punctuated::Punctuated,
     token::{Brace, Bracket, Paren},
 };
 
+mod kw {
+    syn::custom_keyword!(cost);
+    syn::custom_keyword!(disjoint_io);
+    syn::custom_keyword!(secondary);
+    syn::custom_keyword!(tie_breaker);
+}
+
 #[derive(Debug, Parse)]
 pub struct Architecture {
     pub vis: Visibility,
@@ -25,6 +32,7 @@ pub struct ArchitectureInner {
     pub cells: Tuple<CellDef>,
     pub operands: Tuple<NameAndOpTuplesDef>,
     pub instructions: Tuple<Instruction>,
+    pub spill: Option<Tuple<SpillRegion>>,
 }
 
 impl Parse for ArchitectureInner {
@@ -32,12 +40,14 @@ impl Parse for ArchitectureInner {
         let mut cells = None;
         let mut operands = None;
         let mut instructions = None;
+        let mut spill = None;
         while !input.is_empty() {
             let ident: Ident = input.parse()?;
             match ident.to_string().as_str() {
                 "cells" => cells = Some(input.parse()?),
                 "operands" => operands = Some(input.parse()?),
                 "instructions" => instructions = Some(input.parse()?),
+                "spill" => spill = Some(input.parse()?),
                 _ => return Err(Error::new(ident.span(), "invalid property")),
             }
             if !input.is_empty() {
@@ -49,6 +59,7 @@ impl Parse for ArchitectureInner {
                 cells,
                 operands,
                 instructions,
+                spill,
             }),
             _ => Err(Error::new(input.span(), "missing property")),
         }
@@ -70,6 +81,17 @@ pub struct CellDef {
     pub num: Option<LitInt>,
 }
 
+/// `<from> = <to>[<range>]` designates the given cells of type `to` for spilling values held by
+/// cells of type `from`
+#[derive(Debug, Parse)]
+pub struct SpillRegion {
+    pub from: Ident,
+    #[expect(unused)]
+    pub eq: Token![=],
+    pub to: Ident,
+    pub range: Range,
+}
+
 #[derive(Debug, Parse)]
 pub struct NameAndOpTuplesDef {
     pub name: Ident,
@@ -167,6 +189,42 @@ pub struct Instruction {
     #[inside(paren)]
     #[parse_if(arrow.is_some())]
     pub output: Option<Tuple<Ident>>,
+    /// `disjoint_io` requires the outputs to be placed in cells distinct from the inputs
+    #[peek(kw::disjoint_io)]
+    pub disjoint_io: Option<kw::disjoint_io>,
+    /// `tie_breaker = <input>` resolves ties of an even-arity majority by the input with this index
+    #[peek(kw::tie_breaker)]
+    pub tie_breaker: Option<InstructionTieBreaker>,
+    /// `secondary = <function>` computes another function of the inputs into the second output
+    #[peek(kw::secondary)]
+    pub secondary: Option<InstructionSecondary>,
+    #[peek(kw::cost)]
+    pub cost: Option<InstructionCost>,
+}
+
+#[derive(Debug, Parse)]
+pub struct InstructionTieBreaker {
+    pub kw: kw::tie_breaker,
+    #[expect(unused)]
+    pub eq: Token![=],
+    pub input: LitInt,
+}
+
+#[derive(Debug, Parse)]
+pub struct InstructionSecondary {
+    pub kw: kw::secondary,
+    #[expect(unused)]
+    pub eq: Token![=],
+    pub function: Function,
+}
+
+#[derive(Debug, Parse)]
+pub struct InstructionCost {
+    #[expect(unused)]
+    pub kw: kw::cost,
+    #[expect(unused)]
+    pub eq: Token![=],
+    pub value: Expr,
 }
 
 #[derive(Debug, Parse)]
@@ -187,9 +245,23 @@ pub struct Function {
 
 #[derive(Debug)]
 pub enum Range {
-    LeftOpen { bracket: Bracket, end: LitInt },
-    RightOpen { bracket: Bracket, start: LitInt },
-    Single { bracket: Bracket, idx: LitInt },
+    LeftOpen {
+        bracket: Bracket,
+        end: LitInt,
+    },
+    RightOpen {
+        bracket: Bracket,
+        start: LitInt,
+    },
+    Single {
+        bracket: Bracket,
+        idx: LitInt,
+    },
+    Bounded {
+        bracket: Bracket,
+        start: LitInt,
+        end: LitInt,
+    },
 }
 
 impl Range {
@@ -198,6 +270,7 @@ impl Range {
             Self::LeftOpen { bracket, .. } => bracket.span.join(),
             Self::RightOpen { bracket, .. } => bracket.span.join(),
             Self::Single { bracket, .. } => bracket.span.join(),
+            Self::Bounded { bracket, .. } => bracket.span.join(),
         }
     }
 }
@@ -212,6 +285,13 @@ impl Parse for Range {
                 return Ok(Range::Single { bracket, idx: int });
             } else if stream.peek(Token![..]) {
                 stream.parse::<Token![..]>()?;
+                if stream.peek(LitInt) {
+                    return Ok(Range::Bounded {
+                        bracket,
+                        start: int,
+                        end: stream.parse()?,
+                    });
+                }
                 return Ok(Range::RightOpen {
                     bracket,
                     start: int,
@@ -222,7 +302,10 @@ impl Parse for Range {
             let int = stream.parse()?;
             return Ok(Range::LeftOpen { bracket, end: int });
         }
-        return Err(Error::new(stream.span(), "expected [..i], [i..] or [i]"));
+        return Err(Error::new(
+            stream.span(),
+            "expected [..i], [i..], [i..j] or [i]",
+        ));
     }
 }
 
@@ -291,6 +374,13 @@ impl TryFrom<&Range> for InputIndices {
                 Ok(InputIndices::None)
             }
             Range::Single { idx, .. } => Ok(InputIndices::Index(idx.base10_parse()?)),
+            Range::Bounded { start, end, .. } => {
+                let (start, end) = (start.base10_parse()?, end.base10_parse()?);
+                if start >= end {
+                    return Err(Error::new(value.span(), "range must not be empty"));
+                }
+                Ok(InputIndices::Range { start, end })
+            }
             _ => Err(Error::new(
                 value.span(),
                 "this range is not supported here (yet)",
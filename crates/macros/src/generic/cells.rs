@@ -15,6 +15,8 @@ use super::{ast, ast::BoolOrIdent, krate};
 pub struct Cells {
     #[deref]
     entries: BTreeMap<String, Option<u32>>,
+    /// spill region (cell type, start and end index) per cell type
+    spill_regions: BTreeMap<String, (String, CellIndex, CellIndex)>,
     ast: Rc<ast::Architecture>,
 }
 
@@ -31,7 +33,47 @@ impl Cells {
                 return Err(syn::Error::new(def.name.span(), "duplicate cell type name"));
             }
         }
-        Ok(Self { entries, ast })
+        let mut spill_regions = BTreeMap::new();
+        for region in ast.inner.spill.iter().flat_map(|spill| spill.value.iter()) {
+            if !entries.contains_key(&region.from.to_string()) {
+                return Err(Error::new(region.from.span(), "no such cell type"));
+            }
+            let Some(count) = entries.get(&region.to.to_string()) else {
+                return Err(Error::new(region.to.span(), "no such cell type"));
+            };
+            let (start, end) = match &region.range {
+                ast::Range::Single { idx, .. } => {
+                    let idx = idx.base10_parse()?;
+                    (idx, idx + 1)
+                }
+                ast::Range::LeftOpen { end, .. } => (0, end.base10_parse()?),
+                ast::Range::Bounded { start, end, .. } => {
+                    (start.base10_parse()?, end.base10_parse()?)
+                }
+                ast::Range::RightOpen { start, .. } => match count {
+                    Some(count) => (start.base10_parse()?, *count),
+                    None => {
+                        return Err(Error::new(
+                            region.range.span(),
+                            "spill region of a cell type with infinite cells has to be bounded",
+                        ));
+                    }
+                },
+            };
+            if start >= end || count.is_some_and(|count| end > count) {
+                return Err(Error::new(region.range.span(), "invalid spill region"));
+            }
+            let prev =
+                spill_regions.insert(region.from.to_string(), (region.to.to_string(), start, end));
+            if prev.is_some() {
+                return Err(Error::new(region.from.span(), "duplicate spill region"));
+            }
+        }
+        Ok(Self {
+            entries,
+            spill_regions,
+            ast,
+        })
     }
 
     pub fn new_operand_types(&self, ast: &ast::OperandPats) -> Result<Pats<OperandPat<CellType>>> {
@@ -115,6 +157,22 @@ impl ToTokens for Cells {
         let name = cell_type_enum_name(&self.ast.name);
         let vis = &self.ast.vis;
         let krate = krate();
+        // the default implementation designates no spill regions
+        let spill_region = (!self.spill_regions.is_empty()).then(|| {
+            let regions = self.spill_regions.iter().map(|(from, (to, start, end))| {
+                let from = format_ident!("{from}");
+                let to = format_ident!("{to}");
+                quote!(Self::#from => Some((Self::#to, #start..#end)),)
+            });
+            quote! {
+                fn spill_region(self) -> Option<(Self, std::ops::Range<#krate::CellIndex>)> {
+                    match self {
+                        #(#regions)*
+                        _ => None,
+                    }
+                }
+            }
+        });
         tokens.extend(quote! {
             #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
             #vis enum #name {
@@ -135,6 +193,7 @@ impl ToTokens for Cells {
                         #(Self::#name_idents => #name_strs),*
                     }.into()
                 }
+                #spill_region
             }
             impl std::fmt::Display for #name {
                 fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -58,7 +58,16 @@ impl InstructionTypes {
                     "specifiying a range here is not (yet) supported",
                 ));
             }
-            let function = (&instruction.function).try_into()?;
+            let mut function: Function = (&instruction.function).try_into()?;
+            if let Some(tie_breaker) = &instruction.tie_breaker {
+                function.tie_breaker = Some(tie_breaker_index(tie_breaker, function, &input)?);
+            }
+            let outputs = Outputs::new(operands, &instruction.output)?;
+            let secondary = instruction
+                .secondary
+                .as_ref()
+                .map(|secondary| secondary_function(secondary, &outputs))
+                .transpose()?;
             entry.insert(InstructionType {
                 id: id as u8,
                 name: instruction.name.to_string().into(),
@@ -67,7 +76,9 @@ impl InstructionTypes {
                 input_inverted,
                 input_range: Range { start: 0 },
                 function,
-                outputs: Outputs::new(operands, &instruction.output)?.0,
+                outputs: outputs.0,
+                secondary,
+                requires_disjoint_io: instruction.disjoint_io.is_some(),
             });
         }
         Ok(Self(result))
@@ -99,6 +110,8 @@ impl ToTokens for InstructionTypeValue<'_> {
             input_inverted,
             function,
             outputs,
+            secondary,
+            requires_disjoint_io,
         } = &self.0;
         let (input, input_override, input_inverted, function, range, outputs) = (
             TuplesDefValue(input),
@@ -108,6 +121,13 @@ impl ToTokens for InstructionTypeValue<'_> {
             RangeValue(*input_range),
             Outputs(outputs.clone()),
         );
+        let secondary = match secondary {
+            Some(secondary) => {
+                let secondary = FunctionValue(*secondary);
+                quote!(Some(#secondary))
+            }
+            None => quote!(None),
+        };
         let krate = krate();
         tokens.extend(quote! {
             #krate::InstructionType {
@@ -119,11 +139,60 @@ impl ToTokens for InstructionTypeValue<'_> {
                 input_range: #range,
                 function: #function,
                 outputs: #outputs,
+                secondary: #secondary,
+                requires_disjoint_io: #requires_disjoint_io,
             }
         });
     }
 }
 
+/// Checks that the tie-breaker refers to an input of a majority with an even arity.
+fn tie_breaker_index(
+    tie_breaker: &ast::InstructionTieBreaker,
+    function: Function,
+    input: &TuplesDef<CellPat<CellType>>,
+) -> Result<u8> {
+    let span = tie_breaker.kw.span;
+    if function.gate != Gate::Maj {
+        return Err(Error::new(span, "only majorities can have a tie-breaker"));
+    }
+    let Some(arity) = input.arity().filter(|arity| arity.is_multiple_of(2)) else {
+        return Err(Error::new(
+            span,
+            "a tie-breaker requires an even number of inputs",
+        ));
+    };
+    let index = tie_breaker.input.base10_parse::<u8>()?;
+    if usize::from(index) >= arity {
+        return Err(Error::new(
+            tie_breaker.input.span(),
+            "tie-breaker is not an input",
+        ));
+    }
+    Ok(index)
+}
+
+/// Checks that every output alternative of an instruction with a secondary function consists of
+/// exactly two operands.
+fn secondary_function(
+    secondary: &ast::InstructionSecondary,
+    outputs: &Outputs,
+) -> Result<Function> {
+    if let Some(range) = &secondary.function.forwarded {
+        return Err(Error::new(
+            range.span(),
+            "specifiying a range here is not (yet) supported",
+        ));
+    }
+    if outputs.0.iter().any(|ops| ops.arity() != Some(2)) {
+        return Err(Error::new(
+            secondary.kw.span,
+            "a secondary function requires exactly two outputs",
+        ));
+    }
+    (&secondary.function).try_into()
+}
+
 struct InputIndicesValue<'a>(&'a InputIndices);
 
 impl ToTokens for InputIndicesValue<'_> {
@@ -133,6 +202,9 @@ impl ToTokens for InputIndicesValue<'_> {
             InputIndices::None => quote!(#krate::InputIndices::None),
             InputIndices::All => quote!(#krate::InputIndices::All),
             InputIndices::Index(idx) => quote!(#krate::InputIndices::Index(#idx)),
+            InputIndices::Range { start, end } => {
+                quote!(#krate::InputIndices::Range { start: #start, end: #end })
+            }
         })
     }
 }
@@ -144,6 +216,7 @@ impl TryFrom<&ast::Function> for Function {
         Ok(Function {
             gate: Gate::try_from(&value.gate)?,
             inverted: value.inverted.is_some(),
+            tie_breaker: None,
         })
     }
 }
@@ -153,12 +226,21 @@ struct FunctionValue(Function);
 impl ToTokens for FunctionValue {
     fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
         let krate = krate();
-        let Function { inverted, gate } = self.0;
+        let Function {
+            inverted,
+            gate,
+            tie_breaker,
+        } = self.0;
         let gate = GateValue(gate);
+        let tie_breaker = match tie_breaker {
+            Some(tie_breaker) => quote!(Some(#tie_breaker)),
+            None => quote!(None),
+        };
         tokens.extend(quote! {
             #krate::Function {
                 gate: #gate,
                 inverted: #inverted,
+                tie_breaker: #tie_breaker,
             }
         });
     }
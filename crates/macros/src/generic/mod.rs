@@ -53,6 +53,16 @@ impl ToTokens for Architecture {
             instr_ids.extend(quote! { pub const #ident: u8 = #id; });
         }
 
+        // instruction ids are assigned in order of definition, see InstructionTypes::new
+        let mut costs = TokenStream::new();
+        for (id, instr) in self.ast.inner.instructions.value.iter().enumerate() {
+            if let Some(cost) = &instr.cost {
+                let id = id as u8;
+                let value = &cost.value;
+                costs.extend(quote! { #id => f64::from(#value), });
+            }
+        }
+
         tokens.extend(quote! {
             #cells
 
@@ -67,6 +77,17 @@ impl ToTokens for Architecture {
                     #krate::Architecture::new(Self::instructions())
                 }
             }
+
+            impl #krate::InstructionCosts for #name {
+                type CellType = #ct;
+
+                fn instruction_cost(id: u8) -> f64 {
+                    match id {
+                        #costs
+                        _ => 1.0,
+                    }
+                }
+            }
         });
     }
 }
@@ -16,6 +16,7 @@ use self::extraction::CompilingCostFunction;
 use crate::opt_extractor::OptExtractor;
 use eggmock::egg::{EGraph, Rewrite, Runner, rewrite};
 use eggmock::{EggExt, Mig, MigLanguage, Network, NetworkReceiver, Receiver, ReceiverFFI};
+use lime_generic::verbosity::Verbosity;
 use program::*;
 use rows::*;
 
@@ -155,10 +156,10 @@ fn compiling_receiver<'a>(
             let t_runner = std::time::Instant::now();
             let runner = Runner::default().with_egraph(graph).run(rules);
             let t_runner = t_runner.elapsed().as_millis();
-            if settings.verbose {
-                println!("== Runner Report");
-                runner.print_report();
-            }
+            settings.verbosity.log(
+                Verbosity::TRACE,
+                format_args!("== Runner Report\n{}", runner.report()),
+            );
             graph = runner.egraph;
             t_runner
         } else {
@@ -178,12 +179,9 @@ fn compiling_receiver<'a>(
         let start_time = Instant::now();
         let program = compile(architecture, &network).expect("network should be compilable");
         let t_compiler = start_time.elapsed().as_millis();
-        if settings.print_program || settings.verbose {
-            if settings.verbose {
-                println!("== Program")
-            }
-            println!("{program}");
-        }
+        settings
+            .verbosity
+            .log(Verbosity::PROGRAM, format_args!("== Program\n{program}"));
 
         let output = CompilerOutput {
             graph,
@@ -191,12 +189,12 @@ fn compiling_receiver<'a>(
             program,
         };
         let program_string = output.borrow_program().to_string();
-        if settings.verbose {
-            println!("== Timings");
-            println!("t_runner: {t_runner}ms");
-            println!("t_extractor: {t_extractor}ms");
-            println!("t_compiler: {t_compiler}ms");
-        }
+        settings.verbosity.log(
+            Verbosity::SUMMARY,
+            format_args!(
+                "== Timings\nt_runner: {t_runner}ms\nt_extractor: {t_extractor}ms\nt_compiler: {t_compiler}ms"
+            ),
+        );
         CompilingReceiverResult {
             output,
             t_runner,
@@ -210,8 +208,7 @@ fn compiling_receiver<'a>(
 #[derive(Debug, Copy, Clone)]
 #[repr(C)]
 struct CompilerSettings {
-    print_program: bool,
-    verbose: bool,
+    verbosity: Verbosity,
     rewrite: bool,
 }
 
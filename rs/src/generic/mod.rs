@@ -2,12 +2,10 @@ use eggmock::ReceiverFFI;
 use lime_generic::{
     CompilerSettings, CompilerStatistics,
     CompilerStatisticsFfi,
-    copy::placeholder::CellOrVar,
-    cost::{Cost, EqualCosts, OperationCost},
-    definitions::{Ambit, AmbitCellType, FELIX, FELIXCellType, IMPLY, PLiM, SIMDRAM},
+    cost::{AmbitCost, DeclaredCosts, EqualCosts},
+    definitions::{Ambit, FELIX, IMPLY, PLiM, SIMDRAM},
     generic_compiler_entrypoint, generic_compiler_with_program,
     map_result_to_ffi,
-    lime_generic_def::Instruction,
 };
 
 #[unsafe(no_mangle)]
@@ -40,22 +38,6 @@ pub extern "C" fn gp_compile_ambit_with_program<'a>(
     ReceiverFFI::new(recv)
 }
 
-#[derive(Clone)]
-struct AmbitCost;
-
-impl OperationCost<AmbitCellType> for AmbitCost {
-    fn cost<I: Into<CellOrVar<AmbitCellType>>>(
-        &self,
-        instruction: &Instruction<I, AmbitCellType>,
-    ) -> Cost {
-        if instruction.outputs.is_empty() {
-            (2.0 / 3.0).into()
-        } else {
-            1.into()
-        }
-    }
-}
-
 #[unsafe(no_mangle)]
 pub extern "C" fn gp_compile_plim<'a>(
     settings: CompilerSettings,
@@ -81,21 +63,10 @@ pub extern "C" fn gp_compile_felix<'a>(
     settings: CompilerSettings,
 ) -> ReceiverFFI<'a, CompilerStatistics> {
     let arch = FELIX::new();
-    ReceiverFFI::new(generic_compiler_entrypoint(arch, FELIXCost, settings, true))
-}
-
-#[derive(Clone)]
-struct FELIXCost;
-
-impl OperationCost<FELIXCellType> for FELIXCost {
-    fn cost<I: Into<CellOrVar<FELIXCellType>>>(
-        &self,
-        instruction: &Instruction<I, FELIXCellType>,
-    ) -> Cost {
-        if instruction.typ.id == FELIX::XOR_INSTRUCTION_ID {
-            (1.5).into()
-        } else {
-            1.into()
-        }
-    }
+    ReceiverFFI::new(generic_compiler_entrypoint(
+        arch,
+        DeclaredCosts::<FELIX>::default(),
+        settings,
+        true,
+    ))
 }
@@ -29,20 +29,19 @@ extern "C"
 
   struct ambit_compiler_settings
   {
-    bool print_program;
-    bool verbose;
+    // 0 = silent, 1 = summary, 2 = program, 3 = trace
+    uint8_t verbosity = 0;
     bool preoptimize = true;
     bool rewrite = true;
   };
 
   struct ambit_compiler_settings_ffi
   {
-    bool print_program;
-    bool verbose;
+    uint8_t verbosity = 0;
     bool rewrite = true;
 
     ambit_compiler_settings_ffi( ambit_compiler_settings s )
-        : print_program( s.print_program ), verbose( s.verbose ), rewrite( s.rewrite ) {}
+        : verbosity( s.verbosity ), rewrite( s.rewrite ) {}
   };
 
   void ambit_free_program_string(char* ptr);
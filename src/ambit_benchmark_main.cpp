@@ -32,8 +32,7 @@ int main( int const argc, char** argv )
   auto const t_opt = duration_cast<milliseconds>( system_clock::now() - opt_begin ).count();
 
   auto constexpr settings = ambit_compiler_settings{
-      .print_program = false,
-      .verbose = false,
+      .verbosity = 0,
   };
 
   const auto [egraph_classes, egraph_nodes, egraph_size,
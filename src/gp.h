@@ -16,7 +16,15 @@ extern "C"
     uint64_t n_nodes_post_trim;
     uint64_t t_extractor;
     double rebuilt_ntk_cost;
+    uint64_t extraction_none_count;
+    uint64_t extraction_total_classes;
+    uint64_t extracted_depth;
   };
+  // `cost` of the `compiler_statistics` of a malformed network
+  constexpr double invalid_network_cost = -1.0;
+  // `cost` of the `compiler_statistics` of a network the compiler failed to compile
+  constexpr double compilation_failed_cost = -2.0;
+
   struct compiler_statistics
   {
     rewriting_statistics rewrite;
@@ -28,6 +36,7 @@ extern "C"
     bool validation_success;
 
     const char* program_str = nullptr;
+    const char* usage_str = nullptr;
   };
   enum class rewriting_strategy
   {
@@ -36,6 +45,7 @@ extern "C"
     compiling,
     compiling_memusage,
     greedy_estimate,
+    min_depth,
   };
   enum class compilation_mode
   {
@@ -46,14 +56,36 @@ extern "C"
   {
     all,
     mig_based_compiler,
+    minimal_pressure,
+    random,
+  };
+  // `and` and `xor` are reserved in C++, hence the trailing underscores
+  struct gate_function_biases
+  {
+    double and_ = 1.0;
+    double maj = 1.0;
+    double xor_ = 1.0;
   };
   struct compiler_settings
   {
     rewriting_strategy rewriting;
     uint64_t rewriting_size_factor;
+    // may be left zero-initialized, validation then fails without calling it
     eggmock::receiver_ffi<bool> validator;
     compilation_mode mode;
     candidate_selection_mode candidate_selection;
+    uint64_t tied_optimal_limit = 0;
+    uint64_t extraction_compile_threshold = 0;
+    bool trace_search = false;
+    double spill_warning_fraction = 1.0;
+    bool prematerialize_constants = false;
+    const char* dump_extracted_network = nullptr;
+    uint64_t seed = 0;
+    // 0 = silent, 1 = summary, 2 = program, 3 = trace
+    uint8_t verbosity = 0;
+    gate_function_biases gate_biases;
+    bool profile = false;
+    double cell_reuse_bias = 0.0;
   };
   void gp_free_program_string(char* ptr);
 }
@@ -54,19 +54,21 @@ int main( int argc, char** argv )
   //  <benchmark>
   //  <arch:                imply / plim / felix / ambit>
   //  <mode:                greedy / exhaustive>
-  //  <candidate selection: all / plim_compiler>
-  //  <rewriting mode:      none / compiling / lp / greedy>
+  //  <candidate selection: all / plim_compiler / minimal_pressure / random>
+  //  <rewriting mode:      none / compiling / lp / greedy / min_depth>
   //  <rewriting size factor>
+  //  [verbosity:           0 (default) to 3]
 
-  if ( argc != 7 )
+  if ( argc != 7 && argc != 8 )
   {
     std::cerr << "usage: " << argv[0]
               << "  <benchmark>\n"
               << "  <arch:                imply / plim / felix / ambit>\n"
               << "  <mode:                greedy / exhaustive>\n"
-              << "  <candidate selection: all / plim_compiler>\n"
-              << "  <rewriting mode:      none / compiling / lp / greedy>\n"
-              << "  <rewriting size factor>" << std::endl;
+              << "  <candidate selection: all / plim_compiler / minimal_pressure / random>\n"
+              << "  <rewriting mode:      none / compiling / lp / greedy / min_depth>\n"
+              << "  <rewriting size factor>\n"
+              << "  [verbosity:           0 (default) to 3]" << std::endl;
     return 1;
   }
 
@@ -78,6 +80,10 @@ int main( int argc, char** argv )
   std::string rewriting_size_factor_str = argv[6];
 
   compiler_settings settings;
+  if ( argc == 8 )
+  {
+    settings.verbosity = static_cast<uint8_t>( std::stoul( argv[7] ) );
+  }
   if ( mode == "greedy" )
   {
     settings.mode = compilation_mode::greedy;
@@ -100,6 +106,14 @@ int main( int argc, char** argv )
   {
     settings.candidate_selection = candidate_selection_mode::mig_based_compiler;
   }
+  else if ( candsel == "minimal_pressure" )
+  {
+    settings.candidate_selection = candidate_selection_mode::minimal_pressure;
+  }
+  else if ( candsel == "random" )
+  {
+    settings.candidate_selection = candidate_selection_mode::random;
+  }
   else
   {
     std::cerr << "invalid candidate selection strategy" << std::endl;
@@ -126,6 +140,10 @@ int main( int argc, char** argv )
   {
     settings.rewriting = rewriting_strategy::greedy_estimate;
   }
+  else if ( rewriting == "min_depth" )
+  {
+    settings.rewriting = rewriting_strategy::min_depth;
+  }
   else
   {
     std::cerr << "invalid rewriting strategy" << std::endl;
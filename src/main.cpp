@@ -43,6 +43,7 @@ int main()
                                         .validator = new_validator( in ),
                                         .mode = compilation_mode::exhaustive,
                                         .candidate_selection = candidate_selection_mode::all,
+                                        .verbosity = 2,
                                     } ) ) );
   // if (result.program_str) {
   //   program_str = ProgramStringGP(const_cast<char*>(result.program_str));